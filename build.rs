@@ -0,0 +1,15 @@
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let proto = "./protos/email.proto";
+
+    tonic_build::configure()
+        .build_client(false)
+        .build_server(true)
+        .compile(&[proto], &["./protos"])?;
+
+    // recompile the protobufs only if the proto file changes.
+    println!("cargo:rerun-if-changed={}", proto);
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+-- Subscriber attributes and uniqueness for bulk imports
+alter table subscriber
+    add column if not exists attributes jsonb not null default '{}';
+
+-- Duplicate subscriptions have to be resolved by hand, which of them to keep
+-- is not ours to guess.
+do $$
+declare
+    duplicates text;
+begin
+    select string_agg(format('%s in group %s', email, group_id), ', ')
+    into duplicates
+    from (
+        select email, group_id
+        from subscriber
+        group by email, group_id
+        having count(*) > 1
+    ) as duplicated;
+
+    if duplicates is not null then
+        raise exception 'subscriber has duplicate subscriptions (%), remove them before running this migration', duplicates;
+    end if;
+end
+$$;
+
+alter table subscriber
+    add constraint subscriber_email_group_id_key unique (email, group_id);
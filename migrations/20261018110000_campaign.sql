@@ -0,0 +1,31 @@
+-- Campaigns persisted with their content, targets and lifecycle
+create table if not exists campaign
+(
+    id           bigint generated by default as identity,
+    name         varchar     not null default '',
+    subject      varchar     not null default '',
+    text_body    text        not null default '',
+    html_body    text        not null default '',
+    sender       varchar     not null default '',
+    status       varchar     not null default 'draft',
+    scheduled_at timestamptz,
+    sent_at      timestamptz,
+    created_at   timestamptz not null default current_timestamp,
+    updated_at   timestamptz not null default current_timestamp
+);
+
+alter table campaign
+    add constraint campaign_id_pk primary key (id);
+
+alter table campaign
+    add constraint campaign_status_check
+        check (status in ('draft', 'scheduled', 'sending', 'sent', 'cancelled'));
+
+create table if not exists campaign_group
+(
+    campaign_id bigint not null references campaign (id) on delete cascade,
+    group_id    bigint not null references subscription_group (id) on delete cascade
+);
+
+alter table campaign_group
+    add constraint campaign_group_pk primary key (campaign_id, group_id);
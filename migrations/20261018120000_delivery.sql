@@ -0,0 +1,27 @@
+-- Per-recipient log of every outbound message
+create table if not exists delivery
+(
+    id            bigint generated by default as identity,
+    message_id    varchar     not null unique,
+    recipient     varchar     not null,
+    subject       varchar     not null default '',
+    campaign_id   bigint      references campaign (id) on delete set null,
+    status        varchar     not null default 'queued',
+    smtp_response varchar     not null default '',
+    sent_at       timestamptz,
+    created_at    timestamptz not null default current_timestamp,
+    updated_at    timestamptz not null default current_timestamp
+);
+
+alter table delivery
+    add constraint delivery_id_pk primary key (id);
+
+alter table delivery
+    add constraint delivery_status_check
+        check (status in ('queued', 'sent', 'failed', 'bounced'));
+
+create index if not exists delivery_recipient_created_at_idx
+    on delivery (recipient, created_at desc);
+
+create index if not exists delivery_campaign_id_idx
+    on delivery (campaign_id);
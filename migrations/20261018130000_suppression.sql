@@ -0,0 +1,17 @@
+-- Addresses that must never be mailed again
+create table if not exists suppression
+(
+    id         bigint generated by default as identity,
+    email      varchar     not null unique,
+    reason     varchar     not null,
+    detail     varchar     not null default '',
+    message_id varchar,
+    created_at timestamptz not null default current_timestamp
+);
+
+alter table suppression
+    add constraint suppression_id_pk primary key (id);
+
+alter table suppression
+    add constraint suppression_reason_check
+        check (reason in ('hard_bounce', 'complaint'));
@@ -0,0 +1,3 @@
+-- Token embedded in the per-message VERP return path
+alter table delivery
+    add column if not exists verp_token varchar unique;
@@ -0,0 +1,20 @@
+-- Opens and clicks recorded by the tracking endpoint
+create table if not exists tracking_event
+(
+    id          bigint generated by default as identity,
+    delivery_id bigint      not null references delivery (id) on delete cascade,
+    kind        varchar     not null,
+    url         varchar,
+    user_agent  varchar     not null default '',
+    created_at  timestamptz not null default current_timestamp
+);
+
+alter table tracking_event
+    add constraint tracking_event_id_pk primary key (id);
+
+alter table tracking_event
+    add constraint tracking_event_kind_check
+        check (kind in ('open', 'click'));
+
+create index if not exists tracking_event_delivery_id_idx
+    on tracking_event (delivery_id);
@@ -0,0 +1,23 @@
+-- Identities that groups, campaigns and single sends can send as
+create table if not exists sender_identity
+(
+    id            bigint generated by default as identity,
+    name          varchar     not null unique,
+    display_name  varchar     not null default '',
+    address       varchar     not null,
+    reply_to      varchar,
+    smtp_username varchar,
+    smtp_password varchar,
+    created_at    timestamptz not null default current_timestamp,
+    updated_at    timestamptz not null default current_timestamp
+);
+
+alter table sender_identity
+    add constraint sender_identity_id_pk primary key (id);
+
+alter table subscription_group
+    add column if not exists default_identity_id bigint
+        references sender_identity (id) on delete set null;
+
+alter table campaign
+    add column if not exists identity varchar not null default '';
@@ -0,0 +1,20 @@
+-- Per-group footer overrides
+alter table subscription_group
+    add column if not exists footer_text text,
+    add column if not exists footer_html text;
+
+-- Memberships given up through a footer unsubscribe link
+create table if not exists unsubscription
+(
+    id          bigint generated by default as identity,
+    email       varchar     not null,
+    group_id    bigint      not null references subscription_group (id) on delete cascade,
+    delivery_id bigint      references delivery (id) on delete set null,
+    created_at  timestamptz not null default current_timestamp
+);
+
+alter table unsubscription
+    add constraint unsubscription_id_pk primary key (id);
+
+create index if not exists unsubscription_email_idx
+    on unsubscription (lower(email));
@@ -0,0 +1,22 @@
+-- Named templates with one variant per locale
+create table if not exists email_template
+(
+    id         bigint generated by default as identity,
+    name       varchar     not null,
+    locale     varchar     not null,
+    subject    varchar     not null default '',
+    text_body  text        not null default '',
+    html_body  text        not null default '',
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+alter table email_template
+    add constraint email_template_id_pk primary key (id);
+
+alter table email_template
+    add constraint email_template_name_locale_key unique (name, locale);
+
+alter table campaign
+    add column if not exists template varchar not null default '',
+    add column if not exists locale   varchar not null default '';
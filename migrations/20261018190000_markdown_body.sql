@@ -0,0 +1,3 @@
+-- Campaigns written in Markdown keep their source for later edits
+alter table campaign
+    add column if not exists markdown_body text not null default '';
@@ -0,0 +1,12 @@
+-- Internal reviewers who receive test sends of a group's blasts
+create table if not exists group_seed
+(
+    id         bigint generated by default as identity,
+    group_id   bigint      not null references subscription_group (id) on delete cascade,
+    email      varchar     not null,
+    created_at timestamptz not null default current_timestamp,
+    unique (group_id, email)
+);
+
+alter table group_seed
+    add constraint group_seed_id_pk primary key (id);
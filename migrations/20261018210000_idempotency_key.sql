@@ -0,0 +1,20 @@
+-- Outcomes of send and blast requests, replayed when a client retries with
+-- the same key
+create table if not exists idempotency_key
+(
+    id          bigint generated by default as identity,
+    operation   varchar     not null,
+    key         varchar     not null,
+    fingerprint varchar     not null,
+    response    varchar,
+    error       varchar,
+    created_at  timestamptz not null default current_timestamp,
+    expires_at  timestamptz not null,
+    unique (operation, key)
+);
+
+alter table idempotency_key
+    add constraint idempotency_key_id_pk primary key (id);
+
+create index if not exists idempotency_key_expires_at_idx
+    on idempotency_key (expires_at);
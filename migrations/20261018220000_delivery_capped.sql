@@ -0,0 +1,7 @@
+-- Blast recipients skipped because they reached a frequency cap
+alter table delivery
+    drop constraint if exists delivery_status_check;
+
+alter table delivery
+    add constraint delivery_status_check
+        check (status in ('queued', 'sent', 'failed', 'bounced', 'capped'));
@@ -0,0 +1,10 @@
+-- Blast deliveries held back until the recipient's local send time or the
+-- end of quiet hours
+alter table delivery
+    add column if not exists send_after timestamptz;
+
+create index if not exists delivery_status_send_after_idx
+    on delivery (status, send_after);
+
+alter table campaign
+    add column if not exists local_send_time varchar not null default '';
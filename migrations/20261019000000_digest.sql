@@ -0,0 +1,42 @@
+-- Notifications batched into one templated email per recipient and digest
+-- type
+create table if not exists digest_type
+(
+    id         bigint generated by default as identity,
+    name       varchar     not null unique,
+    cadence    varchar     not null default 'daily',
+    template   varchar     not null,
+    created_at timestamptz not null default current_timestamp,
+    updated_at timestamptz not null default current_timestamp
+);
+
+alter table digest_type
+    add constraint digest_type_id_pk primary key (id);
+
+alter table digest_type
+    add constraint digest_type_cadence_check
+        check (cadence in ('hourly', 'daily'));
+
+create table if not exists digest_item
+(
+    id             bigint generated by default as identity,
+    digest_type_id bigint      not null references digest_type (id) on delete cascade,
+    recipient      varchar     not null,
+    title          varchar     not null,
+    body           text        not null default '',
+    status         varchar     not null default 'pending',
+    message_id     varchar,
+    created_at     timestamptz not null default current_timestamp,
+    updated_at     timestamptz not null default current_timestamp
+);
+
+alter table digest_item
+    add constraint digest_item_id_pk primary key (id);
+
+alter table digest_item
+    add constraint digest_item_status_check
+        check (status in ('pending', 'sent', 'failed'));
+
+create index if not exists digest_item_pending_idx
+    on digest_item (digest_type_id, recipient, created_at)
+    where status = 'pending';
@@ -0,0 +1,19 @@
+-- Transactional or marketing class of every campaign and delivery
+alter table campaign
+    add column if not exists message_class varchar not null default 'marketing';
+
+alter table campaign
+    add constraint campaign_message_class_check
+        check (message_class in ('transactional', 'marketing'));
+
+alter table delivery
+    add column if not exists message_class varchar not null default 'marketing';
+
+-- Deliveries recorded outside of a campaign are taken to be transactional.
+update delivery
+set message_class = 'transactional'
+where campaign_id is null;
+
+alter table delivery
+    add constraint delivery_message_class_check
+        check (message_class in ('transactional', 'marketing'));
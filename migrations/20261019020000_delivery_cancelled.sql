@@ -0,0 +1,7 @@
+-- Queued deliveries of a blast that was cancelled before they were sent
+alter table delivery
+    drop constraint if exists delivery_status_check;
+
+alter table delivery
+    add constraint delivery_status_check
+        check (status in ('queued', 'sent', 'failed', 'bounced', 'capped', 'cancelled'));
@@ -0,0 +1,7 @@
+-- Queued deliveries claimed by the instance sending them
+alter table delivery
+    drop constraint if exists delivery_status_check;
+
+alter table delivery
+    add constraint delivery_status_check
+        check (status in ('queued', 'sending', 'sent', 'failed', 'bounced', 'capped', 'cancelled'));
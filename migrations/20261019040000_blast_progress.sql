@@ -0,0 +1,11 @@
+-- Recipients a campaign blast went out to, and the ones it left out without
+-- capping them
+alter table campaign
+    add column recipient_count bigint;
+
+alter table delivery
+    drop constraint if exists delivery_status_check;
+
+alter table delivery
+    add constraint delivery_status_check
+        check (status in ('queued', 'sending', 'sent', 'failed', 'bounced', 'capped', 'skipped', 'cancelled'));
@@ -0,0 +1,7 @@
+-- Digest items claimed by the instance sending them
+alter table digest_item
+    drop constraint if exists digest_item_status_check;
+
+alter table digest_item
+    add constraint digest_item_status_check
+        check (status in ('pending', 'sending', 'sent', 'failed'));
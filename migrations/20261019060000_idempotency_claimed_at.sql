@@ -0,0 +1,4 @@
+-- When a pending key was claimed, so that a claim left behind by a request
+-- that never finished can be taken over
+alter table idempotency_key
+    add column if not exists claimed_at timestamptz not null default current_timestamp;
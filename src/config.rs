@@ -20,4 +20,68 @@ pub struct AppConfig {
     pub service_email_password: String,
     #[arg(long, env)]
     pub seed: bool,
+    #[arg(long, env)]
+    pub disposable_domains_path: Option<String>,
+    #[arg(long, env, default_value_t = 60)]
+    pub campaign_poll_interval_seconds: u64,
+    #[arg(long, env)]
+    pub bounce_directory: Option<String>,
+    #[arg(long, env, default_value_t = 30)]
+    pub bounce_poll_interval_seconds: u64,
+    #[arg(long, env, default_value_t = 60)]
+    pub digest_poll_interval_seconds: u64,
+    #[arg(long, env)]
+    pub verp_domain: Option<String>,
+    #[arg(long, env, default_value = "bounce")]
+    pub verp_prefix: String,
+    #[arg(long, env)]
+    pub tracking_base_url: Option<String>,
+    #[arg(long, env)]
+    pub tracking_secret: Option<String>,
+    #[arg(long, env, default_value_t = 8081)]
+    pub tracking_port: u32,
+    #[arg(long, env)]
+    pub dkim_selector: Option<String>,
+    #[arg(long, env)]
+    pub dkim_domain: Option<String>,
+    #[arg(long, env)]
+    pub dkim_private_key_path: Option<String>,
+    #[arg(long, env, default_value = "rsa-sha256")]
+    pub dkim_algorithm: String,
+    #[arg(long, env)]
+    pub footer_postal_address: Option<String>,
+    #[arg(long, env)]
+    pub footer_text_template_path: Option<String>,
+    #[arg(long, env)]
+    pub footer_html_template_path: Option<String>,
+    #[arg(long, env, default_value = "en")]
+    pub default_locale: String,
+    #[arg(long, env)]
+    pub markdown_layout_path: Option<String>,
+    #[arg(long, env, default_value = "[TEST] ")]
+    pub test_blast_subject_prefix: String,
+    #[arg(long, env, default_value_t = 86400)]
+    pub idempotency_window_seconds: u64,
+    #[arg(long, env)]
+    pub frequency_cap_daily: Option<i64>,
+    #[arg(long, env)]
+    pub frequency_cap_weekly: Option<i64>,
+    #[arg(long, env, default_value = "UTC")]
+    pub default_timezone: String,
+    #[arg(long, env)]
+    pub quiet_hours_start: Option<String>,
+    #[arg(long, env)]
+    pub quiet_hours_end: Option<String>,
+    #[arg(long, env)]
+    pub transactional_identity: Option<String>,
+    #[arg(long, env)]
+    pub marketing_identity: Option<String>,
+    #[arg(long, env)]
+    pub transactional_rate_limit_per_second: Option<u32>,
+    #[arg(long, env)]
+    pub marketing_rate_limit_per_second: Option<u32>,
+    #[arg(long, env, default_value_t = 8)]
+    pub outbox_workers: usize,
+    #[arg(long, env, default_value_t = 10)]
+    pub low_priority_share_percent: u32,
 }
@@ -1,75 +1,386 @@
-use crate::service::{
-    email::DynEmailServiceTrait, group::DynGroupServiceTrait, subscriber::DynSubscriberServiceTrait,
+use std::{fmt::Debug, future::Future, pin::Pin};
+
+use crate::{
+    repository::{
+        campaign::NewCampaign, delivery::MessageClass, digest::NewDigestItem,
+        group::FooterTemplate, identity::NewSenderIdentity, template::NewTemplate,
+    },
+    service::{
+        campaign::DynCampaignServiceTrait,
+        delivery::DynDeliveryServiceTrait,
+        digest::DynDigestServiceTrait,
+        email::{DynEmailServiceTrait, EmailContent, EmailRecipients},
+        group::DynGroupServiceTrait,
+        idempotency::DynIdempotencyServiceTrait,
+        identity::DynSenderIdentityServiceTrait,
+        outbox::MessagePriority,
+        subscriber::DynSubscriberServiceTrait,
+        template::DynTemplateServiceTrait,
+        tracking::DynTrackingServiceTrait,
+    },
 };
-use tonic::{Request, Response, Status};
+use futures::{Stream, StreamExt, TryStreamExt};
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use sha2::{Digest, Sha256};
+use tonic::{Request, Response, Status, Streaming};
+use tracing::log::error;
 
-use madtofan_microservice_common::email::{
-    email_server::Email, AddGroupRequest, AddSubscriberRequest, BlastEmailRequest, EmailResponse,
-    GetSubscriberGroupsRequest, GetSubscribersRequest, GroupsResponse, RemoveGroupRequest,
-    RemoveSubscriberRequest, SendEmailRequest, SubscribersResponse,
+use crate::proto::email::{
+    email_server::Email, AddGroupRequest, AddSenderIdentityRequest, AddSubscriberRequest,
+    AddSubscribersRequest, BlastEmailRequest, BlastEmailResponse, BlastProgressResponse,
+    BulkSubscribersResponse, CampaignEngagementResponse, CampaignResponse, CampaignsResponse,
+    CancelBlastRequest, CancelCampaignRequest, CopyGroupRequest, CreateCampaignRequest,
+    DeliveriesResponse, DeliveryResponse, DigestTypeResponse, EditCampaignRequest, EmailResponse,
+    EnqueueDigestItemRequest, ExportSubscribersRequest, ExportSubscribersResponse,
+    GetCampaignEngagementRequest, GetMessageStatusRequest, GetSubscriberGroupsRequest,
+    GetSubscribersRequest, GroupOperationResponse, GroupSeedsResponse, GroupsResponse,
+    ImportSubscribersRequest, ImportSubscribersResponse, ListCampaignsRequest,
+    ListDeliveriesRequest, ListGroupSeedsRequest, ListSenderIdentitiesRequest,
+    ListTemplatesRequest, MergeGroupsRequest, MessageClass as RequestedMessageClass,
+    MessagePriority as RequestedPriority, MoveSubscriberRequest, PreviewCampaignRequest,
+    PreviewCampaignResponse, PreviewEmailRequest, PreviewEmailResponse, RemoveGroupRequest,
+    RemoveSenderIdentityRequest, RemoveSubscriberRequest, RemoveSubscribersRequest,
+    RemoveTemplateRequest, SaveDigestTypeRequest, SaveTemplateRequest, SendCampaignRequest,
+    SendEmailRequest, SendEmailResponse, SenderIdentitiesResponse, SenderIdentityResponse,
+    SetGroupFooterRequest, SetGroupIdentityRequest, SetGroupSeedsRequest, SubscribersResponse,
+    TemplateResponse, TemplatesResponse, TestBlastRequest, TestBlastResponse, WatchBlastRequest,
 };
 
 pub struct RequestHandler {
     subscriber_service: DynSubscriberServiceTrait,
     group_service: DynGroupServiceTrait,
     email_service: DynEmailServiceTrait,
+    campaign_service: DynCampaignServiceTrait,
+    delivery_service: DynDeliveryServiceTrait,
+    tracking_service: DynTrackingServiceTrait,
+    identity_service: DynSenderIdentityServiceTrait,
+    template_service: DynTemplateServiceTrait,
+    idempotency_service: DynIdempotencyServiceTrait,
+    digest_service: DynDigestServiceTrait,
 }
 
+/// Largest CSV an import accepts, the upload is held in memory until it is
+/// parsed.
+pub(crate) const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Metadata a client can send the idempotency key in instead of the request
+/// field.
+const IDEMPOTENCY_KEY_METADATA: &str = "idempotency-key";
+
 impl RequestHandler {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         subscriber_service: DynSubscriberServiceTrait,
         group_service: DynGroupServiceTrait,
         email_service: DynEmailServiceTrait,
+        campaign_service: DynCampaignServiceTrait,
+        delivery_service: DynDeliveryServiceTrait,
+        tracking_service: DynTrackingServiceTrait,
+        identity_service: DynSenderIdentityServiceTrait,
+        template_service: DynTemplateServiceTrait,
+        idempotency_service: DynIdempotencyServiceTrait,
+        digest_service: DynDigestServiceTrait,
     ) -> Self {
         Self {
             subscriber_service,
             group_service,
             email_service,
+            campaign_service,
+            delivery_service,
+            tracking_service,
+            identity_service,
+            template_service,
+            idempotency_service,
+            digest_service,
+        }
+    }
+
+    /// Group and CSV of an import upload, refused as soon as it grows past
+    /// `MAX_IMPORT_BYTES`.
+    pub(crate) async fn collect_import(
+        mut chunks: impl Stream<Item = Result<ImportSubscribersRequest, Status>> + Unpin,
+    ) -> Result<(String, Vec<u8>), Status> {
+        let mut group = String::new();
+        let mut csv = Vec::new();
+        while let Some(chunk) = chunks.try_next().await? {
+            if group.is_empty() {
+                group = chunk.group;
+            }
+            if csv.len() + chunk.chunk.len() > MAX_IMPORT_BYTES {
+                return Err(Status::invalid_argument(format!(
+                    "import must not be larger than {} bytes",
+                    MAX_IMPORT_BYTES
+                )));
+            }
+            csv.extend_from_slice(&chunk.chunk);
         }
+
+        Ok((group, csv))
+    }
+
+    /// Key from the request field, or else from the request metadata.
+    fn idempotency_key<T>(request: &Request<T>, field: impl Fn(&T) -> &str) -> Option<String> {
+        Some(field(request.get_ref()))
+            .filter(|key| !key.is_empty())
+            .or_else(|| {
+                request
+                    .metadata()
+                    .get(IDEMPOTENCY_KEY_METADATA)
+                    .and_then(|key| key.to_str().ok())
+                    .filter(|key| !key.is_empty())
+            })
+            .map(str::to_string)
+    }
+
+    /// Class a request asked for, or `default` when it left it unspecified.
+    fn message_class(requested: RequestedMessageClass, default: MessageClass) -> MessageClass {
+        match requested {
+            RequestedMessageClass::Unspecified => default,
+            RequestedMessageClass::Transactional => MessageClass::Transactional,
+            RequestedMessageClass::Marketing => MessageClass::Marketing,
+        }
+    }
+
+    /// Priority a request asked for, if any.
+    fn priority(requested: RequestedPriority) -> Option<MessagePriority> {
+        match requested {
+            RequestedPriority::Unspecified => None,
+            RequestedPriority::High => Some(MessagePriority::High),
+            RequestedPriority::Normal => Some(MessagePriority::Normal),
+            RequestedPriority::Low => Some(MessagePriority::Low),
+        }
+    }
+
+    fn request_fingerprint(request: impl Debug) -> String {
+        format!("{:x}", Sha256::digest(format!("{:?}", request)))
+    }
+
+    /// Runs the request once per idempotency key, repeats get the stored
+    /// outcome of the first run.
+    async fn idempotent(
+        &self,
+        operation: &str,
+        key: Option<String>,
+        fingerprint: String,
+        run: impl Future<Output = ServiceResult<String>>,
+    ) -> ServiceResult<String> {
+        let key = match key {
+            Some(key) => key,
+            None => return run.await,
+        };
+        if let Some(response) = self
+            .idempotency_service
+            .begin(operation.to_string(), key.clone(), fingerprint)
+            .await?
+        {
+            return Ok(response);
+        }
+
+        let outcome = run.await;
+        // The client still gets the outcome, the key just stays pending until
+        // its claim can be taken over.
+        if let Err(err) = self
+            .idempotency_service
+            .finish(operation.to_string(), key.clone(), &outcome)
+            .await
+        {
+            error!("failed to store the outcome of {:?}: {:?}", &key, err);
+        }
+
+        outcome
+    }
+
+    /// Subscriber whose merge fields a message is rendered with, the chosen
+    /// one or else the first one in the group.
+    async fn sample_subscriber(&self, group: &str, email: String) -> Result<String, Status> {
+        if !email.is_empty() {
+            return Ok(email);
+        }
+
+        self.subscriber_service
+            .list_subs_by_group(group.to_string(), Some(0), Some(1))
+            .await?
+            .subscribers
+            .into_iter()
+            .next()
+            .map(|subscriber| subscriber.email)
+            .ok_or_else(|| Status::invalid_argument("group has no subscribers to sample"))
     }
 }
 
 #[tonic::async_trait]
 impl Email for RequestHandler {
+    type ExportSubscribersStream =
+        Pin<Box<dyn Stream<Item = Result<ExportSubscribersResponse, Status>> + Send>>;
+    type WatchBlastStream =
+        Pin<Box<dyn Stream<Item = Result<BlastProgressResponse, Status>> + Send>>;
+
     async fn send_email(
         &self,
         request: Request<SendEmailRequest>,
-    ) -> Result<Response<EmailResponse>, Status> {
-        let req = request.into_inner();
+    ) -> Result<Response<SendEmailResponse>, Status> {
+        let idempotency_key = Self::idempotency_key(&request, |req| req.idempotency_key.as_str());
+        let mut req = request.into_inner();
+        req.idempotency_key.clear();
 
-        self.email_service
-            .send_email(req.email, req.title, req.body)
+        let mut headers = std::mem::take(&mut req.headers)
+            .into_iter()
+            .collect::<Vec<(String, String)>>();
+        headers.sort();
+        let fingerprint = Self::request_fingerprint((&req, &headers));
+        // Mail sent to named people is transactional unless told otherwise.
+        let message_class = Self::message_class(req.message_class(), MessageClass::Transactional);
+        let priority = Self::priority(req.priority());
+
+        let send = self.email_service.send_email(
+            EmailRecipients {
+                to: Some(req.email)
+                    .filter(|email| !email.is_empty())
+                    .into_iter()
+                    .chain(req.to)
+                    .collect(),
+                cc: req.cc,
+                bcc: req.bcc,
+            },
+            EmailContent {
+                subject: req.title,
+                text_body: req.body,
+                markdown_body: Some(req.markdown_body).filter(|body| !body.is_empty()),
+                identity: Some(req.identity).filter(|identity| !identity.is_empty()),
+                reply_to: Some(req.reply_to).filter(|reply_to| !reply_to.is_empty()),
+                headers,
+                without_footer: req.without_footer,
+                template: Some(req.template).filter(|template| !template.is_empty()),
+                locale: Some(req.locale).filter(|locale| !locale.is_empty()),
+                message_class,
+                priority,
+                ..Default::default()
+            },
+        );
+        let message_id = self
+            .idempotent("send_email", idempotency_key, fingerprint, send)
             .await?;
 
-        Ok(Response::new(EmailResponse {
+        Ok(Response::new(SendEmailResponse {
             message: String::from("Success sending email!"),
+            message_id,
         }))
     }
 
     async fn blast_email(
         &self,
         request: Request<BlastEmailRequest>,
-    ) -> Result<Response<EmailResponse>, Status> {
-        let req = request.into_inner();
+    ) -> Result<Response<BlastEmailResponse>, Status> {
+        let idempotency_key = Self::idempotency_key(&request, |req| req.idempotency_key.as_str());
+        let mut req = request.into_inner();
+        req.idempotency_key.clear();
+        let fingerprint = Self::request_fingerprint(&req);
+        let message_class = Self::message_class(req.message_class(), MessageClass::Marketing);
 
-        let subscribers = self
-            .subscriber_service
-            .list_subs_by_group(req.group, None, None)
+        let blast = async move {
+            let campaign = self
+                .campaign_service
+                .create_campaign(
+                    NewCampaign {
+                        name: req.title.clone(),
+                        subject: req.title,
+                        text_body: req.body,
+                        html_body: String::new(),
+                        markdown_body: req.markdown_body,
+                        sender: String::new(),
+                        identity: req.identity,
+                        template: req.template,
+                        locale: req.locale,
+                        local_send_time: req.local_send_time,
+                        message_class,
+                    },
+                    vec![req.group],
+                )
+                .await?;
+
+            // The blast goes on in the background, to be followed with
+            // WatchBlast.
+            self.campaign_service.start_campaign(campaign.id).await?;
+
+            Ok::<_, ServiceError>(campaign.id.to_string())
+        };
+        let id = self
+            .idempotent("blast_email", idempotency_key, fingerprint, blast)
             .await?
-            .subscribers;
+            .parse::<i64>()
+            .map_err(|_| {
+                ServiceError::InternalServerErrorWithContext(String::from(
+                    "stored blast outcome is not a campaign id",
+                ))
+            })?;
 
-        let addresses = subscribers
-            .into_iter()
-            .map(|subscriber| subscriber.email)
-            .collect::<Vec<String>>();
+        Ok(Response::new(BlastEmailResponse {
+            message: String::from("Blasting email started!"),
+            id,
+        }))
+    }
+
+    async fn preview_email(
+        &self,
+        request: Request<PreviewEmailRequest>,
+    ) -> Result<Response<PreviewEmailResponse>, Status> {
+        let req = request.into_inner();
 
-        self.email_service
-            .blast_email(addresses, req.title, req.body)
+        let recipient = self.sample_subscriber(&req.group, req.email).await?;
+
+        let preview_response = self
+            .campaign_service
+            .preview_email(
+                NewCampaign {
+                    name: req.title.clone(),
+                    subject: req.title,
+                    text_body: req.body,
+                    html_body: String::new(),
+                    markdown_body: req.markdown_body,
+                    sender: String::new(),
+                    identity: req.identity,
+                    template: req.template,
+                    locale: req.locale,
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec![req.group],
+                recipient,
+            )
             .await?;
 
-        Ok(Response::new(EmailResponse {
-            message: String::from("Success blasting email!"),
-        }))
+        Ok(Response::new(preview_response))
+    }
+
+    async fn test_blast(
+        &self,
+        request: Request<TestBlastRequest>,
+    ) -> Result<Response<TestBlastResponse>, Status> {
+        let req = request.into_inner();
+
+        let sample = self.sample_subscriber(&req.group, req.email).await?;
+        let test_blast_response = self
+            .campaign_service
+            .test_blast(
+                NewCampaign {
+                    name: req.title.clone(),
+                    subject: req.title,
+                    text_body: req.body,
+                    html_body: String::new(),
+                    markdown_body: req.markdown_body,
+                    sender: String::new(),
+                    identity: req.identity,
+                    template: req.template,
+                    locale: req.locale,
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec![req.group],
+                sample,
+            )
+            .await?;
+
+        Ok(Response::new(test_blast_response))
     }
 
     async fn add_subscriber(
@@ -87,6 +398,61 @@ impl Email for RequestHandler {
         }))
     }
 
+    async fn add_subscribers(
+        &self,
+        request: Request<AddSubscribersRequest>,
+    ) -> Result<Response<BulkSubscribersResponse>, Status> {
+        let req = request.into_inner();
+
+        let bulk_response = self
+            .subscriber_service
+            .add_subscribers(req.emails, req.group)
+            .await?;
+
+        Ok(Response::new(bulk_response))
+    }
+
+    async fn import_subscribers(
+        &self,
+        request: Request<Streaming<ImportSubscribersRequest>>,
+    ) -> Result<Response<ImportSubscribersResponse>, Status> {
+        let (group, csv) = Self::collect_import(request.into_inner()).await?;
+
+        let import_response = self
+            .subscriber_service
+            .import_subscribers(group, csv)
+            .await?;
+
+        Ok(Response::new(import_response))
+    }
+
+    async fn export_subscribers(
+        &self,
+        request: Request<ExportSubscribersRequest>,
+    ) -> Result<Response<Self::ExportSubscribersStream>, Status> {
+        let req = request.into_inner();
+        let format = req.format();
+        let segment = req
+            .segment
+            .into_iter()
+            .map(|(key, value)| (key, serde_json::Value::from(value)))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+
+        let rows = self
+            .subscriber_service
+            .export_subscribers(
+                req.group,
+                serde_json::Value::Object(segment),
+                format,
+                req.columns,
+            )
+            .await?
+            .map_ok(|row| ExportSubscribersResponse { row })
+            .map_err(Status::from);
+
+        Ok(Response::new(Box::pin(rows)))
+    }
+
     async fn remove_subscriber(
         &self,
         request: Request<RemoveSubscriberRequest>,
@@ -102,6 +468,20 @@ impl Email for RequestHandler {
         }))
     }
 
+    async fn remove_subscribers(
+        &self,
+        request: Request<RemoveSubscribersRequest>,
+    ) -> Result<Response<BulkSubscribersResponse>, Status> {
+        let req = request.into_inner();
+
+        let bulk_response = self
+            .subscriber_service
+            .remove_subscribers(req.emails, req.group)
+            .await?;
+
+        Ok(Response::new(bulk_response))
+    }
+
     async fn add_group(
         &self,
         request: Request<AddGroupRequest>,
@@ -157,4 +537,425 @@ impl Email for RequestHandler {
 
         Ok(Response::new(group_response))
     }
+
+    async fn copy_group(
+        &self,
+        request: Request<CopyGroupRequest>,
+    ) -> Result<Response<GroupOperationResponse>, Status> {
+        let req = request.into_inner();
+
+        let operation_response = self
+            .group_service
+            .copy_group(req.from_group, req.to_group)
+            .await?;
+
+        Ok(Response::new(operation_response))
+    }
+
+    async fn move_subscriber(
+        &self,
+        request: Request<MoveSubscriberRequest>,
+    ) -> Result<Response<GroupOperationResponse>, Status> {
+        let req = request.into_inner();
+
+        let operation_response = self
+            .group_service
+            .move_subscriber(req.email, req.from_group, req.to_group)
+            .await?;
+
+        Ok(Response::new(operation_response))
+    }
+
+    async fn merge_groups(
+        &self,
+        request: Request<MergeGroupsRequest>,
+    ) -> Result<Response<GroupOperationResponse>, Status> {
+        let req = request.into_inner();
+
+        let operation_response = self
+            .group_service
+            .merge_groups(req.into_group, req.from_group)
+            .await?;
+
+        Ok(Response::new(operation_response))
+    }
+
+    async fn create_campaign(
+        &self,
+        request: Request<CreateCampaignRequest>,
+    ) -> Result<Response<CampaignResponse>, Status> {
+        let req = request.into_inner();
+        let message_class = Self::message_class(req.message_class(), MessageClass::Marketing);
+
+        let campaign_response = self
+            .campaign_service
+            .create_campaign(
+                NewCampaign {
+                    name: req.name,
+                    subject: req.subject,
+                    text_body: req.text_body,
+                    html_body: req.html_body,
+                    markdown_body: req.markdown_body,
+                    sender: req.sender,
+                    identity: req.identity,
+                    template: req.template,
+                    locale: req.locale,
+                    local_send_time: req.local_send_time,
+                    message_class,
+                },
+                req.groups,
+            )
+            .await?;
+
+        Ok(Response::new(campaign_response))
+    }
+
+    async fn edit_campaign(
+        &self,
+        request: Request<EditCampaignRequest>,
+    ) -> Result<Response<CampaignResponse>, Status> {
+        let req = request.into_inner();
+        let message_class = Self::message_class(req.message_class(), MessageClass::Marketing);
+
+        let campaign_response = self
+            .campaign_service
+            .edit_campaign(
+                req.id,
+                NewCampaign {
+                    name: req.name,
+                    subject: req.subject,
+                    text_body: req.text_body,
+                    html_body: req.html_body,
+                    markdown_body: req.markdown_body,
+                    sender: req.sender,
+                    identity: req.identity,
+                    template: req.template,
+                    locale: req.locale,
+                    local_send_time: req.local_send_time,
+                    message_class,
+                },
+                req.groups,
+            )
+            .await?;
+
+        Ok(Response::new(campaign_response))
+    }
+
+    async fn preview_campaign(
+        &self,
+        request: Request<PreviewCampaignRequest>,
+    ) -> Result<Response<PreviewCampaignResponse>, Status> {
+        let req = request.into_inner();
+
+        let preview_response = self.campaign_service.preview_campaign(req.id).await?;
+
+        Ok(Response::new(preview_response))
+    }
+
+    async fn send_campaign(
+        &self,
+        request: Request<SendCampaignRequest>,
+    ) -> Result<Response<CampaignResponse>, Status> {
+        let req = request.into_inner();
+
+        let campaign_response = self
+            .campaign_service
+            .send_campaign(req.id, Some(req.scheduled_at).filter(|at| *at > 0))
+            .await?;
+
+        Ok(Response::new(campaign_response))
+    }
+
+    async fn cancel_campaign(
+        &self,
+        request: Request<CancelCampaignRequest>,
+    ) -> Result<Response<CampaignResponse>, Status> {
+        let req = request.into_inner();
+
+        let campaign_response = self.campaign_service.cancel_campaign(req.id).await?;
+
+        Ok(Response::new(campaign_response))
+    }
+
+    async fn watch_blast(
+        &self,
+        request: Request<WatchBlastRequest>,
+    ) -> Result<Response<Self::WatchBlastStream>, Status> {
+        let req = request.into_inner();
+
+        let progress = self
+            .campaign_service
+            .watch_blast(req.id)
+            .await?
+            .map(Ok::<_, Status>);
+
+        Ok(Response::new(Box::pin(progress)))
+    }
+
+    async fn cancel_blast(
+        &self,
+        request: Request<CancelBlastRequest>,
+    ) -> Result<Response<CampaignResponse>, Status> {
+        let req = request.into_inner();
+
+        let campaign_response = self.campaign_service.cancel_blast(req.id).await?;
+
+        Ok(Response::new(campaign_response))
+    }
+
+    async fn list_campaigns(
+        &self,
+        request: Request<ListCampaignsRequest>,
+    ) -> Result<Response<CampaignsResponse>, Status> {
+        let req = request.into_inner();
+
+        let campaigns_response = self
+            .campaign_service
+            .list_campaigns(Some(req.status), Some(req.offset), Some(req.limit))
+            .await?;
+
+        Ok(Response::new(campaigns_response))
+    }
+
+    async fn get_message_status(
+        &self,
+        request: Request<GetMessageStatusRequest>,
+    ) -> Result<Response<DeliveryResponse>, Status> {
+        let req = request.into_inner();
+
+        let delivery_response = self
+            .delivery_service
+            .get_message_status(req.message_id)
+            .await?;
+
+        Ok(Response::new(delivery_response))
+    }
+
+    async fn list_deliveries(
+        &self,
+        request: Request<ListDeliveriesRequest>,
+    ) -> Result<Response<DeliveriesResponse>, Status> {
+        let req = request.into_inner();
+
+        let deliveries_response = self
+            .delivery_service
+            .list_deliveries(
+                Some(req.recipient).filter(|recipient| !recipient.is_empty()),
+                Some(req.campaign_id).filter(|campaign_id| *campaign_id > 0),
+                Some(req.offset),
+                Some(req.limit),
+            )
+            .await?;
+
+        Ok(Response::new(deliveries_response))
+    }
+
+    async fn get_campaign_engagement(
+        &self,
+        request: Request<GetCampaignEngagementRequest>,
+    ) -> Result<Response<CampaignEngagementResponse>, Status> {
+        let req = request.into_inner();
+
+        let engagement_response = self
+            .tracking_service
+            .get_campaign_engagement(req.campaign_id)
+            .await?;
+
+        Ok(Response::new(engagement_response))
+    }
+
+    async fn add_sender_identity(
+        &self,
+        request: Request<AddSenderIdentityRequest>,
+    ) -> Result<Response<SenderIdentityResponse>, Status> {
+        let req = request.into_inner();
+
+        let identity_response = self
+            .identity_service
+            .add_identity(NewSenderIdentity {
+                name: req.name,
+                display_name: req.display_name,
+                address: req.address,
+                reply_to: Some(req.reply_to).filter(|reply_to| !reply_to.is_empty()),
+                smtp_username: Some(req.smtp_username).filter(|username| !username.is_empty()),
+                smtp_password: Some(req.smtp_password).filter(|password| !password.is_empty()),
+            })
+            .await?;
+
+        Ok(Response::new(identity_response))
+    }
+
+    async fn remove_sender_identity(
+        &self,
+        request: Request<RemoveSenderIdentityRequest>,
+    ) -> Result<Response<EmailResponse>, Status> {
+        let req = request.into_inner();
+
+        self.identity_service.remove_identity(req.name).await?;
+
+        Ok(Response::new(EmailResponse {
+            message: String::from("Successfully removed sender identity!"),
+        }))
+    }
+
+    async fn list_sender_identities(
+        &self,
+        _request: Request<ListSenderIdentitiesRequest>,
+    ) -> Result<Response<SenderIdentitiesResponse>, Status> {
+        let identities_response = self.identity_service.list_identities().await?;
+
+        Ok(Response::new(identities_response))
+    }
+
+    async fn set_group_identity(
+        &self,
+        request: Request<SetGroupIdentityRequest>,
+    ) -> Result<Response<EmailResponse>, Status> {
+        let req = request.into_inner();
+
+        self.identity_service
+            .set_group_identity(
+                req.group,
+                Some(req.identity).filter(|identity| !identity.is_empty()),
+            )
+            .await?;
+
+        Ok(Response::new(EmailResponse {
+            message: String::from("Successfully set group sender identity!"),
+        }))
+    }
+
+    async fn set_group_footer(
+        &self,
+        request: Request<SetGroupFooterRequest>,
+    ) -> Result<Response<EmailResponse>, Status> {
+        let req = request.into_inner();
+
+        let footer = Some(FooterTemplate {
+            text: req.text_template,
+            html: req.html_template,
+        })
+        .filter(|footer| !footer.text.is_empty() || !footer.html.is_empty());
+        self.group_service
+            .set_group_footer(req.group, footer)
+            .await?;
+
+        Ok(Response::new(EmailResponse {
+            message: String::from("Successfully set group footer!"),
+        }))
+    }
+
+    async fn set_group_seeds(
+        &self,
+        request: Request<SetGroupSeedsRequest>,
+    ) -> Result<Response<EmailResponse>, Status> {
+        let req = request.into_inner();
+
+        self.group_service
+            .set_group_seeds(req.group, req.emails)
+            .await?;
+
+        Ok(Response::new(EmailResponse {
+            message: String::from("Successfully set group seeds!"),
+        }))
+    }
+
+    async fn list_group_seeds(
+        &self,
+        request: Request<ListGroupSeedsRequest>,
+    ) -> Result<Response<GroupSeedsResponse>, Status> {
+        let req = request.into_inner();
+
+        let seeds_response = self.group_service.list_group_seeds(req.group).await?;
+
+        Ok(Response::new(seeds_response))
+    }
+
+    async fn save_template(
+        &self,
+        request: Request<SaveTemplateRequest>,
+    ) -> Result<Response<TemplateResponse>, Status> {
+        let req = request.into_inner();
+
+        let template_response = self
+            .template_service
+            .save_template(NewTemplate {
+                name: req.name,
+                locale: req.locale,
+                subject: req.subject,
+                text_body: req.text_body,
+                html_body: req.html_body,
+            })
+            .await?;
+
+        Ok(Response::new(template_response))
+    }
+
+    async fn list_templates(
+        &self,
+        request: Request<ListTemplatesRequest>,
+    ) -> Result<Response<TemplatesResponse>, Status> {
+        let req = request.into_inner();
+
+        let templates_response = self
+            .template_service
+            .list_templates(Some(req.name).filter(|name| !name.is_empty()))
+            .await?;
+
+        Ok(Response::new(templates_response))
+    }
+
+    async fn remove_template(
+        &self,
+        request: Request<RemoveTemplateRequest>,
+    ) -> Result<Response<EmailResponse>, Status> {
+        let req = request.into_inner();
+
+        self.template_service
+            .remove_template(
+                req.name,
+                Some(req.locale).filter(|locale| !locale.is_empty()),
+            )
+            .await?;
+
+        Ok(Response::new(EmailResponse {
+            message: String::from("Successfully removed template!"),
+        }))
+    }
+
+    async fn save_digest_type(
+        &self,
+        request: Request<SaveDigestTypeRequest>,
+    ) -> Result<Response<DigestTypeResponse>, Status> {
+        let req = request.into_inner();
+
+        let digest_type_response = self
+            .digest_service
+            .save_digest_type(req.name, req.cadence, req.template)
+            .await?;
+
+        Ok(Response::new(digest_type_response))
+    }
+
+    async fn enqueue_digest_item(
+        &self,
+        request: Request<EnqueueDigestItemRequest>,
+    ) -> Result<Response<EmailResponse>, Status> {
+        let req = request.into_inner();
+
+        self.digest_service
+            .enqueue_digest_item(
+                req.digest_type,
+                NewDigestItem {
+                    recipient: req.recipient,
+                    title: req.title,
+                    body: req.body,
+                },
+            )
+            .await?;
+
+        Ok(Response::new(EmailResponse {
+            message: String::from("Successfully queued digest item!"),
+        }))
+    }
 }
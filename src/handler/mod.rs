@@ -1,29 +1,58 @@
 pub mod email;
+pub mod tracking;
 
 #[cfg(test)]
 pub mod test {
-    use std::sync::Arc;
+    use std::{collections::HashMap, sync::Arc};
 
     use clap::Parser;
-    use madtofan_microservice_common::email::{
-        email_server::Email, AddGroupRequest, AddSubscriberRequest, BlastEmailRequest,
-        GetSubscriberGroupsRequest, GetSubscribersRequest, RemoveGroupRequest,
-        RemoveSubscriberRequest, SendEmailRequest,
-    };
+    use futures::{stream, TryStreamExt};
     use sqlx::PgPool;
     use tonic::Request;
 
     use crate::{
         config::AppConfig,
-        handler::email::RequestHandler,
+        handler::email::{RequestHandler, MAX_IMPORT_BYTES},
+        proto::email::{
+            email_server::Email, AddGroupRequest, AddSenderIdentityRequest, AddSubscriberRequest,
+            AddSubscribersRequest, BlastEmailRequest, CancelBlastRequest, CreateCampaignRequest,
+            EnqueueDigestItemRequest, ExportFormat, ExportSubscribersRequest,
+            GetCampaignEngagementRequest, GetMessageStatusRequest, GetSubscriberGroupsRequest,
+            GetSubscribersRequest, ImportSubscribersRequest, ListCampaignsRequest,
+            ListDeliveriesRequest, ListGroupSeedsRequest, ListSenderIdentitiesRequest,
+            ListTemplatesRequest, MergeGroupsRequest, MessageClass, MessagePriority,
+            PreviewEmailRequest, RemoveGroupRequest, RemoveSenderIdentityRequest,
+            RemoveSubscriberRequest, RemoveSubscribersRequest, RemoveTemplateRequest,
+            SaveDigestTypeRequest, SaveTemplateRequest, SendCampaignRequest, SendEmailRequest,
+            SetGroupFooterRequest, SetGroupIdentityRequest, SetGroupSeedsRequest,
+            SubscriberOutcome, TestBlastRequest, WatchBlastRequest,
+        },
         repository::{
+            campaign::{CampaignRepository, DynCampaignRepositoryTrait},
+            delivery::{DeliveryRepository, DynDeliveryRepositoryTrait},
+            digest::{DigestRepository, DynDigestRepositoryTrait},
             group::{DynGroupRepositoryTrait, GroupRepository},
+            idempotency::{DynIdempotencyRepositoryTrait, IdempotencyRepository},
+            identity::{DynSenderIdentityRepositoryTrait, SenderIdentityRepository},
             subcriber::{DynSubscriberRepositoryTrait, SubscriberRepository},
+            suppression::{DynSuppressionRepositoryTrait, SuppressionRepository},
+            template::{DynTemplateRepositoryTrait, TemplateRepository},
+            tracking::{DynTrackingRepositoryTrait, TrackingRepository},
+            unsubscription::{DynUnsubscriptionRepositoryTrait, UnsubscriptionRepository},
         },
         service::{
+            campaign::{CampaignService, DynCampaignServiceTrait},
+            delivery::{DeliveryService, DynDeliveryServiceTrait},
+            digest::{DigestService, DynDigestServiceTrait},
             email::{DynEmailServiceTrait, EmailService},
             group::{DynGroupServiceTrait, GroupService},
+            idempotency::{DynIdempotencyServiceTrait, IdempotencyService},
+            identity::{DynSenderIdentityServiceTrait, SenderIdentityService},
+            markdown::MarkdownRenderer,
             subscriber::{DynSubscriberServiceTrait, SubscriberService},
+            template::{DynTemplateServiceTrait, TemplateService},
+            tracking::{DynTrackingServiceTrait, TrackingService},
+            validation::EmailValidator,
         },
     };
 
@@ -40,17 +69,93 @@ pub mod test {
             Arc::new(SubscriberRepository::new(pool.clone())) as DynSubscriberRepositoryTrait;
         let group_repository =
             Arc::new(GroupRepository::new(pool.clone())) as DynGroupRepositoryTrait;
+        let email_validator = Arc::new(EmailValidator::default());
         let subscriber_service = Arc::new(SubscriberService::new(
             subscriber_repository.clone(),
             group_repository.clone(),
+            email_validator.clone(),
         )) as DynSubscriberServiceTrait;
-        let group_service =
-            Arc::new(GroupService::new(group_repository.clone())) as DynGroupServiceTrait;
-        let email_service = Arc::new(EmailService::new(&config)) as DynEmailServiceTrait;
+        let group_service = Arc::new(GroupService::new(
+            group_repository.clone(),
+            email_validator.clone(),
+        )) as DynGroupServiceTrait;
+        let delivery_repository =
+            Arc::new(DeliveryRepository::new(pool.clone())) as DynDeliveryRepositoryTrait;
+        let suppression_repository =
+            Arc::new(SuppressionRepository::new(pool.clone())) as DynSuppressionRepositoryTrait;
+        let identity_repository = Arc::new(SenderIdentityRepository::new(pool.clone()))
+            as DynSenderIdentityRepositoryTrait;
+        let template_repository =
+            Arc::new(TemplateRepository::new(pool.clone())) as DynTemplateRepositoryTrait;
+        let unsubscription_repository = Arc::new(UnsubscriptionRepository::new(pool.clone()))
+            as DynUnsubscriptionRepositoryTrait;
+        let email_service = Arc::new(EmailService::new(
+            &config,
+            email_validator.clone(),
+            delivery_repository.clone(),
+            suppression_repository,
+            identity_repository.clone(),
+            template_repository.clone(),
+            subscriber_repository.clone(),
+            unsubscription_repository.clone(),
+            Arc::new(MarkdownRenderer::default()),
+            None,
+            None,
+            None,
+            None,
+        )) as DynEmailServiceTrait;
+        let delivery_service =
+            Arc::new(DeliveryService::new(delivery_repository.clone())) as DynDeliveryServiceTrait;
+        let campaign_repository =
+            Arc::new(CampaignRepository::new(pool.clone())) as DynCampaignRepositoryTrait;
+        let campaign_service = Arc::new(CampaignService::new(
+            campaign_repository.clone(),
+            group_repository.clone(),
+            identity_repository.clone(),
+            template_repository.clone(),
+            email_service.clone(),
+            config.test_blast_subject_prefix.clone(),
+        )) as DynCampaignServiceTrait;
+        let digest_repository =
+            Arc::new(DigestRepository::new(pool.clone())) as DynDigestRepositoryTrait;
+        let digest_service = Arc::new(DigestService::new(
+            digest_repository,
+            template_repository.clone(),
+            email_validator,
+            email_service.clone(),
+        )) as DynDigestServiceTrait;
+        let template_service =
+            Arc::new(TemplateService::new(template_repository)) as DynTemplateServiceTrait;
+        let idempotency_repository =
+            Arc::new(IdempotencyRepository::new(pool.clone())) as DynIdempotencyRepositoryTrait;
+        let idempotency_service = Arc::new(IdempotencyService::new(
+            idempotency_repository,
+            config.idempotency_window_seconds,
+        )) as DynIdempotencyServiceTrait;
+        let identity_service = Arc::new(SenderIdentityService::new(
+            identity_repository,
+            group_repository.clone(),
+        )) as DynSenderIdentityServiceTrait;
+        let tracking_repository =
+            Arc::new(TrackingRepository::new(pool.clone())) as DynTrackingRepositoryTrait;
+        let tracking_service = Arc::new(TrackingService::new(
+            tracking_repository,
+            campaign_repository,
+            delivery_repository,
+            unsubscription_repository,
+            None,
+        )) as DynTrackingServiceTrait;
         let handler = RequestHandler::new(
             subscriber_service.clone(),
             group_service.clone(),
             email_service.clone(),
+            campaign_service.clone(),
+            delivery_service.clone(),
+            tracking_service.clone(),
+            identity_service.clone(),
+            template_service.clone(),
+            idempotency_service.clone(),
+            digest_service.clone(),
         );
 
         AllTraits {
@@ -65,11 +170,99 @@ pub mod test {
         let all_traits = initialize_handler(pool);
         let request = Request::new(SendEmailRequest {
             body: "test_email_body".to_string(),
+            markdown_body: String::new(),
             email: "test@address.com".to_string(),
             title: "test_email_title".to_string(),
+            identity: String::new(),
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            reply_to: "reply@address.com".to_string(),
+            headers: HashMap::from([("X-Entity-Ref-ID".to_string(), "test_entity".to_string())]),
+            without_footer: true,
+            template: String::new(),
+            locale: String::new(),
+            idempotency_key: String::new(),
+            message_class: MessageClass::Unspecified as i32,
+            priority: MessagePriority::Unspecified as i32,
         });
 
-        all_traits.handler.send_email(request).await?;
+        let message_id = all_traits
+            .handler
+            .send_email(request)
+            .await?
+            .into_inner()
+            .message_id;
+
+        let request = Request::new(GetMessageStatusRequest { message_id });
+        let delivery = all_traits
+            .handler
+            .get_message_status(request)
+            .await?
+            .into_inner();
+
+        assert_eq!(delivery.recipient, "test@address.com");
+        assert_eq!(delivery.status, "sent");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn send_email_idempotency_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+        let send_request = |body: &str| {
+            let mut request = Request::new(SendEmailRequest {
+                body: body.to_string(),
+                markdown_body: String::new(),
+                email: "test@address.com".to_string(),
+                title: "test_email_title".to_string(),
+                identity: String::new(),
+                to: Vec::new(),
+                cc: Vec::new(),
+                bcc: Vec::new(),
+                reply_to: String::new(),
+                headers: HashMap::new(),
+                without_footer: true,
+                template: String::new(),
+                locale: String::new(),
+                idempotency_key: String::new(),
+                message_class: MessageClass::Unspecified as i32,
+                priority: MessagePriority::Unspecified as i32,
+            });
+            request
+                .metadata_mut()
+                .insert("idempotency-key", "retried-send".parse().unwrap());
+            request
+        };
+
+        let first = all_traits
+            .handler
+            .send_email(send_request("test_email_body"))
+            .await?
+            .into_inner();
+        let retry = all_traits
+            .handler
+            .send_email(send_request("test_email_body"))
+            .await?
+            .into_inner();
+        let reused = all_traits
+            .handler
+            .send_email(send_request("another_email_body"))
+            .await;
+        let deliveries = all_traits
+            .handler
+            .list_deliveries(Request::new(ListDeliveriesRequest {
+                recipient: "test@address.com".to_string(),
+                campaign_id: 0,
+                offset: 0,
+                limit: 10,
+            }))
+            .await?
+            .into_inner();
+
+        assert_eq!(first.message_id, retry.message_id);
+        assert!(reused.is_err());
+        assert_eq!(deliveries.count, 1);
 
         Ok(())
     }
@@ -98,10 +291,124 @@ pub mod test {
         let request = Request::new(BlastEmailRequest {
             group: group_name.to_string(),
             body: "email body".to_string(),
+            markdown_body: String::new(),
             title: "email title".to_string(),
+            identity: String::new(),
+            template: String::new(),
+            locale: String::new(),
+            local_send_time: String::new(),
+            idempotency_key: String::new(),
+            message_class: MessageClass::Unspecified as i32,
         });
 
-        all_traits.handler.blast_email(request).await?;
+        let blast = all_traits.handler.blast_email(request).await?.into_inner();
+        let progress = all_traits
+            .handler
+            .watch_blast(Request::new(WatchBlastRequest { id: blast.id }))
+            .await?
+            .into_inner()
+            .try_collect::<Vec<_>>()
+            .await?;
+        let last = progress.last().unwrap();
+
+        assert_eq!(last.id, blast.id);
+        assert_eq!(last.total, 2);
+        assert!(last.finished);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn preview_email_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+        let group = all_traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        all_traits
+            .group_repository
+            .add_group("empty_group", "group_description")
+            .await?;
+        all_traits
+            .subscriber_repository
+            .add_subscriber("sample@test.com", &group)
+            .await?;
+
+        let preview_request = |group: &str| {
+            Request::new(PreviewEmailRequest {
+                group: group.to_string(),
+                email: String::new(),
+                body: "Hi {{email}}".to_string(),
+                markdown_body: String::new(),
+                title: "email title".to_string(),
+                identity: String::new(),
+                template: String::new(),
+                locale: String::new(),
+            })
+        };
+
+        let preview = all_traits
+            .handler
+            .preview_email(preview_request("group_name"))
+            .await?
+            .into_inner();
+        let empty_preview = all_traits
+            .handler
+            .preview_email(preview_request("empty_group"))
+            .await;
+
+        assert_eq!(preview.recipient, "sample@test.com");
+        assert!(preview.text_body.starts_with("Hi sample@test.com"));
+        assert!(empty_preview.is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_blast_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+        let group = all_traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        all_traits
+            .subscriber_repository
+            .add_subscriber("sample@test.com", &group)
+            .await?;
+
+        all_traits
+            .handler
+            .set_group_seeds(Request::new(SetGroupSeedsRequest {
+                group: "group_name".to_string(),
+                emails: vec!["reviewer@test.com".to_string()],
+            }))
+            .await?;
+        let seeds = all_traits
+            .handler
+            .list_group_seeds(Request::new(ListGroupSeedsRequest {
+                group: "group_name".to_string(),
+            }))
+            .await?
+            .into_inner()
+            .emails;
+        let test_blast = all_traits
+            .handler
+            .test_blast(Request::new(TestBlastRequest {
+                group: "group_name".to_string(),
+                email: String::new(),
+                body: "email body".to_string(),
+                markdown_body: String::new(),
+                title: "email title".to_string(),
+                identity: String::new(),
+                template: String::new(),
+                locale: String::new(),
+            }))
+            .await?
+            .into_inner();
+
+        assert_eq!(seeds, vec!["reviewer@test.com"]);
+        assert_eq!(test_blast.sample, "sample@test.com");
+        assert_eq!(test_blast.seeds, vec!["reviewer@test.com"]);
 
         Ok(())
     }
@@ -171,6 +478,123 @@ pub mod test {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn add_subscribers_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        let group = all_traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let request = Request::new(AddSubscribersRequest {
+            emails: vec![
+                "sub1@address.com".to_string(),
+                "sub2@address.com".to_string(),
+            ],
+            group: group_name.to_string(),
+        });
+
+        let outcomes = all_traits
+            .handler
+            .add_subscribers(request)
+            .await?
+            .into_inner()
+            .outcomes;
+        let subs_list = all_traits
+            .subscriber_repository
+            .list_subs_by_group(&group, None, None)
+            .await?;
+
+        assert!(outcomes
+            .iter()
+            .all(|outcome| outcome.status == SubscriberOutcome::Added as i32));
+        assert_eq!(subs_list.len(), 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn remove_subscribers_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        let group = all_traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let sub1_email = "sub1@address.com";
+        all_traits
+            .subscriber_repository
+            .add_subscriber(sub1_email, &group)
+            .await?;
+        let sub2_email = "sub2@address.com";
+        all_traits
+            .subscriber_repository
+            .add_subscriber(sub2_email, &group)
+            .await?;
+
+        let request = Request::new(RemoveSubscribersRequest {
+            emails: vec![sub1_email.to_string()],
+            group: group_name.to_string(),
+        });
+
+        all_traits.handler.remove_subscribers(request).await?;
+        let subs_list = all_traits
+            .subscriber_repository
+            .list_subs_by_group(&group, None, None)
+            .await?;
+
+        assert_eq!(subs_list.len(), 1);
+        assert_eq!(subs_list.first().unwrap().email, sub2_email);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn export_subscribers_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        let group = all_traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let sub1_email = "sub1_email";
+        all_traits
+            .subscriber_repository
+            .add_subscriber(sub1_email, &group)
+            .await?;
+        let sub2_email = "sub2_email";
+        all_traits
+            .subscriber_repository
+            .add_subscriber(sub2_email, &group)
+            .await?;
+
+        let request = Request::new(ExportSubscribersRequest {
+            group: group_name.to_string(),
+            format: ExportFormat::Csv as i32,
+            columns: vec![],
+            segment: Default::default(),
+        });
+
+        let rows = all_traits
+            .handler
+            .export_subscribers(request)
+            .await?
+            .into_inner()
+            .map_ok(|response| response.row)
+            .try_collect::<Vec<String>>()
+            .await?;
+
+        assert_eq!(rows, vec!["email\n", "sub1_email\n", "sub2_email\n"]);
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn add_group_test(pool: PgPool) -> anyhow::Result<()> {
         let all_traits = initialize_handler(pool);
@@ -309,4 +733,406 @@ pub mod test {
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn merge_groups_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let into_group_name = "into_group";
+        let into_group = all_traits
+            .group_repository
+            .add_group(into_group_name, "into_group_description")
+            .await?;
+        let from_group_name = "from_group";
+        let from_group = all_traits
+            .group_repository
+            .add_group(from_group_name, "from_group_description")
+            .await?;
+
+        all_traits
+            .subscriber_repository
+            .add_subscriber("sub1_email", &into_group)
+            .await?;
+        all_traits
+            .subscriber_repository
+            .add_subscriber("sub2_email", &from_group)
+            .await?;
+
+        let request = Request::new(MergeGroupsRequest {
+            into_group: into_group_name.to_string(),
+            from_group: from_group_name.to_string(),
+        });
+
+        let summary = all_traits.handler.merge_groups(request).await?.into_inner();
+        let groups_list = all_traits.group_repository.list_groups().await?;
+
+        assert_eq!(summary.inserted, 1);
+        assert_eq!(groups_list.len(), 1);
+        assert_eq!(groups_list.first().unwrap().name, into_group_name);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn create_and_list_campaigns_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        all_traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let request = Request::new(CreateCampaignRequest {
+            name: "campaign_name".to_string(),
+            subject: "campaign subject".to_string(),
+            text_body: "campaign body".to_string(),
+            html_body: String::new(),
+            markdown_body: String::new(),
+            sender: String::new(),
+            identity: String::new(),
+            template: String::new(),
+            locale: String::new(),
+            local_send_time: String::new(),
+            groups: vec![group_name.to_string()],
+            message_class: MessageClass::Unspecified as i32,
+        });
+
+        let created = all_traits
+            .handler
+            .create_campaign(request)
+            .await?
+            .into_inner();
+
+        let request = Request::new(ListCampaignsRequest {
+            status: "draft".to_string(),
+            offset: 0,
+            limit: 10,
+        });
+
+        let campaigns = all_traits
+            .handler
+            .list_campaigns(request)
+            .await?
+            .into_inner()
+            .campaigns;
+
+        let request = Request::new(GetCampaignEngagementRequest {
+            campaign_id: created.id,
+        });
+
+        let engagement = all_traits
+            .handler
+            .get_campaign_engagement(request)
+            .await?
+            .into_inner();
+
+        assert_eq!(campaigns.len(), 1);
+        assert_eq!(campaigns.first().unwrap().id, created.id);
+        assert_eq!(created.message_class, "marketing");
+        assert_eq!(campaigns.first().unwrap().groups, vec![group_name]);
+        assert_eq!(engagement.sent, 0);
+        assert_eq!(engagement.open_rate, 0.0);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn sender_identity_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        all_traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let identity = all_traits
+            .handler
+            .add_sender_identity(Request::new(AddSenderIdentityRequest {
+                name: "newsletter".to_string(),
+                display_name: "Newsletter".to_string(),
+                address: "newsletter@email.com".to_string(),
+                reply_to: String::new(),
+                smtp_username: "newsletter".to_string(),
+                smtp_password: "app_password".to_string(),
+            }))
+            .await?
+            .into_inner();
+        assert!(identity.has_credentials);
+        assert!(identity.reply_to.is_empty());
+
+        all_traits
+            .handler
+            .set_group_identity(Request::new(SetGroupIdentityRequest {
+                group: group_name.to_string(),
+                identity: "newsletter".to_string(),
+            }))
+            .await?;
+
+        let identities = all_traits
+            .handler
+            .list_sender_identities(Request::new(ListSenderIdentitiesRequest {}))
+            .await?
+            .into_inner();
+        assert_eq!(identities.identities.len(), 1);
+
+        all_traits
+            .handler
+            .remove_sender_identity(Request::new(RemoveSenderIdentityRequest {
+                name: "newsletter".to_string(),
+            }))
+            .await?;
+
+        let identities = all_traits
+            .handler
+            .list_sender_identities(Request::new(ListSenderIdentitiesRequest {}))
+            .await?
+            .into_inner();
+        assert!(identities.identities.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn set_group_footer_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let group = all_traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+
+        all_traits
+            .handler
+            .set_group_footer(Request::new(SetGroupFooterRequest {
+                group: "group_name".to_string(),
+                text_template: "Unsubscribe: {{unsubscribe_url}}".to_string(),
+                html_template: String::new(),
+            }))
+            .await?;
+        let footer = all_traits
+            .group_repository
+            .get_group_footer(group.id)
+            .await?;
+        assert_eq!(footer.unwrap().text, "Unsubscribe: {{unsubscribe_url}}");
+
+        all_traits
+            .handler
+            .set_group_footer(Request::new(SetGroupFooterRequest {
+                group: "group_name".to_string(),
+                text_template: String::new(),
+                html_template: String::new(),
+            }))
+            .await?;
+        let footer = all_traits
+            .group_repository
+            .get_group_footer(group.id)
+            .await?;
+        assert!(footer.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn template_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        for (locale, subject) in [("en", "Welcome"), ("ms_my", "Selamat datang")] {
+            all_traits
+                .handler
+                .save_template(Request::new(SaveTemplateRequest {
+                    name: "welcome".to_string(),
+                    locale: locale.to_string(),
+                    subject: subject.to_string(),
+                    text_body: subject.to_string(),
+                    html_body: String::new(),
+                }))
+                .await?;
+        }
+
+        let templates = all_traits
+            .handler
+            .list_templates(Request::new(ListTemplatesRequest {
+                name: "welcome".to_string(),
+            }))
+            .await?
+            .into_inner()
+            .templates;
+        assert_eq!(
+            templates
+                .iter()
+                .map(|template| template.locale.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["en", "ms-MY"]
+        );
+
+        all_traits
+            .handler
+            .remove_template(Request::new(RemoveTemplateRequest {
+                name: "welcome".to_string(),
+                locale: "ms-MY".to_string(),
+            }))
+            .await?;
+        let templates = all_traits
+            .handler
+            .list_templates(Request::new(ListTemplatesRequest {
+                name: String::new(),
+            }))
+            .await?
+            .into_inner()
+            .templates;
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates.first().unwrap().subject, "Welcome");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn digest_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        all_traits
+            .handler
+            .save_template(Request::new(SaveTemplateRequest {
+                name: "activity".to_string(),
+                locale: "en".to_string(),
+                subject: "{{item_count}} updates".to_string(),
+                text_body: "What happened:\n{{items}}".to_string(),
+                html_body: String::new(),
+            }))
+            .await?;
+        let digest_type = all_traits
+            .handler
+            .save_digest_type(Request::new(SaveDigestTypeRequest {
+                name: "activity".to_string(),
+                cadence: "hourly".to_string(),
+                template: "activity".to_string(),
+            }))
+            .await?
+            .into_inner();
+        let unknown_cadence = all_traits
+            .handler
+            .save_digest_type(Request::new(SaveDigestTypeRequest {
+                name: "activity".to_string(),
+                cadence: "weekly".to_string(),
+                template: "activity".to_string(),
+            }))
+            .await;
+        all_traits
+            .handler
+            .enqueue_digest_item(Request::new(EnqueueDigestItemRequest {
+                digest_type: "activity".to_string(),
+                recipient: "someone@test.com".to_string(),
+                title: "Aminah commented on your post".to_string(),
+                body: "Nice work!".to_string(),
+            }))
+            .await?;
+        let unknown_digest_type = all_traits
+            .handler
+            .enqueue_digest_item(Request::new(EnqueueDigestItemRequest {
+                digest_type: "billing".to_string(),
+                recipient: "someone@test.com".to_string(),
+                title: "Invoice ready".to_string(),
+                body: String::new(),
+            }))
+            .await;
+
+        assert_eq!(digest_type.cadence, "hourly");
+        assert!(unknown_cadence.is_err());
+        assert!(unknown_digest_type.is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn watch_blast_test(pool: PgPool) -> anyhow::Result<()> {
+        let all_traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        all_traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let request = Request::new(CreateCampaignRequest {
+            name: "campaign_name".to_string(),
+            subject: "campaign subject".to_string(),
+            text_body: "campaign body".to_string(),
+            html_body: String::new(),
+            markdown_body: String::new(),
+            sender: String::new(),
+            identity: String::new(),
+            template: String::new(),
+            locale: String::new(),
+            local_send_time: String::new(),
+            groups: vec![group_name.to_string()],
+            message_class: MessageClass::Unspecified as i32,
+        });
+
+        let created = all_traits
+            .handler
+            .create_campaign(request)
+            .await?
+            .into_inner();
+
+        let draft_watch = all_traits
+            .handler
+            .watch_blast(Request::new(WatchBlastRequest { id: created.id }))
+            .await;
+
+        let request = Request::new(SendCampaignRequest {
+            id: created.id,
+            scheduled_at: 0,
+        });
+
+        all_traits.handler.send_campaign(request).await?;
+
+        let progress = all_traits
+            .handler
+            .watch_blast(Request::new(WatchBlastRequest { id: created.id }))
+            .await?
+            .into_inner()
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let cancelled = all_traits
+            .handler
+            .cancel_blast(Request::new(CancelBlastRequest { id: created.id }))
+            .await;
+
+        assert!(draft_watch.is_err());
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress.first().unwrap().id, created.id);
+        assert_eq!(progress.first().unwrap().total, 0);
+        assert!(progress.first().unwrap().finished);
+        assert!(cancelled.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_subscribers_limit_test() -> anyhow::Result<()> {
+        let chunk = |group: &str, size: usize| ImportSubscribersRequest {
+            group: group.to_string(),
+            chunk: vec![b'a'; size],
+        };
+
+        let (group, csv) = RequestHandler::collect_import(stream::iter(vec![
+            Ok(chunk("group", MAX_IMPORT_BYTES / 2)),
+            Ok(chunk("", MAX_IMPORT_BYTES / 2)),
+        ]))
+        .await?;
+        let oversized = RequestHandler::collect_import(stream::iter(vec![
+            Ok(chunk("group", MAX_IMPORT_BYTES)),
+            Ok(chunk("", 1)),
+        ]))
+        .await;
+
+        assert_eq!(group, "group");
+        assert_eq!(csv.len(), MAX_IMPORT_BYTES);
+        assert_eq!(oversized.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use madtofan_microservice_common::errors::ServiceError;
+use tracing::log::error;
+
+use crate::service::tracking::DynTrackingServiceTrait;
+
+/// Transparent 1x1 GIF served as the open pixel.
+const PIXEL: [u8; 43] = [
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+pub fn tracking_router(tracking_service: DynTrackingServiceTrait) -> Router {
+    Router::new()
+        .route("/track/open/:delivery_id/:signature", get(track_open))
+        .route("/track/click/:delivery_id/:signature", get(track_click))
+        // Only a POST unsubscribes (RFC 8058), link scanners that prefetch the
+        // footer link just get the confirmation page.
+        .route(
+            "/unsubscribe/:delivery_id/:signature",
+            get(confirm_unsubscribe).post(unsubscribe),
+        )
+        .with_state(tracking_service)
+}
+
+fn user_agent(headers: &HeaderMap) -> String {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+async fn track_open(
+    State(tracking_service): State<DynTrackingServiceTrait>,
+    Path((delivery_id, signature)): Path<(i64, String)>,
+    headers: HeaderMap,
+) -> Response {
+    // Mail clients get the pixel no matter what, a broken image would only
+    // draw attention to it.
+    if let Err(err) = tracking_service
+        .record_open(delivery_id, signature, user_agent(&headers))
+        .await
+    {
+        error!("failed to record open for {:?}: {:?}", delivery_id, err);
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store, max-age=0"),
+        ],
+        PIXEL,
+    )
+        .into_response()
+}
+
+async fn track_click(
+    State(tracking_service): State<DynTrackingServiceTrait>,
+    Path((delivery_id, signature)): Path<(i64, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let encoded_url = query.get("u").cloned().unwrap_or_default();
+
+    match tracking_service
+        .record_click(delivery_id, encoded_url, signature, user_agent(&headers))
+        .await
+    {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(ServiceError::BadRequest(_)) => StatusCode::BAD_REQUEST.into_response(),
+        Err(err) => {
+            error!("failed to record click for {:?}: {:?}", delivery_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn confirm_unsubscribe() -> Html<&'static str> {
+    Html(
+        "<form method=\"post\">\
+            <p>Unsubscribe from these emails?</p>\
+            <button type=\"submit\">Unsubscribe</button>\
+        </form>",
+    )
+}
+
+async fn unsubscribe(
+    State(tracking_service): State<DynTrackingServiceTrait>,
+    Path((delivery_id, signature)): Path<(i64, String)>,
+) -> Response {
+    match tracking_service.unsubscribe(delivery_id, signature).await {
+        Ok(_) => Html("<p>You have been unsubscribed.</p>").into_response(),
+        Err(ServiceError::BadRequest(_)) | Err(ServiceError::ObjectConflict(_)) => {
+            StatusCode::BAD_REQUEST.into_response()
+        }
+        Err(err) => {
+            error!("failed to unsubscribe {:?}: {:?}", delivery_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,16 +1,45 @@
 use crate::config::AppConfig;
 use crate::handler::email::RequestHandler;
+use crate::handler::tracking::tracking_router;
+use crate::proto::email::email_server::EmailServer;
+use crate::repository::campaign::{CampaignRepository, DynCampaignRepositoryTrait};
+use crate::repository::delivery::{DeliveryRepository, DynDeliveryRepositoryTrait};
+use crate::repository::digest::{DigestRepository, DynDigestRepositoryTrait};
 use crate::repository::group::{DynGroupRepositoryTrait, GroupRepository};
+use crate::repository::idempotency::{DynIdempotencyRepositoryTrait, IdempotencyRepository};
+use crate::repository::identity::{DynSenderIdentityRepositoryTrait, SenderIdentityRepository};
 use crate::repository::subcriber::{DynSubscriberRepositoryTrait, SubscriberRepository};
+use crate::repository::suppression::{DynSuppressionRepositoryTrait, SuppressionRepository};
+use crate::repository::template::{DynTemplateRepositoryTrait, TemplateRepository};
+use crate::repository::tracking::{DynTrackingRepositoryTrait, TrackingRepository};
+use crate::repository::unsubscription::{
+    DynUnsubscriptionRepositoryTrait, UnsubscriptionRepository,
+};
+use crate::service::bounce::{BounceService, DynBounceServiceTrait};
+use crate::service::campaign::{CampaignService, DynCampaignServiceTrait};
+use crate::service::delivery::{DeliveryService, DynDeliveryServiceTrait};
+use crate::service::digest::{DigestService, DynDigestServiceTrait};
+use crate::service::dkim::DkimSigner;
 use crate::service::email::{DynEmailServiceTrait, EmailService};
+use crate::service::footer::Footer;
 use crate::service::group::{DynGroupServiceTrait, GroupService};
+use crate::service::idempotency::{DynIdempotencyServiceTrait, IdempotencyService};
+use crate::service::identity::{DynSenderIdentityServiceTrait, SenderIdentityService};
+use crate::service::markdown::MarkdownRenderer;
 use crate::service::subscriber::{DynSubscriberServiceTrait, SubscriberService};
+use crate::service::template::{DynTemplateServiceTrait, TemplateService};
+use crate::service::tracker::Tracker;
+use crate::service::tracking::{DynTrackingServiceTrait, TrackingService};
+use crate::service::validation::EmailValidator;
+use crate::service::verp::Verp;
+use crate::worker::bounce_watcher::BounceWatcher;
+use crate::worker::campaign_scheduler::CampaignScheduler;
+use crate::worker::digest_scheduler::DigestScheduler;
 use clap::Parser;
 use dotenv::dotenv;
-use madtofan_microservice_common::{
-    email::email_server::EmailServer, repository::connection_pool::ServiceConnectionManager,
-};
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionManager;
 use std::sync::Arc;
+use std::time::Duration;
 use tonic::transport::Server;
 use tracing::{error, info};
 use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
@@ -18,8 +47,10 @@ use tracing_subscriber::util::SubscriberInitExt;
 
 mod config;
 mod handler;
+mod proto;
 mod repository;
 mod service;
+mod worker;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -50,16 +81,203 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app_url = format!("{}:{}", app_host, app_port).parse().unwrap();
     let subscriber_repository =
         Arc::new(SubscriberRepository::new(pg_pool.clone())) as DynSubscriberRepositoryTrait;
-    let group_repository = Arc::new(GroupRepository::new(pg_pool)) as DynGroupRepositoryTrait;
+    let group_repository =
+        Arc::new(GroupRepository::new(pg_pool.clone())) as DynGroupRepositoryTrait;
+    let campaign_repository =
+        Arc::new(CampaignRepository::new(pg_pool.clone())) as DynCampaignRepositoryTrait;
+    let delivery_repository =
+        Arc::new(DeliveryRepository::new(pg_pool.clone())) as DynDeliveryRepositoryTrait;
+    let suppression_repository =
+        Arc::new(SuppressionRepository::new(pg_pool.clone())) as DynSuppressionRepositoryTrait;
+    let tracking_repository =
+        Arc::new(TrackingRepository::new(pg_pool.clone())) as DynTrackingRepositoryTrait;
+    let identity_repository = Arc::new(SenderIdentityRepository::new(pg_pool.clone()))
+        as DynSenderIdentityRepositoryTrait;
+    let unsubscription_repository = Arc::new(UnsubscriptionRepository::new(pg_pool.clone()))
+        as DynUnsubscriptionRepositoryTrait;
+    let template_repository =
+        Arc::new(TemplateRepository::new(pg_pool.clone())) as DynTemplateRepositoryTrait;
+    let digest_repository =
+        Arc::new(DigestRepository::new(pg_pool.clone())) as DynDigestRepositoryTrait;
+    let idempotency_repository =
+        Arc::new(IdempotencyRepository::new(pg_pool)) as DynIdempotencyRepositoryTrait;
     info!("Repositories initialized, Initializing Services");
+    let email_validator = Arc::new(
+        EmailValidator::from_file(config.disposable_domains_path.as_deref())
+            .expect("could not load the disposable domain list"),
+    );
     let subscriber_service = Arc::new(SubscriberService::new(
-        subscriber_repository,
+        subscriber_repository.clone(),
         group_repository.clone(),
+        email_validator.clone(),
     )) as DynSubscriberServiceTrait;
-    let group_service = Arc::new(GroupService::new(group_repository)) as DynGroupServiceTrait;
-    let email_service = Arc::new(EmailService::new(&config)) as DynEmailServiceTrait;
-    info!("Services initialized, Initializing Handler");
-    let request_handler = RequestHandler::new(subscriber_service, group_service, email_service);
+    let group_service = Arc::new(GroupService::new(
+        group_repository.clone(),
+        email_validator.clone(),
+    )) as DynGroupServiceTrait;
+    let verp = config
+        .verp_domain
+        .as_deref()
+        .map(|verp_domain| Arc::new(Verp::new(&config.verp_prefix, verp_domain)));
+    let tracker = config
+        .tracking_base_url
+        .as_deref()
+        .map(|tracking_base_url| {
+            Arc::new(Tracker::new(
+                tracking_base_url,
+                config
+                    .tracking_secret
+                    .as_deref()
+                    .expect("a tracking secret is required when tracking is enabled"),
+            ))
+        });
+    let dkim = config
+        .dkim_private_key_path
+        .as_deref()
+        .map(|dkim_private_key_path| {
+            let dkim_domain = config
+                .dkim_domain
+                .clone()
+                .or_else(|| {
+                    config
+                        .service_email_address
+                        .rsplit_once('@')
+                        .map(|(_, domain)| domain.to_string())
+                })
+                .expect("a DKIM domain is required when DKIM signing is enabled");
+            Arc::new(
+                DkimSigner::from_file(
+                    config
+                        .dkim_selector
+                        .as_deref()
+                        .expect("a DKIM selector is required when DKIM signing is enabled"),
+                    &dkim_domain,
+                    dkim_private_key_path,
+                    &config.dkim_algorithm,
+                )
+                .expect("failed to load the DKIM private key"),
+            )
+        });
+    let footer = config
+        .footer_postal_address
+        .as_deref()
+        .map(|postal_address| {
+            assert!(
+                tracker.is_some(),
+                "tracking is required for the footer unsubscribe link"
+            );
+            Arc::new(
+                Footer::from_files(
+                    postal_address,
+                    config.footer_text_template_path.as_deref(),
+                    config.footer_html_template_path.as_deref(),
+                )
+                .expect("could not load the footer templates"),
+            )
+        });
+    let markdown = Arc::new(
+        MarkdownRenderer::from_file(config.markdown_layout_path.as_deref())
+            .expect("could not load the markdown layout"),
+    );
+    let email_service = Arc::new(EmailService::new(
+        &config,
+        email_validator.clone(),
+        delivery_repository.clone(),
+        suppression_repository.clone(),
+        identity_repository.clone(),
+        template_repository.clone(),
+        subscriber_repository,
+        unsubscription_repository.clone(),
+        markdown,
+        verp.clone(),
+        tracker.clone(),
+        dkim,
+        footer,
+    )) as DynEmailServiceTrait;
+    let bounce_service = Arc::new(BounceService::new(
+        delivery_repository.clone(),
+        suppression_repository,
+        verp,
+    )) as DynBounceServiceTrait;
+    let delivery_service =
+        Arc::new(DeliveryService::new(delivery_repository.clone())) as DynDeliveryServiceTrait;
+    let tracking_service = Arc::new(TrackingService::new(
+        tracking_repository,
+        campaign_repository.clone(),
+        delivery_repository,
+        unsubscription_repository,
+        tracker.clone(),
+    )) as DynTrackingServiceTrait;
+    let identity_service = Arc::new(SenderIdentityService::new(
+        identity_repository.clone(),
+        group_repository.clone(),
+    )) as DynSenderIdentityServiceTrait;
+    let campaign_service = Arc::new(CampaignService::new(
+        campaign_repository,
+        group_repository,
+        identity_repository,
+        template_repository.clone(),
+        email_service.clone(),
+        config.test_blast_subject_prefix.clone(),
+    )) as DynCampaignServiceTrait;
+    let digest_service = Arc::new(DigestService::new(
+        digest_repository,
+        template_repository.clone(),
+        email_validator,
+        email_service.clone(),
+    )) as DynDigestServiceTrait;
+    let template_service =
+        Arc::new(TemplateService::new(template_repository)) as DynTemplateServiceTrait;
+    let idempotency_service = Arc::new(IdempotencyService::new(
+        idempotency_repository,
+        config.idempotency_window_seconds,
+    )) as DynIdempotencyServiceTrait;
+    info!("Services initialized, Initializing Workers");
+    let campaign_scheduler = CampaignScheduler::new(
+        campaign_service.clone(),
+        Duration::from_secs(config.campaign_poll_interval_seconds),
+    );
+    tokio::spawn(campaign_scheduler.run());
+    let digest_scheduler = DigestScheduler::new(
+        digest_service.clone(),
+        Duration::from_secs(config.digest_poll_interval_seconds),
+    );
+    tokio::spawn(digest_scheduler.run());
+    if let Some(bounce_directory) = &config.bounce_directory {
+        let bounce_watcher = BounceWatcher::new(
+            bounce_service,
+            bounce_directory.into(),
+            Duration::from_secs(config.bounce_poll_interval_seconds),
+        );
+        tokio::spawn(bounce_watcher.run());
+    }
+
+    info!("Workers initialized, Initializing Handler");
+    if tracker.is_some() {
+        let tracking_url = format!("{}:{}", app_host, config.tracking_port)
+            .parse()
+            .unwrap();
+        let tracking_server = axum::Server::bind(&tracking_url)
+            .serve(tracking_router(tracking_service.clone()).into_make_service());
+        info!("Tracking endpoint ready at {:#?}!", tracking_url);
+        tokio::spawn(async move {
+            if let Err(err) = tracking_server.await {
+                error!("tracking endpoint stopped: {:?}", err);
+            }
+        });
+    }
+    let request_handler = RequestHandler::new(
+        subscriber_service,
+        group_service,
+        email_service,
+        campaign_service,
+        delivery_service,
+        tracking_service,
+        identity_service,
+        template_service,
+        idempotency_service,
+        digest_service,
+    );
 
     info!("Service ready for request at {:#?}!", app_url);
     Server::builder()
@@ -0,0 +1,3 @@
+pub mod email {
+    tonic::include_proto!("email");
+}
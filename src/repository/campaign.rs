@@ -0,0 +1,638 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::{query, query_as, types::time::OffsetDateTime, FromRow};
+
+use super::{
+    delivery::{MessageClass, CLAIM_LEASE_SECONDS},
+    group::GroupEntity,
+};
+
+use crate::proto::email::CampaignResponse;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CampaignStatus {
+    Draft,
+    Scheduled,
+    Sending,
+    Sent,
+    Cancelled,
+}
+
+impl CampaignStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CampaignStatus::Draft => "draft",
+            CampaignStatus::Scheduled => "scheduled",
+            CampaignStatus::Sending => "sending",
+            CampaignStatus::Sent => "sent",
+            CampaignStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "draft" => Some(CampaignStatus::Draft),
+            "scheduled" => Some(CampaignStatus::Scheduled),
+            "sending" => Some(CampaignStatus::Sending),
+            "sent" => Some(CampaignStatus::Sent),
+            "cancelled" => Some(CampaignStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, FromRow)]
+pub struct CampaignEntity {
+    pub id: i64,
+    pub name: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    pub markdown_body: String,
+    pub sender: String,
+    pub identity: String,
+    pub template: String,
+    pub locale: String,
+    pub local_send_time: String,
+    pub message_class: String,
+    pub status: String,
+    pub scheduled_at: Option<OffsetDateTime>,
+    pub sent_at: Option<OffsetDateTime>,
+    pub recipient_count: Option<i64>,
+}
+
+impl CampaignEntity {
+    pub fn into_campaign_response(self, groups: Vec<String>) -> CampaignResponse {
+        CampaignResponse {
+            id: self.id,
+            name: self.name,
+            subject: self.subject,
+            text_body: self.text_body,
+            html_body: self.html_body,
+            markdown_body: self.markdown_body,
+            sender: self.sender,
+            identity: self.identity,
+            template: self.template,
+            locale: self.locale,
+            local_send_time: self.local_send_time,
+            message_class: self.message_class,
+            status: self.status,
+            groups,
+            scheduled_at: self
+                .scheduled_at
+                .map_or(0, |scheduled_at| scheduled_at.unix_timestamp()),
+            sent_at: self.sent_at.map_or(0, |sent_at| sent_at.unix_timestamp()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NewCampaign {
+    pub name: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    pub markdown_body: String,
+    pub sender: String,
+    pub identity: String,
+    pub template: String,
+    pub locale: String,
+    pub local_send_time: String,
+    pub message_class: MessageClass,
+}
+
+#[automock]
+#[async_trait]
+pub trait CampaignRepositoryTrait {
+    async fn add_campaign(
+        &self,
+        campaign: &NewCampaign,
+        groups: &[GroupEntity],
+    ) -> anyhow::Result<CampaignEntity>;
+    async fn update_campaign(
+        &self,
+        id: i64,
+        campaign: &NewCampaign,
+        groups: &[GroupEntity],
+    ) -> anyhow::Result<Option<CampaignEntity>>;
+    async fn get_campaign(&self, id: i64) -> anyhow::Result<Option<CampaignEntity>>;
+    async fn list_campaigns(
+        &self,
+        status: Option<CampaignStatus>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<Vec<CampaignEntity>>;
+    async fn get_campaigns_count(&self, status: Option<CampaignStatus>) -> anyhow::Result<i64>;
+    async fn list_campaign_groups(&self, id: i64) -> anyhow::Result<Vec<GroupEntity>>;
+    async fn list_campaign_recipients(&self, id: i64) -> anyhow::Result<Vec<String>>;
+    async fn list_due_campaigns(&self) -> anyhow::Result<Vec<CampaignEntity>>;
+    async fn list_campaigns_with_due_deliveries(&self) -> anyhow::Result<Vec<CampaignEntity>>;
+    async fn transition_campaign(
+        &self,
+        id: i64,
+        from: &[CampaignStatus],
+        to: CampaignStatus,
+        scheduled_at: Option<OffsetDateTime>,
+    ) -> anyhow::Result<Option<CampaignEntity>>;
+    async fn set_recipient_count(&self, id: i64, recipient_count: i64) -> anyhow::Result<()>;
+}
+
+pub type DynCampaignRepositoryTrait = Arc<dyn CampaignRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct CampaignRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl CampaignRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl CampaignRepositoryTrait for CampaignRepository {
+    async fn add_campaign(
+        &self,
+        campaign: &NewCampaign,
+        groups: &[GroupEntity],
+    ) -> anyhow::Result<CampaignEntity> {
+        let mut transaction = self.pool.begin().await?;
+
+        let campaign_entity = query_as!(
+            CampaignEntity,
+            r#"
+                insert into campaign (
+                        name,
+                        subject,
+                        text_body,
+                        html_body,
+                        markdown_body,
+                        sender,
+                        identity,
+                        template,
+                        locale,
+                        local_send_time,
+                        message_class
+                    )
+                values (
+                        $1::varchar,
+                        $2::varchar,
+                        $3::text,
+                        $4::text,
+                        $5::text,
+                        $6::varchar,
+                        $7::varchar,
+                        $8::varchar,
+                        $9::varchar,
+                        $10::varchar,
+                        $11::varchar
+                    )
+                returning
+                    id,
+                    name,
+                    subject,
+                    text_body,
+                    html_body,
+                    sender,
+                    status,
+                    scheduled_at,
+                    sent_at,
+                    identity,
+                    template,
+                    locale,
+                    markdown_body,
+                    local_send_time,
+                    message_class,
+                    recipient_count
+            "#,
+            campaign.name,
+            campaign.subject,
+            campaign.text_body,
+            campaign.html_body,
+            campaign.markdown_body,
+            campaign.sender,
+            campaign.identity,
+            campaign.template,
+            campaign.locale,
+            campaign.local_send_time,
+            campaign.message_class.as_str(),
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .context("an unexpected error occured while creating the campaign")?;
+
+        let group_ids = groups.iter().map(|group| group.id).collect::<Vec<i64>>();
+        query!(
+            r#"
+                insert into campaign_group (
+                        campaign_id,
+                        group_id
+                    )
+                select
+                    $1::bigint,
+                    group_id
+                from unnest($2::bigint[]) as target(group_id)
+            "#,
+            campaign_entity.id,
+            &group_ids,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while assigning the campaign groups")?;
+
+        transaction.commit().await?;
+
+        Ok(campaign_entity)
+    }
+
+    async fn update_campaign(
+        &self,
+        id: i64,
+        campaign: &NewCampaign,
+        groups: &[GroupEntity],
+    ) -> anyhow::Result<Option<CampaignEntity>> {
+        let mut transaction = self.pool.begin().await?;
+
+        let campaign_entity = query_as!(
+            CampaignEntity,
+            r#"
+                update campaign
+                set
+                    name = $2::varchar,
+                    subject = $3::varchar,
+                    text_body = $4::text,
+                    html_body = $5::text,
+                    markdown_body = $6::text,
+                    sender = $7::varchar,
+                    identity = $8::varchar,
+                    template = $9::varchar,
+                    locale = $10::varchar,
+                    local_send_time = $11::varchar,
+                    message_class = $12::varchar,
+                    updated_at = current_timestamp
+                where
+                    id = $1::bigint
+                    and status in ('draft', 'scheduled')
+                returning
+                    id,
+                    name,
+                    subject,
+                    text_body,
+                    html_body,
+                    sender,
+                    status,
+                    scheduled_at,
+                    sent_at,
+                    identity,
+                    template,
+                    locale,
+                    markdown_body,
+                    local_send_time,
+                    message_class,
+                    recipient_count
+            "#,
+            id,
+            campaign.name,
+            campaign.subject,
+            campaign.text_body,
+            campaign.html_body,
+            campaign.markdown_body,
+            campaign.sender,
+            campaign.identity,
+            campaign.template,
+            campaign.locale,
+            campaign.local_send_time,
+            campaign.message_class.as_str(),
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .context("an unexpected error occured while updating the campaign")?;
+
+        if campaign_entity.is_some() {
+            let group_ids = groups.iter().map(|group| group.id).collect::<Vec<i64>>();
+            query!(
+                r#"
+                    delete from campaign_group
+                    where campaign_id = $1::bigint
+                "#,
+                id,
+            )
+            .execute(&mut *transaction)
+            .await
+            .context("an unexpected error occured while clearing the campaign groups")?;
+            query!(
+                r#"
+                    insert into campaign_group (
+                            campaign_id,
+                            group_id
+                        )
+                    select
+                        $1::bigint,
+                        group_id
+                    from unnest($2::bigint[]) as target(group_id)
+                "#,
+                id,
+                &group_ids,
+            )
+            .execute(&mut *transaction)
+            .await
+            .context("an unexpected error occured while assigning the campaign groups")?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(campaign_entity)
+    }
+
+    async fn get_campaign(&self, id: i64) -> anyhow::Result<Option<CampaignEntity>> {
+        query_as!(
+            CampaignEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    subject,
+                    text_body,
+                    html_body,
+                    markdown_body,
+                    sender,
+                    identity,
+                    template,
+                    locale,
+                    local_send_time,
+                    message_class,
+                    status,
+                    scheduled_at,
+                    sent_at,
+                    recipient_count
+                from campaign
+                where id = $1::bigint
+            "#,
+            id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while searching for campaign")
+    }
+
+    async fn list_campaigns(
+        &self,
+        status: Option<CampaignStatus>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<Vec<CampaignEntity>> {
+        query_as!(
+            CampaignEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    subject,
+                    text_body,
+                    html_body,
+                    markdown_body,
+                    sender,
+                    identity,
+                    template,
+                    locale,
+                    local_send_time,
+                    message_class,
+                    status,
+                    scheduled_at,
+                    sent_at,
+                    recipient_count
+                from campaign
+                where $1::varchar is null or status = $1::varchar
+                order by created_at desc, id desc
+                limit $2::bigint
+                offset $3::bigint
+            "#,
+            status.map(|status| status.as_str()),
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the campaign list")
+    }
+
+    async fn get_campaigns_count(&self, status: Option<CampaignStatus>) -> anyhow::Result<i64> {
+        let count_result = query!(
+            r#"
+                select
+                    count(*)
+                from campaign
+                where $1::varchar is null or status = $1::varchar
+            "#,
+            status.map(|status| status.as_str()),
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count_result.count.unwrap())
+    }
+
+    async fn list_campaign_groups(&self, id: i64) -> anyhow::Result<Vec<GroupEntity>> {
+        query_as!(
+            GroupEntity,
+            r#"
+                select
+                    sg.id as id,
+                    sg.name as name,
+                    sg.description as description
+                from subscription_group as sg
+                join campaign_group as cg
+                on sg.id = cg.group_id
+                where cg.campaign_id = $1::bigint
+                order by sg.name
+            "#,
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the campaign groups")
+    }
+
+    async fn list_campaign_recipients(&self, id: i64) -> anyhow::Result<Vec<String>> {
+        let recipients = query!(
+            r#"
+                select distinct
+                    s.email as "email!"
+                from subscriber as s
+                join campaign_group as cg
+                on s.group_id = cg.group_id
+                where cg.campaign_id = $1::bigint
+                order by s.email
+            "#,
+            id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the campaign recipients")?;
+
+        Ok(recipients
+            .into_iter()
+            .map(|recipient| recipient.email)
+            .collect())
+    }
+
+    async fn list_due_campaigns(&self) -> anyhow::Result<Vec<CampaignEntity>> {
+        query_as!(
+            CampaignEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    subject,
+                    text_body,
+                    html_body,
+                    markdown_body,
+                    sender,
+                    identity,
+                    template,
+                    locale,
+                    local_send_time,
+                    message_class,
+                    status,
+                    scheduled_at,
+                    sent_at,
+                    recipient_count
+                from campaign
+                where
+                    status = 'scheduled'
+                    and scheduled_at <= current_timestamp
+                order by scheduled_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the due campaigns")
+    }
+
+    async fn list_campaigns_with_due_deliveries(&self) -> anyhow::Result<Vec<CampaignEntity>> {
+        query_as!(
+            CampaignEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    subject,
+                    text_body,
+                    html_body,
+                    markdown_body,
+                    sender,
+                    identity,
+                    template,
+                    locale,
+                    local_send_time,
+                    message_class,
+                    status,
+                    scheduled_at,
+                    sent_at,
+                    recipient_count
+                from campaign
+                where exists (
+                    select 1
+                    from delivery
+                    where
+                        delivery.campaign_id = campaign.id
+                        and (
+                            (
+                                delivery.status = 'queued'
+                                and delivery.send_after <= current_timestamp
+                            )
+                            or (
+                                delivery.status = 'sending'
+                                and delivery.updated_at <= current_timestamp
+                                    - make_interval(secs => $1::bigint)
+                            )
+                        )
+                )
+                order by id
+            "#,
+            CLAIM_LEASE_SECONDS,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the campaigns with due deliveries")
+    }
+
+    async fn transition_campaign(
+        &self,
+        id: i64,
+        from: &[CampaignStatus],
+        to: CampaignStatus,
+        scheduled_at: Option<OffsetDateTime>,
+    ) -> anyhow::Result<Option<CampaignEntity>> {
+        let from = from
+            .iter()
+            .map(|status| status.as_str().to_string())
+            .collect::<Vec<String>>();
+
+        query_as!(
+            CampaignEntity,
+            r#"
+                update campaign
+                set
+                    status = $3::varchar,
+                    scheduled_at = coalesce($4::timestamptz, scheduled_at),
+                    sent_at = case
+                        when $3::varchar = 'sent' then current_timestamp
+                        else sent_at
+                    end,
+                    updated_at = current_timestamp
+                where
+                    id = $1::bigint
+                    and status = any($2::varchar[])
+                returning
+                    id,
+                    name,
+                    subject,
+                    text_body,
+                    html_body,
+                    sender,
+                    status,
+                    scheduled_at,
+                    sent_at,
+                    identity,
+                    template,
+                    locale,
+                    markdown_body,
+                    local_send_time,
+                    message_class,
+                    recipient_count
+            "#,
+            id,
+            &from,
+            to.as_str(),
+            scheduled_at,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while updating the campaign status")
+    }
+
+    async fn set_recipient_count(&self, id: i64, recipient_count: i64) -> anyhow::Result<()> {
+        query!(
+            r#"
+                update campaign
+                set
+                    recipient_count = $2::bigint,
+                    updated_at = current_timestamp
+                where id = $1::bigint
+            "#,
+            id,
+            recipient_count,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while updating the campaign recipient count")?;
+
+        Ok(())
+    }
+}
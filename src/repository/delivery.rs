@@ -0,0 +1,587 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::{query, query_as, types::time::OffsetDateTime, FromRow};
+
+use crate::proto::email::DeliveryResponse;
+
+/// Seconds a claimed delivery stays with the instance that claimed it, after
+/// which it is given up as abandoned and may be claimed again.
+pub const CLAIM_LEASE_SECONDS: i64 = 15 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeliveryStatus {
+    Queued,
+    Sending,
+    Sent,
+    Failed,
+    Bounced,
+    Capped,
+    Skipped,
+    Cancelled,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Sending => "sending",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Bounced => "bounced",
+            DeliveryStatus::Capped => "capped",
+            DeliveryStatus::Skipped => "skipped",
+            DeliveryStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Transactional messages are ones a recipient asked for, such as password
+/// resets, marketing messages are everything they may opt out of.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MessageClass {
+    Transactional,
+    #[default]
+    Marketing,
+}
+
+impl MessageClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageClass::Transactional => "transactional",
+            MessageClass::Marketing => "marketing",
+        }
+    }
+
+    pub fn parse(message_class: &str) -> Option<Self> {
+        match message_class {
+            "transactional" => Some(MessageClass::Transactional),
+            "marketing" => Some(MessageClass::Marketing),
+            _ => None,
+        }
+    }
+}
+
+#[derive(FromRow)]
+pub struct DeliveryEntity {
+    pub id: i64,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    pub message_id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub campaign_id: Option<i64>,
+    pub status: String,
+    pub smtp_response: String,
+    pub sent_at: Option<OffsetDateTime>,
+    pub verp_token: Option<String>,
+    pub message_class: String,
+}
+
+impl DeliveryEntity {
+    pub fn into_delivery_response(self) -> DeliveryResponse {
+        DeliveryResponse {
+            message_id: self.message_id,
+            recipient: self.recipient,
+            subject: self.subject,
+            campaign_id: self.campaign_id.unwrap_or_default(),
+            status: self.status,
+            smtp_response: self.smtp_response,
+            created_at: self.created_at.unix_timestamp(),
+            updated_at: self.updated_at.unix_timestamp(),
+            sent_at: self.sent_at.map_or(0, |sent_at| sent_at.unix_timestamp()),
+        }
+    }
+}
+
+pub struct NewDelivery {
+    pub message_id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub campaign_id: Option<i64>,
+    pub verp_token: Option<String>,
+    pub send_after: Option<OffsetDateTime>,
+    pub message_class: MessageClass,
+}
+
+#[automock]
+#[async_trait]
+pub trait DeliveryRepositoryTrait {
+    async fn add_delivery(&self, delivery: &NewDelivery) -> anyhow::Result<DeliveryEntity>;
+    async fn update_delivery_status(
+        &self,
+        id: i64,
+        status: DeliveryStatus,
+        smtp_response: &str,
+    ) -> anyhow::Result<Option<DeliveryEntity>>;
+    async fn get_delivery(&self, id: i64) -> anyhow::Result<Option<DeliveryEntity>>;
+    async fn get_delivery_by_message_id(
+        &self,
+        message_id: &str,
+    ) -> anyhow::Result<Option<DeliveryEntity>>;
+    async fn get_delivery_by_verp_token(
+        &self,
+        verp_token: &str,
+    ) -> anyhow::Result<Option<DeliveryEntity>>;
+    async fn list_deliveries(
+        &self,
+        recipient: Option<String>,
+        campaign_id: Option<i64>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<Vec<DeliveryEntity>>;
+    async fn get_deliveries_count(
+        &self,
+        recipient: Option<String>,
+        campaign_id: Option<i64>,
+    ) -> anyhow::Result<i64>;
+    async fn count_campaign_deliveries_since(
+        &self,
+        recipients: &[String],
+        since: OffsetDateTime,
+        excluded_campaign_id: Option<i64>,
+    ) -> anyhow::Result<HashMap<String, i64>>;
+    async fn claim_due_deliveries(&self, campaign_id: i64) -> anyhow::Result<Vec<DeliveryEntity>>;
+    async fn defer_delivery(
+        &self,
+        id: i64,
+        send_after: OffsetDateTime,
+    ) -> anyhow::Result<Option<DeliveryEntity>>;
+    async fn cancel_queued_deliveries(&self, campaign_id: i64) -> anyhow::Result<u64>;
+    async fn count_campaign_deliveries_by_status(
+        &self,
+        campaign_id: i64,
+    ) -> anyhow::Result<HashMap<String, i64>>;
+}
+
+pub type DynDeliveryRepositoryTrait = Arc<dyn DeliveryRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct DeliveryRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl DeliveryRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl DeliveryRepositoryTrait for DeliveryRepository {
+    async fn add_delivery(&self, delivery: &NewDelivery) -> anyhow::Result<DeliveryEntity> {
+        query_as!(
+            DeliveryEntity,
+            r#"
+                insert into delivery (
+                        message_id,
+                        recipient,
+                        subject,
+                        campaign_id,
+                        verp_token,
+                        send_after,
+                        message_class
+                    )
+                values (
+                        $1::varchar,
+                        $2::varchar,
+                        $3::varchar,
+                        $4::bigint,
+                        $5::varchar,
+                        $6::timestamptz,
+                        $7::varchar
+                    )
+                returning
+                    id,
+                    created_at,
+                    updated_at,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class
+            "#,
+            delivery.message_id,
+            delivery.recipient,
+            delivery.subject,
+            delivery.campaign_id,
+            delivery.verp_token,
+            delivery.send_after,
+            delivery.message_class.as_str(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("an unexpected error occured while recording the delivery")
+    }
+
+    async fn update_delivery_status(
+        &self,
+        id: i64,
+        status: DeliveryStatus,
+        smtp_response: &str,
+    ) -> anyhow::Result<Option<DeliveryEntity>> {
+        query_as!(
+            DeliveryEntity,
+            r#"
+                update delivery
+                set
+                    status = $2::varchar,
+                    smtp_response = $3::varchar,
+                    sent_at = case
+                        when $2::varchar = 'sent' then current_timestamp
+                        else sent_at
+                    end,
+                    updated_at = current_timestamp
+                where id = $1::bigint
+                returning
+                    id,
+                    created_at,
+                    updated_at,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class
+            "#,
+            id,
+            status.as_str(),
+            smtp_response,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while updating the delivery status")
+    }
+
+    async fn get_delivery(&self, id: i64) -> anyhow::Result<Option<DeliveryEntity>> {
+        query_as!(
+            DeliveryEntity,
+            r#"
+                select
+                    id,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class,
+                    created_at,
+                    updated_at
+                from delivery
+                where id = $1::bigint
+            "#,
+            id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while searching for delivery")
+    }
+
+    async fn get_delivery_by_message_id(
+        &self,
+        message_id: &str,
+    ) -> anyhow::Result<Option<DeliveryEntity>> {
+        query_as!(
+            DeliveryEntity,
+            r#"
+                select
+                    id,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class,
+                    created_at,
+                    updated_at
+                from delivery
+                where message_id = $1::varchar
+            "#,
+            message_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while searching for delivery")
+    }
+
+    async fn get_delivery_by_verp_token(
+        &self,
+        verp_token: &str,
+    ) -> anyhow::Result<Option<DeliveryEntity>> {
+        query_as!(
+            DeliveryEntity,
+            r#"
+                select
+                    id,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class,
+                    created_at,
+                    updated_at
+                from delivery
+                where verp_token = $1::varchar
+            "#,
+            verp_token,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while searching for delivery")
+    }
+
+    async fn list_deliveries(
+        &self,
+        recipient: Option<String>,
+        campaign_id: Option<i64>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> anyhow::Result<Vec<DeliveryEntity>> {
+        query_as!(
+            DeliveryEntity,
+            r#"
+                select
+                    id,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class,
+                    created_at,
+                    updated_at
+                from delivery
+                where
+                    ($1::varchar is null or recipient = $1::varchar)
+                    and ($2::bigint is null or campaign_id = $2::bigint)
+                order by created_at desc, id desc
+                limit $3::bigint
+                offset $4::bigint
+            "#,
+            recipient,
+            campaign_id,
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the delivery list")
+    }
+
+    async fn get_deliveries_count(
+        &self,
+        recipient: Option<String>,
+        campaign_id: Option<i64>,
+    ) -> anyhow::Result<i64> {
+        let count_result = query!(
+            r#"
+                select
+                    count(*)
+                from delivery
+                where
+                    ($1::varchar is null or recipient = $1::varchar)
+                    and ($2::bigint is null or campaign_id = $2::bigint)
+            "#,
+            recipient,
+            campaign_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count_result.count.unwrap())
+    }
+
+    async fn count_campaign_deliveries_since(
+        &self,
+        recipients: &[String],
+        since: OffsetDateTime,
+        excluded_campaign_id: Option<i64>,
+    ) -> anyhow::Result<HashMap<String, i64>> {
+        // Failed and capped deliveries never reached the recipient, and
+        // transactional ones never count towards a cap. Counts are keyed by
+        // the lowercased address.
+        let counts = query!(
+            r#"
+                select
+                    lower(recipient) as "recipient!",
+                    count(*) as "count!"
+                from delivery
+                where
+                    lower(recipient) in (
+                        select lower(address)
+                        from unnest($1::varchar[]) as address
+                    )
+                    and campaign_id is not null
+                    and campaign_id is distinct from $3::bigint
+                    and message_class = 'marketing'
+                    and status in ('queued', 'sending', 'sent', 'bounced')
+                    and created_at >= $2::timestamptz
+                group by lower(recipient)
+            "#,
+            recipients,
+            since,
+            excluded_campaign_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while counting the campaign deliveries")?;
+
+        Ok(counts
+            .into_iter()
+            .map(|count| (count.recipient, count.count))
+            .collect())
+    }
+
+    async fn claim_due_deliveries(&self, campaign_id: i64) -> anyhow::Result<Vec<DeliveryEntity>> {
+        // Rows another instance is claiming are skipped rather than waited
+        // for, so each due delivery is handed to a single sender.
+        query_as!(
+            DeliveryEntity,
+            r#"
+                update delivery
+                set
+                    status = 'sending',
+                    updated_at = current_timestamp
+                where id in (
+                    select id
+                    from delivery
+                    where
+                        campaign_id = $1::bigint
+                        and (
+                            (status = 'queued' and send_after <= current_timestamp)
+                            or (
+                                status = 'sending'
+                                and updated_at <= current_timestamp
+                                    - make_interval(secs => $2::bigint)
+                            )
+                        )
+                    for update skip locked
+                )
+                returning
+                    id,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class,
+                    created_at,
+                    updated_at
+            "#,
+            campaign_id,
+            CLAIM_LEASE_SECONDS,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while claiming the due deliveries")
+    }
+
+    async fn defer_delivery(
+        &self,
+        id: i64,
+        send_after: OffsetDateTime,
+    ) -> anyhow::Result<Option<DeliveryEntity>> {
+        query_as!(
+            DeliveryEntity,
+            r#"
+                update delivery
+                set
+                    status = 'queued',
+                    send_after = $2::timestamptz,
+                    updated_at = current_timestamp
+                where
+                    id = $1::bigint
+                    and status in ('queued', 'sending')
+                returning
+                    id,
+                    created_at,
+                    updated_at,
+                    message_id,
+                    recipient,
+                    subject,
+                    campaign_id,
+                    status,
+                    smtp_response,
+                    sent_at,
+                    verp_token,
+                    message_class
+            "#,
+            id,
+            send_after,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while deferring the delivery")
+    }
+
+    async fn cancel_queued_deliveries(&self, campaign_id: i64) -> anyhow::Result<u64> {
+        let result = query!(
+            r#"
+                update delivery
+                set
+                    status = 'cancelled',
+                    updated_at = current_timestamp
+                where
+                    campaign_id = $1::bigint
+                    and status = 'queued'
+            "#,
+            campaign_id,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while cancelling the queued deliveries")?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn count_campaign_deliveries_by_status(
+        &self,
+        campaign_id: i64,
+    ) -> anyhow::Result<HashMap<String, i64>> {
+        let counts = query!(
+            r#"
+                select
+                    status,
+                    count(*) as "count!"
+                from delivery
+                where campaign_id = $1::bigint
+                group by status
+            "#,
+            campaign_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while counting the campaign deliveries")?;
+
+        Ok(counts
+            .into_iter()
+            .map(|count| (count.status, count.count))
+            .collect())
+    }
+}
@@ -0,0 +1,373 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::{query, query_as, types::time::OffsetDateTime, FromRow};
+use time::Duration;
+
+use crate::proto::email::DigestTypeResponse;
+
+/// Seconds claimed digest items stay with the instance that claimed them,
+/// after which they are given up as abandoned and may be claimed again.
+pub const CLAIM_LEASE_SECONDS: i64 = 15 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DigestCadence {
+    Hourly,
+    Daily,
+}
+
+impl DigestCadence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestCadence::Hourly => "hourly",
+            DigestCadence::Daily => "daily",
+        }
+    }
+
+    pub fn parse(cadence: &str) -> Option<Self> {
+        match cadence {
+            "hourly" => Some(DigestCadence::Hourly),
+            "daily" => Some(DigestCadence::Daily),
+            _ => None,
+        }
+    }
+
+    /// How long the oldest pending item waits before its digest goes out.
+    pub fn period(&self) -> Duration {
+        match self {
+            DigestCadence::Hourly => Duration::hours(1),
+            DigestCadence::Daily => Duration::days(1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DigestItemStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+impl DigestItemStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestItemStatus::Pending => "pending",
+            DigestItemStatus::Sent => "sent",
+            DigestItemStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(FromRow)]
+pub struct DigestTypeEntity {
+    pub id: i64,
+    pub name: String,
+    pub cadence: String,
+    pub template: String,
+}
+
+impl DigestTypeEntity {
+    pub fn into_digest_type_response(self) -> DigestTypeResponse {
+        DigestTypeResponse {
+            name: self.name,
+            cadence: self.cadence,
+            template: self.template,
+        }
+    }
+}
+
+pub struct NewDigestType {
+    pub name: String,
+    pub cadence: DigestCadence,
+    pub template: String,
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct DigestItemEntity {
+    pub id: i64,
+    pub title: String,
+    pub body: String,
+}
+
+pub struct NewDigestItem {
+    pub recipient: String,
+    pub title: String,
+    pub body: String,
+}
+
+#[automock]
+#[async_trait]
+pub trait DigestRepositoryTrait {
+    async fn save_digest_type(
+        &self,
+        digest_type: &NewDigestType,
+    ) -> anyhow::Result<DigestTypeEntity>;
+    async fn get_digest_type(&self, name: &str) -> anyhow::Result<Option<DigestTypeEntity>>;
+    async fn list_digest_types(&self) -> anyhow::Result<Vec<DigestTypeEntity>>;
+    async fn add_digest_item(
+        &self,
+        digest_type_id: i64,
+        item: &NewDigestItem,
+    ) -> anyhow::Result<DigestItemEntity>;
+    async fn list_due_recipients(
+        &self,
+        digest_type_id: i64,
+        queued_before: OffsetDateTime,
+    ) -> anyhow::Result<Vec<String>>;
+    async fn claim_pending_items(
+        &self,
+        digest_type_id: i64,
+        recipient: &str,
+    ) -> anyhow::Result<Vec<DigestItemEntity>>;
+    async fn update_items_status(
+        &self,
+        ids: &[i64],
+        status: DigestItemStatus,
+        message_id: Option<String>,
+    ) -> anyhow::Result<u64>;
+}
+
+pub type DynDigestRepositoryTrait = Arc<dyn DigestRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct DigestRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl DigestRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl DigestRepositoryTrait for DigestRepository {
+    async fn save_digest_type(
+        &self,
+        digest_type: &NewDigestType,
+    ) -> anyhow::Result<DigestTypeEntity> {
+        query_as!(
+            DigestTypeEntity,
+            r#"
+                insert into digest_type (
+                        name,
+                        cadence,
+                        template
+                    )
+                values (
+                        $1::varchar,
+                        $2::varchar,
+                        $3::varchar
+                    )
+                on conflict (name) do update
+                set
+                    cadence = excluded.cadence,
+                    template = excluded.template,
+                    updated_at = current_timestamp
+                returning
+                    id,
+                    name,
+                    cadence,
+                    template
+            "#,
+            digest_type.name,
+            digest_type.cadence.as_str(),
+            digest_type.template,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("an unexpected error occured while saving the digest type")
+    }
+
+    async fn get_digest_type(&self, name: &str) -> anyhow::Result<Option<DigestTypeEntity>> {
+        query_as!(
+            DigestTypeEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    cadence,
+                    template
+                from digest_type
+                where name = $1::varchar
+            "#,
+            name,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while searching for digest type")
+    }
+
+    async fn list_digest_types(&self) -> anyhow::Result<Vec<DigestTypeEntity>> {
+        query_as!(
+            DigestTypeEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    cadence,
+                    template
+                from digest_type
+                order by name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the digest types")
+    }
+
+    async fn add_digest_item(
+        &self,
+        digest_type_id: i64,
+        item: &NewDigestItem,
+    ) -> anyhow::Result<DigestItemEntity> {
+        query_as!(
+            DigestItemEntity,
+            r#"
+                insert into digest_item (
+                        digest_type_id,
+                        recipient,
+                        title,
+                        body
+                    )
+                values (
+                        $1::bigint,
+                        $2::varchar,
+                        $3::varchar,
+                        $4::text
+                    )
+                returning
+                    id,
+                    title,
+                    body
+            "#,
+            digest_type_id,
+            item.recipient,
+            item.title,
+            item.body,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("an unexpected error occured while queueing the digest item")
+    }
+
+    async fn list_due_recipients(
+        &self,
+        digest_type_id: i64,
+        queued_before: OffsetDateTime,
+    ) -> anyhow::Result<Vec<String>> {
+        let recipients = query!(
+            r#"
+                select
+                    recipient
+                from digest_item
+                where
+                    digest_type_id = $1::bigint
+                    and (
+                        status = 'pending'
+                        or (
+                            status = 'sending'
+                            and updated_at <= current_timestamp
+                                - make_interval(secs => $3::bigint)
+                        )
+                    )
+                group by recipient
+                having min(created_at) <= $2::timestamptz
+                order by recipient
+            "#,
+            digest_type_id,
+            queued_before,
+            CLAIM_LEASE_SECONDS,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the due digest recipients")?;
+
+        Ok(recipients
+            .into_iter()
+            .map(|recipient| recipient.recipient)
+            .collect())
+    }
+
+    async fn claim_pending_items(
+        &self,
+        digest_type_id: i64,
+        recipient: &str,
+    ) -> anyhow::Result<Vec<DigestItemEntity>> {
+        // Items another instance is claiming are skipped rather than waited
+        // for, so each item goes out in a single digest.
+        query_as!(
+            DigestItemEntity,
+            r#"
+                with claimed as (
+                    update digest_item
+                    set
+                        status = 'sending',
+                        updated_at = current_timestamp
+                    where id in (
+                        select id
+                        from digest_item
+                        where
+                            digest_type_id = $1::bigint
+                            and recipient = $2::varchar
+                            and (
+                                status = 'pending'
+                                or (
+                                    status = 'sending'
+                                    and updated_at <= current_timestamp
+                                        - make_interval(secs => $3::bigint)
+                                )
+                            )
+                        for update skip locked
+                    )
+                    returning
+                        id,
+                        title,
+                        body,
+                        created_at
+                )
+                select
+                    id as "id!",
+                    title as "title!",
+                    body as "body!"
+                from claimed
+                order by created_at, id
+            "#,
+            digest_type_id,
+            recipient,
+            CLAIM_LEASE_SECONDS,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while claiming the pending digest items")
+    }
+
+    async fn update_items_status(
+        &self,
+        ids: &[i64],
+        status: DigestItemStatus,
+        message_id: Option<String>,
+    ) -> anyhow::Result<u64> {
+        let result = query!(
+            r#"
+                update digest_item
+                set
+                    status = $2::varchar,
+                    message_id = $3::varchar,
+                    updated_at = current_timestamp
+                where id = any($1::bigint[])
+            "#,
+            ids,
+            status.as_str(),
+            message_id,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while updating the digest items")?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -4,15 +4,13 @@ use anyhow::Context;
 use async_trait::async_trait;
 use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
 use mockall::automock;
-use sqlx::{query, query_as, types::time::OffsetDateTime, FromRow};
+use sqlx::{query, query_as, FromRow};
 
-use madtofan_microservice_common::email::groups_response::Group;
+use crate::proto::email::{groups_response::Group, GroupOperationResponse};
 
 #[derive(FromRow)]
 pub struct GroupEntity {
     pub id: i64,
-    pub created_at: OffsetDateTime,
-    pub updated_at: OffsetDateTime,
     pub name: String,
     pub description: String,
 }
@@ -26,9 +24,35 @@ impl GroupEntity {
     }
 }
 
+#[derive(Default)]
+pub struct GroupOperationSummary {
+    pub inserted: i64,
+    pub skipped: i64,
+    pub removed: i64,
+}
+
+impl GroupOperationSummary {
+    pub fn into_group_operation_response(self) -> GroupOperationResponse {
+        GroupOperationResponse {
+            inserted: self.inserted,
+            skipped: self.skipped,
+            removed: self.removed,
+        }
+    }
+}
+
+/// Footer templates a group uses instead of the service wide ones, an empty
+/// template keeps the service wide one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FooterTemplate {
+    pub text: String,
+    pub html: String,
+}
+
 #[automock]
 #[async_trait]
 pub trait GroupRepositoryTrait {
+    #[cfg(test)]
     async fn list_groups(&self) -> anyhow::Result<Vec<GroupEntity>>;
     async fn list_groups_by_sub(
         &self,
@@ -40,6 +64,30 @@ pub trait GroupRepositoryTrait {
     async fn get_group(&self, name: &str) -> anyhow::Result<Option<GroupEntity>>;
     async fn add_group(&self, name: &str, description: &str) -> anyhow::Result<GroupEntity>;
     async fn remove_group(&self, name: &str) -> anyhow::Result<Option<GroupEntity>>;
+    async fn copy_group_members(
+        &self,
+        from_group: &GroupEntity,
+        to_group: &GroupEntity,
+    ) -> anyhow::Result<GroupOperationSummary>;
+    async fn move_subscriber(
+        &self,
+        email: &str,
+        from_group: &GroupEntity,
+        to_group: &GroupEntity,
+    ) -> anyhow::Result<GroupOperationSummary>;
+    async fn merge_groups(
+        &self,
+        into_group: &GroupEntity,
+        from_group: &GroupEntity,
+    ) -> anyhow::Result<GroupOperationSummary>;
+    async fn set_group_footer(
+        &self,
+        group: &GroupEntity,
+        footer: Option<FooterTemplate>,
+    ) -> anyhow::Result<()>;
+    async fn get_group_footer(&self, group_id: i64) -> anyhow::Result<Option<FooterTemplate>>;
+    async fn set_group_seeds(&self, group: &GroupEntity, emails: &[String]) -> anyhow::Result<()>;
+    async fn list_group_seeds(&self, group_id: i64) -> anyhow::Result<Vec<String>>;
 }
 
 pub type DynGroupRepositoryTrait = Arc<dyn GroupRepositoryTrait + Send + Sync>;
@@ -57,6 +105,7 @@ impl GroupRepository {
 
 #[async_trait]
 impl GroupRepositoryTrait for GroupRepository {
+    #[cfg(test)]
     async fn list_groups(&self) -> anyhow::Result<Vec<GroupEntity>> {
         query_as!(
             GroupEntity,
@@ -64,9 +113,7 @@ impl GroupRepositoryTrait for GroupRepository {
                 select
                     id,
                     name,
-                    description,
-                    created_at,
-                    updated_at
+                    description
                 from subscription_group
             "#,
         )
@@ -88,9 +135,7 @@ impl GroupRepositoryTrait for GroupRepository {
                 select
                     sg.id as id,
                     sg.name as name,
-                    sg.description as description,
-                    sg.created_at as created_at,
-                    sg.updated_at as updated_at
+                    sg.description as description
                 from subscription_group as sg
                 join subscriber as s
                 on sg.id = s.group_id
@@ -138,9 +183,7 @@ impl GroupRepositoryTrait for GroupRepository {
                 select
                     id,
                     name,
-                    description,
-                    created_at,
-                    updated_at
+                    description
                 from subscription_group
                 where name = $1::varchar
             "#,
@@ -163,7 +206,10 @@ impl GroupRepositoryTrait for GroupRepository {
                         $1::varchar,
                         $2::varchar
                     )
-                returning *
+                returning
+                    id,
+                    name,
+                    description
             "#,
             name,
             description,
@@ -179,7 +225,10 @@ impl GroupRepositoryTrait for GroupRepository {
             r#"
                 delete from subscription_group 
                 where name = $1::varchar
-                returning *
+                returning
+                    id,
+                    name,
+                    description
             "#,
             name,
         )
@@ -187,4 +236,356 @@ impl GroupRepositoryTrait for GroupRepository {
         .await
         .context("an unexpected error occured while removing the subscription group")
     }
+
+    async fn copy_group_members(
+        &self,
+        from_group: &GroupEntity,
+        to_group: &GroupEntity,
+    ) -> anyhow::Result<GroupOperationSummary> {
+        let mut transaction = self.pool.begin().await?;
+
+        let member_count = query!(
+            r#"
+                select
+                    count(*)
+                from subscriber
+                where group_id = $1::bigint
+            "#,
+            from_group.id
+        )
+        .fetch_one(&mut *transaction)
+        .await?
+        .count
+        .unwrap_or_default();
+
+        let inserted = query!(
+            r#"
+                insert into subscriber (
+                        email,
+                        group_id,
+                        attributes
+                    )
+                select
+                    email,
+                    $2::bigint,
+                    attributes
+                from subscriber
+                where group_id = $1::bigint
+                on conflict (email, group_id) do nothing
+            "#,
+            from_group.id,
+            to_group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while copying group members")?
+        .rows_affected() as i64;
+
+        transaction.commit().await?;
+
+        Ok(GroupOperationSummary {
+            inserted,
+            skipped: member_count - inserted,
+            removed: 0,
+        })
+    }
+
+    async fn move_subscriber(
+        &self,
+        email: &str,
+        from_group: &GroupEntity,
+        to_group: &GroupEntity,
+    ) -> anyhow::Result<GroupOperationSummary> {
+        let mut transaction = self.pool.begin().await?;
+
+        let removed_subscriber = query!(
+            r#"
+                delete from subscriber
+                where
+                    email = $1::varchar
+                    and group_id = $2::bigint
+                returning attributes
+            "#,
+            email,
+            from_group.id,
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .context("an unexpected error occured while removing the subscriber")?;
+
+        let summary = match removed_subscriber {
+            Some(removed_subscriber) => {
+                let inserted = query!(
+                    r#"
+                        insert into subscriber (
+                                email,
+                                group_id,
+                                attributes
+                            )
+                        values (
+                                $1::varchar,
+                                $2::bigint,
+                                $3::jsonb
+                            )
+                        on conflict (email, group_id) do nothing
+                    "#,
+                    email,
+                    to_group.id,
+                    removed_subscriber.attributes,
+                )
+                .execute(&mut *transaction)
+                .await
+                .context("an unexpected error occured while moving the subscriber")?
+                .rows_affected() as i64;
+
+                GroupOperationSummary {
+                    inserted,
+                    skipped: 1 - inserted,
+                    removed: 1,
+                }
+            }
+            None => GroupOperationSummary::default(),
+        };
+
+        transaction.commit().await?;
+
+        Ok(summary)
+    }
+
+    async fn merge_groups(
+        &self,
+        into_group: &GroupEntity,
+        from_group: &GroupEntity,
+    ) -> anyhow::Result<GroupOperationSummary> {
+        let mut transaction = self.pool.begin().await?;
+
+        let inserted = query!(
+            r#"
+                insert into subscriber (
+                        email,
+                        group_id,
+                        attributes
+                    )
+                select
+                    email,
+                    $2::bigint,
+                    attributes
+                from subscriber
+                where group_id = $1::bigint
+                on conflict (email, group_id) do nothing
+            "#,
+            from_group.id,
+            into_group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while merging group members")?
+        .rows_affected() as i64;
+
+        let removed = query!(
+            r#"
+                delete from subscriber
+                where group_id = $1::bigint
+            "#,
+            from_group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while removing merged group members")?
+        .rows_affected() as i64;
+
+        // Campaigns, opt-outs and seeds of the merged group would otherwise be
+        // cascaded away with it.
+        query!(
+            r#"
+                insert into campaign_group (
+                        campaign_id,
+                        group_id
+                    )
+                select
+                    campaign_id,
+                    $2::bigint
+                from campaign_group
+                where group_id = $1::bigint
+                on conflict (campaign_id, group_id) do nothing
+            "#,
+            from_group.id,
+            into_group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while merging group campaigns")?;
+
+        query!(
+            r#"
+                update unsubscription
+                set group_id = $2::bigint
+                where
+                    group_id = $1::bigint
+                    and not exists (
+                        select 1
+                        from unsubscription as merged
+                        where
+                            merged.group_id = $2::bigint
+                            and lower(merged.email) = lower(unsubscription.email)
+                    )
+            "#,
+            from_group.id,
+            into_group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while merging group unsubscriptions")?;
+
+        query!(
+            r#"
+                insert into group_seed (
+                        group_id,
+                        email
+                    )
+                select
+                    $2::bigint,
+                    email
+                from group_seed
+                where group_id = $1::bigint
+                on conflict (group_id, email) do nothing
+            "#,
+            from_group.id,
+            into_group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while merging group seeds")?;
+
+        query!(
+            r#"
+                delete from subscription_group
+                where id = $1::bigint
+            "#,
+            from_group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while removing the merged group")?;
+
+        transaction.commit().await?;
+
+        Ok(GroupOperationSummary {
+            inserted,
+            skipped: removed - inserted,
+            removed,
+        })
+    }
+
+    async fn set_group_footer(
+        &self,
+        group: &GroupEntity,
+        footer: Option<FooterTemplate>,
+    ) -> anyhow::Result<()> {
+        let (footer_text, footer_html) = match footer {
+            Some(footer) => (Some(footer.text), Some(footer.html)),
+            None => (None, None),
+        };
+
+        query!(
+            r#"
+                update subscription_group
+                set
+                    footer_text = $2::text,
+                    footer_html = $3::text,
+                    updated_at = current_timestamp
+                where id = $1::bigint
+            "#,
+            group.id,
+            footer_text,
+            footer_html,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while setting the group footer")?;
+
+        Ok(())
+    }
+
+    async fn get_group_footer(&self, group_id: i64) -> anyhow::Result<Option<FooterTemplate>> {
+        let footer = query!(
+            r#"
+                select
+                    footer_text,
+                    footer_html
+                from subscription_group
+                where id = $1::bigint
+            "#,
+            group_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the group footer")?;
+
+        Ok(
+            footer.and_then(|footer| match (footer.footer_text, footer.footer_html) {
+                (None, None) => None,
+                (text, html) => Some(FooterTemplate {
+                    text: text.unwrap_or_default(),
+                    html: html.unwrap_or_default(),
+                }),
+            }),
+        )
+    }
+
+    async fn set_group_seeds(&self, group: &GroupEntity, emails: &[String]) -> anyhow::Result<()> {
+        let mut transaction = self.pool.begin().await?;
+
+        query!(
+            r#"
+                delete from group_seed
+                where group_id = $1::bigint
+            "#,
+            group.id,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while clearing the group seeds")?;
+
+        query!(
+            r#"
+                insert into group_seed (
+                        group_id,
+                        email
+                    )
+                select
+                    $1::bigint,
+                    email
+                from unnest($2::varchar[]) as new_seed(email)
+                on conflict (group_id, email) do nothing
+            "#,
+            group.id,
+            emails,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while setting the group seeds")?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn list_group_seeds(&self, group_id: i64) -> anyhow::Result<Vec<String>> {
+        let seeds = query!(
+            r#"
+                select
+                    email
+                from group_seed
+                where group_id = $1::bigint
+                order by email
+            "#,
+            group_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the group seeds")?;
+
+        Ok(seeds.into_iter().map(|seed| seed.email).collect())
+    }
 }
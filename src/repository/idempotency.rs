@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::{query, query_as, types::time::OffsetDateTime, FromRow};
+
+/// Seconds a pending key stays with the request that claimed it, after which
+/// the request is given up as abandoned and a retry may take the key over.
+pub const CLAIM_LEASE_SECONDS: i64 = 5 * 60;
+
+/// A key without a response or an error belongs to a request that is still
+/// running.
+#[derive(Clone, Debug, FromRow)]
+pub struct IdempotencyKeyEntity {
+    pub fingerprint: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+pub struct NewIdempotencyKey {
+    pub operation: String,
+    pub key: String,
+    pub fingerprint: String,
+    pub expires_at: OffsetDateTime,
+}
+
+#[automock]
+#[async_trait]
+pub trait IdempotencyRepositoryTrait {
+    /// Claims the key for a new request, or returns the record of the earlier
+    /// request that still holds it. A pending claim older than the lease is
+    /// taken over by a request with the same fingerprint.
+    async fn claim_key(
+        &self,
+        key: &NewIdempotencyKey,
+    ) -> anyhow::Result<Option<IdempotencyKeyEntity>>;
+    async fn complete_key(
+        &self,
+        operation: &str,
+        key: &str,
+        response: Option<String>,
+        error: Option<String>,
+    ) -> anyhow::Result<()>;
+    async fn remove_key(&self, operation: &str, key: &str) -> anyhow::Result<()>;
+}
+
+pub type DynIdempotencyRepositoryTrait = Arc<dyn IdempotencyRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct IdempotencyRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl IdempotencyRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepositoryTrait for IdempotencyRepository {
+    async fn claim_key(
+        &self,
+        key: &NewIdempotencyKey,
+    ) -> anyhow::Result<Option<IdempotencyKeyEntity>> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Expired keys are dropped as they are passed, so that they can be
+        // claimed again.
+        query!(
+            r#"
+                delete from idempotency_key
+                where expires_at <= current_timestamp
+            "#,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("an unexpected error occured while removing expired idempotency keys")?;
+
+        let claimed = query!(
+            r#"
+                insert into idempotency_key (
+                        operation,
+                        key,
+                        fingerprint,
+                        expires_at
+                    )
+                values (
+                        $1::varchar,
+                        $2::varchar,
+                        $3::varchar,
+                        $4::timestamptz
+                    )
+                on conflict (operation, key) do update
+                set
+                    expires_at = excluded.expires_at,
+                    claimed_at = current_timestamp
+                where
+                    idempotency_key.response is null
+                    and idempotency_key.error is null
+                    and idempotency_key.fingerprint = excluded.fingerprint
+                    and idempotency_key.claimed_at <= current_timestamp
+                        - make_interval(secs => $5::bigint)
+                returning id
+            "#,
+            key.operation,
+            key.key,
+            key.fingerprint,
+            key.expires_at,
+            CLAIM_LEASE_SECONDS,
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .context("an unexpected error occured while claiming the idempotency key")?;
+
+        let existing = match claimed {
+            Some(_) => None,
+            None => Some(
+                query_as!(
+                    IdempotencyKeyEntity,
+                    r#"
+                        select
+                            fingerprint,
+                            response,
+                            error
+                        from idempotency_key
+                        where
+                            operation = $1::varchar
+                            and key = $2::varchar
+                    "#,
+                    key.operation,
+                    key.key,
+                )
+                .fetch_one(&mut *transaction)
+                .await
+                .context("an unexpected error occured while obtaining the idempotency key")?,
+            ),
+        };
+
+        transaction.commit().await?;
+
+        Ok(existing)
+    }
+
+    async fn complete_key(
+        &self,
+        operation: &str,
+        key: &str,
+        response: Option<String>,
+        error: Option<String>,
+    ) -> anyhow::Result<()> {
+        query!(
+            r#"
+                update idempotency_key
+                set
+                    response = $3::varchar,
+                    error = $4::varchar
+                where
+                    operation = $1::varchar
+                    and key = $2::varchar
+            "#,
+            operation,
+            key,
+            response,
+            error,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while completing the idempotency key")?;
+
+        Ok(())
+    }
+
+    async fn remove_key(&self, operation: &str, key: &str) -> anyhow::Result<()> {
+        query!(
+            r#"
+                delete from idempotency_key
+                where
+                    operation = $1::varchar
+                    and key = $2::varchar
+            "#,
+            operation,
+            key,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while removing the idempotency key")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::{query, query_as, FromRow};
+
+use crate::proto::email::SenderIdentityResponse;
+
+#[derive(Clone, FromRow)]
+pub struct SenderIdentityEntity {
+    pub id: i64,
+    pub name: String,
+    pub display_name: String,
+    pub address: String,
+    pub reply_to: Option<String>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+}
+
+impl SenderIdentityEntity {
+    pub fn into_sender_identity_response(self) -> SenderIdentityResponse {
+        SenderIdentityResponse {
+            name: self.name,
+            display_name: self.display_name,
+            address: self.address,
+            reply_to: self.reply_to.unwrap_or_default(),
+            has_credentials: self.smtp_username.is_some(),
+        }
+    }
+}
+
+pub struct NewSenderIdentity {
+    pub name: String,
+    pub display_name: String,
+    pub address: String,
+    pub reply_to: Option<String>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+}
+
+#[automock]
+#[async_trait]
+pub trait SenderIdentityRepositoryTrait {
+    async fn add_identity(
+        &self,
+        identity: &NewSenderIdentity,
+    ) -> anyhow::Result<SenderIdentityEntity>;
+    async fn get_identity(&self, name: &str) -> anyhow::Result<Option<SenderIdentityEntity>>;
+    async fn list_identities(&self) -> anyhow::Result<Vec<SenderIdentityEntity>>;
+    async fn remove_identity(&self, name: &str) -> anyhow::Result<Option<SenderIdentityEntity>>;
+    async fn set_group_identity(
+        &self,
+        group_id: i64,
+        identity_id: Option<i64>,
+    ) -> anyhow::Result<()>;
+    async fn get_group_identity(
+        &self,
+        group_id: i64,
+    ) -> anyhow::Result<Option<SenderIdentityEntity>>;
+}
+
+pub type DynSenderIdentityRepositoryTrait = Arc<dyn SenderIdentityRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct SenderIdentityRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl SenderIdentityRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SenderIdentityRepositoryTrait for SenderIdentityRepository {
+    async fn add_identity(
+        &self,
+        identity: &NewSenderIdentity,
+    ) -> anyhow::Result<SenderIdentityEntity> {
+        query_as!(
+            SenderIdentityEntity,
+            r#"
+                insert into sender_identity (
+                        name,
+                        display_name,
+                        address,
+                        reply_to,
+                        smtp_username,
+                        smtp_password
+                    )
+                values (
+                        $1::varchar,
+                        $2::varchar,
+                        $3::varchar,
+                        $4::varchar,
+                        $5::varchar,
+                        $6::varchar
+                    )
+                returning
+                    id,
+                    name,
+                    display_name,
+                    address,
+                    reply_to,
+                    smtp_username,
+                    smtp_password
+            "#,
+            identity.name,
+            identity.display_name,
+            identity.address,
+            identity.reply_to,
+            identity.smtp_username,
+            identity.smtp_password,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("an unexpected error occured while creating the sender identity")
+    }
+
+    async fn get_identity(&self, name: &str) -> anyhow::Result<Option<SenderIdentityEntity>> {
+        query_as!(
+            SenderIdentityEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    display_name,
+                    address,
+                    reply_to,
+                    smtp_username,
+                    smtp_password
+                from sender_identity
+                where name = $1::varchar
+            "#,
+            name,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while searching for sender identity")
+    }
+
+    async fn list_identities(&self) -> anyhow::Result<Vec<SenderIdentityEntity>> {
+        query_as!(
+            SenderIdentityEntity,
+            r#"
+                select
+                    id,
+                    name,
+                    display_name,
+                    address,
+                    reply_to,
+                    smtp_username,
+                    smtp_password
+                from sender_identity
+                order by name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the sender identity list")
+    }
+
+    async fn remove_identity(&self, name: &str) -> anyhow::Result<Option<SenderIdentityEntity>> {
+        query_as!(
+            SenderIdentityEntity,
+            r#"
+                delete from sender_identity
+                where name = $1::varchar
+                returning
+                    id,
+                    name,
+                    display_name,
+                    address,
+                    reply_to,
+                    smtp_username,
+                    smtp_password
+            "#,
+            name,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while removing the sender identity")
+    }
+
+    async fn set_group_identity(
+        &self,
+        group_id: i64,
+        identity_id: Option<i64>,
+    ) -> anyhow::Result<()> {
+        query!(
+            r#"
+                update subscription_group
+                set
+                    default_identity_id = $2::bigint,
+                    updated_at = current_timestamp
+                where id = $1::bigint
+            "#,
+            group_id,
+            identity_id,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while setting the group sender identity")?;
+
+        Ok(())
+    }
+
+    async fn get_group_identity(
+        &self,
+        group_id: i64,
+    ) -> anyhow::Result<Option<SenderIdentityEntity>> {
+        query_as!(
+            SenderIdentityEntity,
+            r#"
+                select
+                    si.id as id,
+                    si.name as name,
+                    si.display_name as display_name,
+                    si.address as address,
+                    si.reply_to as reply_to,
+                    si.smtp_username as smtp_username,
+                    si.smtp_password as smtp_password
+                from sender_identity as si
+                join subscription_group as sg
+                on si.id = sg.default_identity_id
+                where sg.id = $1::bigint
+            "#,
+            group_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the group sender identity")
+    }
+}
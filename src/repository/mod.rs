@@ -1,22 +1,60 @@
+pub mod campaign;
+pub mod delivery;
+pub mod digest;
 pub mod group;
+pub mod idempotency;
+pub mod identity;
 pub mod subcriber;
+pub mod suppression;
+pub mod template;
+pub mod tracking;
+pub mod unsubscription;
 
 #[cfg(test)]
 pub mod test {
     use std::sync::Arc;
 
-    use sqlx::PgPool;
+    use sqlx::{types::time::OffsetDateTime, PgPool};
+    use time::Duration;
 
     use crate::repository::{
-        group::{DynGroupRepositoryTrait, GroupRepository},
+        campaign::{CampaignRepository, CampaignStatus, DynCampaignRepositoryTrait, NewCampaign},
+        delivery::{
+            DeliveryRepository, DeliveryStatus, DynDeliveryRepositoryTrait, MessageClass,
+            NewDelivery,
+        },
+        digest::{
+            DigestCadence, DigestItemStatus, DigestRepository, DynDigestRepositoryTrait,
+            NewDigestItem, NewDigestType,
+        },
+        group::{DynGroupRepositoryTrait, FooterTemplate, GroupRepository},
+        idempotency::{DynIdempotencyRepositoryTrait, IdempotencyRepository, NewIdempotencyKey},
+        identity::{DynSenderIdentityRepositoryTrait, NewSenderIdentity, SenderIdentityRepository},
         subcriber::DynSubscriberRepositoryTrait,
+        suppression::{
+            DynSuppressionRepositoryTrait, NewSuppression, SuppressionReason, SuppressionRepository,
+        },
+        template::{DynTemplateRepositoryTrait, NewTemplate, TemplateRepository},
+        tracking::{
+            CampaignEngagement, DynTrackingRepositoryTrait, TrackingEventKind, TrackingRepository,
+        },
+        unsubscription::{DynUnsubscriptionRepositoryTrait, UnsubscriptionRepository},
     };
 
-    use super::subcriber::SubscriberRepository;
+    use super::subcriber::{NewSubscriber, SubscriberRepository};
 
     struct AllTraits {
         subscriber_repository: DynSubscriberRepositoryTrait,
         group_repository: DynGroupRepositoryTrait,
+        campaign_repository: DynCampaignRepositoryTrait,
+        delivery_repository: DynDeliveryRepositoryTrait,
+        suppression_repository: DynSuppressionRepositoryTrait,
+        tracking_repository: DynTrackingRepositoryTrait,
+        identity_repository: DynSenderIdentityRepositoryTrait,
+        template_repository: DynTemplateRepositoryTrait,
+        idempotency_repository: DynIdempotencyRepositoryTrait,
+        digest_repository: DynDigestRepositoryTrait,
+        unsubscription_repository: DynUnsubscriptionRepositoryTrait,
     }
 
     fn initialize_handler(pool: PgPool) -> AllTraits {
@@ -24,10 +62,37 @@ pub mod test {
             Arc::new(SubscriberRepository::new(pool.clone())) as DynSubscriberRepositoryTrait;
         let group_repository =
             Arc::new(GroupRepository::new(pool.clone())) as DynGroupRepositoryTrait;
+        let campaign_repository =
+            Arc::new(CampaignRepository::new(pool.clone())) as DynCampaignRepositoryTrait;
+        let delivery_repository =
+            Arc::new(DeliveryRepository::new(pool.clone())) as DynDeliveryRepositoryTrait;
+        let suppression_repository =
+            Arc::new(SuppressionRepository::new(pool.clone())) as DynSuppressionRepositoryTrait;
+        let tracking_repository =
+            Arc::new(TrackingRepository::new(pool.clone())) as DynTrackingRepositoryTrait;
+        let identity_repository = Arc::new(SenderIdentityRepository::new(pool.clone()))
+            as DynSenderIdentityRepositoryTrait;
+        let template_repository =
+            Arc::new(TemplateRepository::new(pool.clone())) as DynTemplateRepositoryTrait;
+        let idempotency_repository =
+            Arc::new(IdempotencyRepository::new(pool.clone())) as DynIdempotencyRepositoryTrait;
+        let digest_repository =
+            Arc::new(DigestRepository::new(pool.clone())) as DynDigestRepositoryTrait;
+        let unsubscription_repository = Arc::new(UnsubscriptionRepository::new(pool.clone()))
+            as DynUnsubscriptionRepositoryTrait;
 
         AllTraits {
             subscriber_repository,
             group_repository,
+            campaign_repository,
+            delivery_repository,
+            suppression_repository,
+            tracking_repository,
+            identity_repository,
+            template_repository,
+            idempotency_repository,
+            digest_repository,
+            unsubscription_repository,
         }
     }
 
@@ -195,4 +260,939 @@ pub mod test {
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn add_subscribers_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+
+        let existing_address = "existing@email.com";
+        traits
+            .subscriber_repository
+            .add_subscriber(existing_address, &group)
+            .await?;
+
+        let new_address = "new@email.com";
+        let inserted = traits
+            .subscriber_repository
+            .add_subscribers(
+                &[
+                    NewSubscriber {
+                        email: existing_address.to_string(),
+                        attributes: serde_json::json!({}),
+                    },
+                    NewSubscriber {
+                        email: new_address.to_string(),
+                        attributes: serde_json::json!({ "name": "new" }),
+                    },
+                ],
+                &group,
+            )
+            .await?;
+
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(inserted.first().unwrap().email, new_address);
+        let attributes: serde_json::Value =
+            sqlx::query_scalar("select attributes from subscriber where email = $1")
+                .bind(new_address)
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(attributes, serde_json::json!({ "name": "new" }));
+        assert_eq!(
+            traits
+                .subscriber_repository
+                .get_subs_by_group_count(&group)
+                .await?,
+            2
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn merge_groups_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let into_group = traits
+            .group_repository
+            .add_group("into_group", "into_group_description")
+            .await?;
+        let from_group = traits
+            .group_repository
+            .add_group("from_group", "from_group_description")
+            .await?;
+
+        let shared_address = "shared@email.com";
+        traits
+            .subscriber_repository
+            .add_subscriber(shared_address, &into_group)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber(shared_address, &from_group)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("from@email.com", &from_group)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("gone@email.com", &from_group)
+            .await?;
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                std::slice::from_ref(&from_group),
+            )
+            .await?;
+        let delivery = traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: "<gone@email.com>".to_string(),
+                recipient: "gone@email.com".to_string(),
+                subject: "subject".to_string(),
+                campaign_id: Some(campaign.id),
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+        traits
+            .unsubscription_repository
+            .unsubscribe("gone@email.com", delivery.id, Some(campaign.id))
+            .await?;
+        traits
+            .group_repository
+            .set_group_seeds(&from_group, &["seed@email.com".to_string()])
+            .await?;
+
+        let summary = traits
+            .group_repository
+            .merge_groups(&into_group, &from_group)
+            .await?;
+        let campaign_groups = traits
+            .campaign_repository
+            .list_campaign_groups(campaign.id)
+            .await?;
+        let opted_out = traits
+            .unsubscription_repository
+            .list_opted_out(&["gone@email.com".to_string()])
+            .await?;
+        let seeds = traits
+            .group_repository
+            .list_group_seeds(into_group.id)
+            .await?;
+
+        assert_eq!(summary.inserted, 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.removed, 2);
+        assert_eq!(
+            traits
+                .subscriber_repository
+                .get_subs_by_group_count(&into_group)
+                .await?,
+            2
+        );
+        assert!(traits
+            .group_repository
+            .get_group("from_group")
+            .await?
+            .is_none());
+        assert_eq!(campaign_groups.len(), 1);
+        assert_eq!(campaign_groups.first().unwrap().id, into_group.id);
+        assert_eq!(opted_out, vec!["gone@email.com"]);
+        assert_eq!(seeds, vec!["seed@email.com"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn campaign_transition_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub_1_address@email.com", &group)
+            .await?;
+
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                &[group],
+            )
+            .await?;
+
+        let sending = traits
+            .campaign_repository
+            .transition_campaign(
+                campaign.id,
+                &[CampaignStatus::Draft],
+                CampaignStatus::Sending,
+                None,
+            )
+            .await?;
+        let sending_again = traits
+            .campaign_repository
+            .transition_campaign(
+                campaign.id,
+                &[CampaignStatus::Draft],
+                CampaignStatus::Sending,
+                None,
+            )
+            .await?;
+        let recipients = traits
+            .campaign_repository
+            .list_campaign_recipients(campaign.id)
+            .await?;
+
+        assert_eq!(sending.unwrap().status, "sending");
+        assert!(sending_again.is_none());
+        assert_eq!(recipients, vec!["sub_1_address@email.com"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn delivery_status_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let recipient = "recipient@email.com";
+        let queued = traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: "<message_1@email.com>".to_string(),
+                recipient: recipient.to_string(),
+                subject: "subject".to_string(),
+                campaign_id: None,
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+        traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: "<message_2@email.com>".to_string(),
+                recipient: "other@email.com".to_string(),
+                subject: "subject".to_string(),
+                campaign_id: None,
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+
+        traits
+            .delivery_repository
+            .update_delivery_status(queued.id, DeliveryStatus::Sent, "250 OK")
+            .await?;
+
+        let sent = traits
+            .delivery_repository
+            .get_delivery_by_message_id("<message_1@email.com>")
+            .await?
+            .unwrap();
+        let recipient_deliveries = traits
+            .delivery_repository
+            .list_deliveries(Some(recipient.to_string()), None, None, None)
+            .await?;
+
+        assert_eq!(queued.status, "queued");
+        assert_eq!(sent.status, "sent");
+        assert_eq!(sent.smtp_response, "250 OK");
+        assert!(sent.sent_at.is_some());
+        assert_eq!(recipient_deliveries.len(), 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn suppression_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let added = traits
+            .suppression_repository
+            .add_suppression(&NewSuppression {
+                email: "Gone@Email.com".to_string(),
+                reason: SuppressionReason::HardBounce,
+                detail: "5.1.1".to_string(),
+                message_id: None,
+            })
+            .await?;
+        let duplicate = traits
+            .suppression_repository
+            .add_suppression(&NewSuppression {
+                email: "gone@email.com".to_string(),
+                reason: SuppressionReason::Complaint,
+                detail: "abuse".to_string(),
+                message_id: None,
+            })
+            .await?;
+
+        let suppressed = traits
+            .suppression_repository
+            .list_suppressed(&["gone@email.com".to_string(), "here@email.com".to_string()])
+            .await?;
+        let suppression = traits
+            .suppression_repository
+            .get_suppression("GONE@email.com")
+            .await?;
+
+        assert_eq!(added.unwrap().reason, "hard_bounce");
+        assert!(duplicate.is_none());
+        assert_eq!(suppressed, vec!["gone@email.com".to_string()]);
+        assert_eq!(suppression.unwrap().reason, "hard_bounce");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn campaign_engagement_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: "<p>html body</p>".to_string(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                &[group],
+            )
+            .await?;
+
+        let mut deliveries = Vec::new();
+        for (index, recipient) in ["sub1@email.com", "sub2@email.com"].iter().enumerate() {
+            let delivery = traits
+                .delivery_repository
+                .add_delivery(&NewDelivery {
+                    message_id: format!("<message_{}@email.com>", index),
+                    recipient: recipient.to_string(),
+                    subject: "subject".to_string(),
+                    campaign_id: Some(campaign.id),
+                    verp_token: None,
+                    send_after: None,
+                    message_class: MessageClass::Marketing,
+                })
+                .await?;
+            traits
+                .delivery_repository
+                .update_delivery_status(delivery.id, DeliveryStatus::Sent, "250 OK")
+                .await?;
+            deliveries.push(delivery);
+        }
+
+        let first = deliveries.first().unwrap().id;
+        traits
+            .tracking_repository
+            .add_event(first, TrackingEventKind::Open, None, "agent")
+            .await?;
+        traits
+            .tracking_repository
+            .add_event(first, TrackingEventKind::Open, None, "agent")
+            .await?;
+        traits
+            .tracking_repository
+            .add_event(
+                first,
+                TrackingEventKind::Click,
+                Some("https://email.com".to_string()),
+                "agent",
+            )
+            .await?;
+
+        let events: i64 =
+            sqlx::query_scalar("select count(*) from tracking_event where delivery_id = $1")
+                .bind(first)
+                .fetch_one(&pool)
+                .await?;
+        let engagement = traits
+            .tracking_repository
+            .get_campaign_engagement(campaign.id)
+            .await?;
+
+        assert_eq!(events, 3);
+        assert_eq!(
+            engagement,
+            CampaignEngagement {
+                sent: 2,
+                opened: 1,
+                clicked: 1,
+                opens: 2,
+                clicks: 1,
+                capped: 0,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn sender_identity_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        let identity = traits
+            .identity_repository
+            .add_identity(&NewSenderIdentity {
+                name: "newsletter".to_string(),
+                display_name: "Newsletter".to_string(),
+                address: "newsletter@email.com".to_string(),
+                reply_to: Some("support@email.com".to_string()),
+                smtp_username: None,
+                smtp_password: None,
+            })
+            .await?;
+
+        assert!(traits
+            .identity_repository
+            .get_group_identity(group.id)
+            .await?
+            .is_none());
+
+        traits
+            .identity_repository
+            .set_group_identity(group.id, Some(identity.id))
+            .await?;
+        let group_identity = traits
+            .identity_repository
+            .get_group_identity(group.id)
+            .await?;
+        assert_eq!(group_identity.unwrap().name, "newsletter");
+
+        traits
+            .identity_repository
+            .remove_identity("newsletter")
+            .await?;
+        let identities = traits.identity_repository.list_identities().await?;
+        let group_identity = traits
+            .identity_repository
+            .get_group_identity(group.id)
+            .await?;
+
+        assert!(identities.is_empty());
+        assert!(group_identity.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn group_footer_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        let footer = FooterTemplate {
+            text: "Sent to members of {{postal_address}}".to_string(),
+            html: String::new(),
+        };
+
+        let unset = traits.group_repository.get_group_footer(group.id).await?;
+        traits
+            .group_repository
+            .set_group_footer(&group, Some(footer.clone()))
+            .await?;
+        let set = traits.group_repository.get_group_footer(group.id).await?;
+        traits
+            .group_repository
+            .set_group_footer(&group, None)
+            .await?;
+        let cleared = traits.group_repository.get_group_footer(group.id).await?;
+
+        assert!(unset.is_none());
+        assert_eq!(set, Some(footer));
+        assert!(cleared.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn group_seeds_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+
+        traits
+            .group_repository
+            .set_group_seeds(
+                &group,
+                &[
+                    "reviewer@email.com".to_string(),
+                    "editor@email.com".to_string(),
+                ],
+            )
+            .await?;
+        let set = traits.group_repository.list_group_seeds(group.id).await?;
+        traits
+            .group_repository
+            .set_group_seeds(&group, &["editor@email.com".to_string()])
+            .await?;
+        let replaced = traits.group_repository.list_group_seeds(group.id).await?;
+
+        assert_eq!(set, vec!["editor@email.com", "reviewer@email.com"]);
+        assert_eq!(replaced, vec!["editor@email.com"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn template_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        for (locale, subject) in [("en", "Welcome"), ("ms", "Selamat datang"), ("ms", "Hai")] {
+            traits
+                .template_repository
+                .save_template(&NewTemplate {
+                    name: "welcome".to_string(),
+                    locale: locale.to_string(),
+                    subject: subject.to_string(),
+                    text_body: "body".to_string(),
+                    html_body: String::new(),
+                })
+                .await?;
+        }
+        let variants = traits
+            .template_repository
+            .list_template_variants("welcome")
+            .await?;
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants.last().unwrap().subject, "Hai");
+
+        let removed = traits
+            .template_repository
+            .remove_template_variant("welcome", "ms")
+            .await?;
+        let templates = traits.template_repository.list_templates().await?;
+        assert!(removed.is_some());
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates.first().unwrap().locale, "en");
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[
+                    NewSubscriber {
+                        email: "malay@email.com".to_string(),
+                        attributes: serde_json::json!({ "locale": "ms-MY" }),
+                    },
+                    NewSubscriber {
+                        email: "unknown@email.com".to_string(),
+                        attributes: serde_json::json!({}),
+                    },
+                ],
+                &group,
+            )
+            .await?;
+        let attributes = traits
+            .subscriber_repository
+            .list_subscriber_attributes(&[
+                "malay@email.com".to_string(),
+                "unknown@email.com".to_string(),
+            ])
+            .await?;
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(
+            attributes.get("malay@email.com").unwrap(),
+            &serde_json::json!({ "locale": "ms-MY" })
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn idempotency_key_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        let new_key = |key: &str, expires_in: Duration| NewIdempotencyKey {
+            operation: "send_email".to_string(),
+            key: key.to_string(),
+            fingerprint: "fingerprint".to_string(),
+            expires_at: OffsetDateTime::now_utc() + expires_in,
+        };
+
+        let claimed = traits
+            .idempotency_repository
+            .claim_key(&new_key("retried", Duration::hours(1)))
+            .await?;
+        let pending = traits
+            .idempotency_repository
+            .claim_key(&new_key("retried", Duration::hours(1)))
+            .await?;
+        traits
+            .idempotency_repository
+            .complete_key(
+                "send_email",
+                "retried",
+                Some("<id@email.com>".to_string()),
+                None,
+            )
+            .await?;
+        let completed = traits
+            .idempotency_repository
+            .claim_key(&new_key("retried", Duration::hours(1)))
+            .await?;
+        traits
+            .idempotency_repository
+            .claim_key(&new_key("expired", -Duration::hours(1)))
+            .await?;
+        let reclaimed = traits
+            .idempotency_repository
+            .claim_key(&new_key("expired", Duration::hours(1)))
+            .await?;
+        traits
+            .idempotency_repository
+            .remove_key("send_email", "retried")
+            .await?;
+        let released = traits
+            .idempotency_repository
+            .claim_key(&new_key("retried", Duration::hours(1)))
+            .await?;
+        traits
+            .idempotency_repository
+            .claim_key(&new_key("abandoned", Duration::hours(1)))
+            .await?;
+        sqlx::query(
+            "update idempotency_key set claimed_at = claimed_at - interval '1 hour' where key = 'abandoned'",
+        )
+        .execute(&pool)
+        .await?;
+        let taken_over = traits
+            .idempotency_repository
+            .claim_key(&new_key("abandoned", Duration::hours(1)))
+            .await?;
+        let still_claimed = traits
+            .idempotency_repository
+            .claim_key(&new_key("abandoned", Duration::hours(1)))
+            .await?;
+
+        assert!(claimed.is_none());
+        assert!(pending.unwrap().response.is_none());
+        assert_eq!(
+            completed.unwrap().response,
+            Some("<id@email.com>".to_string())
+        );
+        assert!(reclaimed.is_none());
+        assert!(released.is_none());
+        assert!(taken_over.is_none());
+        assert!(still_claimed.unwrap().response.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn due_deliveries_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: "09:00".to_string(),
+                    message_class: MessageClass::Marketing,
+                },
+                &[],
+            )
+            .await?;
+        let now = OffsetDateTime::now_utc();
+        let mut deliveries = Vec::new();
+        for (recipient, send_after) in [
+            ("due@email.com", Some(now - Duration::minutes(5))),
+            ("later@email.com", Some(now + Duration::hours(5))),
+            ("immediate@email.com", None),
+        ] {
+            let delivery = traits
+                .delivery_repository
+                .add_delivery(&NewDelivery {
+                    message_id: format!("<{}>", recipient),
+                    recipient: recipient.to_string(),
+                    subject: "subject".to_string(),
+                    campaign_id: Some(campaign.id),
+                    verp_token: None,
+                    send_after,
+                    message_class: MessageClass::Marketing,
+                })
+                .await?;
+            deliveries.push(delivery);
+        }
+
+        let due_campaigns = traits
+            .campaign_repository
+            .list_campaigns_with_due_deliveries()
+            .await?;
+        let (due, claimed_elsewhere) = tokio::join!(
+            traits.delivery_repository.claim_due_deliveries(campaign.id),
+            traits.delivery_repository.claim_due_deliveries(campaign.id),
+        );
+        let (due, claimed_elsewhere) = (due?, claimed_elsewhere?);
+        let due_campaigns_after_claim = traits
+            .campaign_repository
+            .list_campaigns_with_due_deliveries()
+            .await?;
+        let deferred = traits
+            .delivery_repository
+            .defer_delivery(deliveries[0].id, now + Duration::hours(1))
+            .await?;
+        let deferred_send_after: Option<OffsetDateTime> =
+            sqlx::query_scalar("select send_after from delivery where id = $1")
+                .bind(deliveries[0].id)
+                .fetch_one(&pool)
+                .await?;
+        let due_after_deferral = traits
+            .delivery_repository
+            .claim_due_deliveries(campaign.id)
+            .await?;
+        traits
+            .delivery_repository
+            .defer_delivery(deliveries[0].id, now - Duration::minutes(1))
+            .await?;
+        let due_again = traits
+            .delivery_repository
+            .claim_due_deliveries(campaign.id)
+            .await?;
+
+        assert_eq!(campaign.local_send_time, "09:00");
+        assert_eq!(due.len() + claimed_elsewhere.len(), 1);
+        let claimed = due.first().or(claimed_elsewhere.first()).unwrap();
+        assert_eq!(claimed.recipient, "due@email.com");
+        assert_eq!(claimed.status, "sending");
+        assert_eq!(due_campaigns.len(), 1);
+        assert_eq!(due_campaigns.first().unwrap().id, campaign.id);
+        assert!(due_campaigns_after_claim.is_empty());
+        assert_eq!(deferred.unwrap().status, "queued");
+        assert!(deferred_send_after.unwrap() > now);
+        assert!(due_after_deferral.is_empty());
+        assert_eq!(due_again.len(), 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn digest_items_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        traits
+            .digest_repository
+            .save_digest_type(&NewDigestType {
+                name: "activity".to_string(),
+                cadence: DigestCadence::Daily,
+                template: "activity".to_string(),
+            })
+            .await?;
+        let digest_type = traits
+            .digest_repository
+            .save_digest_type(&NewDigestType {
+                name: "activity".to_string(),
+                cadence: DigestCadence::Hourly,
+                template: "activity".to_string(),
+            })
+            .await?;
+        for (recipient, title) in [
+            ("first@email.com", "first title"),
+            ("first@email.com", "second title"),
+            ("second@email.com", "third title"),
+        ] {
+            traits
+                .digest_repository
+                .add_digest_item(
+                    digest_type.id,
+                    &NewDigestItem {
+                        recipient: recipient.to_string(),
+                        title: title.to_string(),
+                        body: String::new(),
+                    },
+                )
+                .await?;
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let due_recipients = traits
+            .digest_repository
+            .list_due_recipients(digest_type.id, now + Duration::minutes(1))
+            .await?;
+        let early_recipients = traits
+            .digest_repository
+            .list_due_recipients(digest_type.id, now - Duration::hours(1))
+            .await?;
+        let pending = traits
+            .digest_repository
+            .claim_pending_items(digest_type.id, "first@email.com")
+            .await?;
+        let claimed_twice = traits
+            .digest_repository
+            .claim_pending_items(digest_type.id, "first@email.com")
+            .await?;
+        let ids = pending.iter().map(|item| item.id).collect::<Vec<i64>>();
+        let updated = traits
+            .digest_repository
+            .update_items_status(&ids, DigestItemStatus::Sent, Some("<id>".to_string()))
+            .await?;
+        let due_after_update = traits
+            .digest_repository
+            .list_due_recipients(digest_type.id, now + Duration::minutes(1))
+            .await?;
+
+        // A claim left behind by an instance that died is taken over once its
+        // lease runs out.
+        traits
+            .digest_repository
+            .claim_pending_items(digest_type.id, "second@email.com")
+            .await?;
+        sqlx::query(
+            "update digest_item set updated_at = updated_at - interval '1 hour' where status = 'sending'",
+        )
+        .execute(&pool)
+        .await?;
+        let abandoned = traits
+            .digest_repository
+            .claim_pending_items(digest_type.id, "second@email.com")
+            .await?;
+        let digest_types = traits.digest_repository.list_digest_types().await?;
+
+        assert_eq!(digest_type.cadence, "hourly");
+        assert_eq!(digest_types.len(), 1);
+        assert_eq!(due_recipients, vec!["first@email.com", "second@email.com"]);
+        assert!(early_recipients.is_empty());
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending.first().unwrap().title, "first title");
+        assert!(claimed_twice.is_empty());
+        assert_eq!(updated, 2);
+        assert_eq!(due_after_update, vec!["second@email.com"]);
+        assert_eq!(abandoned.len(), 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn cancel_queued_deliveries_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: "09:00".to_string(),
+                    message_class: MessageClass::Marketing,
+                },
+                &[],
+            )
+            .await?;
+        let mut deliveries = Vec::new();
+        for recipient in ["sent@email.com", "queued@email.com", "later@email.com"] {
+            let delivery = traits
+                .delivery_repository
+                .add_delivery(&NewDelivery {
+                    message_id: format!("<{}>", recipient),
+                    recipient: recipient.to_string(),
+                    subject: "subject".to_string(),
+                    campaign_id: Some(campaign.id),
+                    verp_token: None,
+                    send_after: Some(OffsetDateTime::now_utc() + Duration::hours(5)),
+                    message_class: MessageClass::Marketing,
+                })
+                .await?;
+            deliveries.push(delivery);
+        }
+        traits
+            .delivery_repository
+            .update_delivery_status(deliveries[0].id, DeliveryStatus::Sent, "250 OK")
+            .await?;
+
+        let cancelled = traits
+            .delivery_repository
+            .cancel_queued_deliveries(campaign.id)
+            .await?;
+        let counts = traits
+            .delivery_repository
+            .count_campaign_deliveries_by_status(campaign.id)
+            .await?;
+        let cancelled_again = traits
+            .delivery_repository
+            .cancel_queued_deliveries(campaign.id)
+            .await?;
+
+        assert_eq!(cancelled, 2);
+        assert_eq!(counts.get("sent"), Some(&1));
+        assert_eq!(counts.get("cancelled"), Some(&2));
+        assert_eq!(counts.get("queued"), None);
+        assert_eq!(cancelled_again, 0);
+
+        Ok(())
+    }
 }
@@ -1,22 +1,27 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Context;
 use async_trait::async_trait;
-use madtofan_microservice_common::{
-    email::subscribers_response::Subscriber, repository::connection_pool::ServiceConnectionPool,
-};
+use futures::{stream::BoxStream, TryStreamExt};
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
 use mockall::automock;
 use sqlx::{query, query_as, types::time::OffsetDateTime, FromRow};
 
 use super::group::GroupEntity;
 
+use crate::proto::email::subscribers_response::Subscriber;
+
 #[derive(FromRow)]
 pub struct SubscriberEntity {
-    pub id: i64,
     pub created_at: OffsetDateTime,
-    pub updated_at: OffsetDateTime,
     pub email: String,
-    pub group_id: i64,
+    pub attributes: serde_json::Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewSubscriber {
+    pub email: String,
+    pub attributes: serde_json::Value,
 }
 
 impl SubscriberEntity {
@@ -35,16 +40,35 @@ pub trait SubscriberRepositoryTrait {
         limit: Option<i64>,
     ) -> anyhow::Result<Vec<SubscriberEntity>>;
     async fn get_subs_by_group_count(&self, group: &GroupEntity) -> anyhow::Result<i64>;
+    fn stream_subs_by_group(
+        &self,
+        group: &GroupEntity,
+        segment: serde_json::Value,
+    ) -> BoxStream<'static, anyhow::Result<SubscriberEntity>>;
     async fn add_subscriber(
         &self,
         email: &str,
         group: &GroupEntity,
     ) -> anyhow::Result<SubscriberEntity>;
+    async fn add_subscribers(
+        &self,
+        subscribers: &[NewSubscriber],
+        group: &GroupEntity,
+    ) -> anyhow::Result<Vec<SubscriberEntity>>;
     async fn remove_subscriber_from_group(
         &self,
         email: &str,
         group: &GroupEntity,
     ) -> anyhow::Result<Option<SubscriberEntity>>;
+    async fn remove_subscribers_from_group(
+        &self,
+        emails: &[String],
+        group: &GroupEntity,
+    ) -> anyhow::Result<Vec<SubscriberEntity>>;
+    async fn list_subscriber_attributes(
+        &self,
+        emails: &[String],
+    ) -> anyhow::Result<HashMap<String, serde_json::Value>>;
 }
 
 pub type DynSubscriberRepositoryTrait = Arc<dyn SubscriberRepositoryTrait + Send + Sync>;
@@ -73,11 +97,9 @@ impl SubscriberRepositoryTrait for SubscriberRepository {
         let query_string = format!(
             r#"
                 select
-                    id,
                     email,
-                    group_id,
-                    created_at,
-                    updated_at
+                    attributes,
+                    created_at
                 from subscriber
                 where group_id = {}
                 {}
@@ -115,6 +137,42 @@ impl SubscriberRepositoryTrait for SubscriberRepository {
         Ok(count_result.count.unwrap())
     }
 
+    fn stream_subs_by_group(
+        &self,
+        group: &GroupEntity,
+        segment: serde_json::Value,
+    ) -> BoxStream<'static, anyhow::Result<SubscriberEntity>> {
+        let pool = self.pool.clone();
+        let group_id = group.id;
+
+        Box::pin(async_stream::try_stream! {
+            let mut rows = query_as!(
+                SubscriberEntity,
+                r#"
+                    select
+                        email,
+                        attributes,
+                        created_at
+                    from subscriber
+                    where group_id = $1::bigint
+                        and attributes @> $2::jsonb
+                    order by id
+                "#,
+                group_id,
+                segment,
+            )
+            .fetch(&pool);
+
+            while let Some(row) = rows
+                .try_next()
+                .await
+                .context("an unexpected error occured while streaming subscribers by group")?
+            {
+                yield row;
+            }
+        })
+    }
+
     async fn add_subscriber(
         &self,
         email: &str,
@@ -131,7 +189,10 @@ impl SubscriberRepositoryTrait for SubscriberRepository {
                         $1::varchar,
                         $2::bigint
                     )
-                returning *
+                returning
+                    email,
+                    attributes,
+                    created_at
             "#,
             email,
             group.id,
@@ -141,6 +202,44 @@ impl SubscriberRepositoryTrait for SubscriberRepository {
         .context("an unexpected error occured while creating the subscriber")
     }
 
+    async fn add_subscribers(
+        &self,
+        subscribers: &[NewSubscriber],
+        group: &GroupEntity,
+    ) -> anyhow::Result<Vec<SubscriberEntity>> {
+        let (emails, attributes): (Vec<String>, Vec<serde_json::Value>) = subscribers
+            .iter()
+            .map(|subscriber| (subscriber.email.clone(), subscriber.attributes.clone()))
+            .unzip();
+
+        query_as!(
+            SubscriberEntity,
+            r#"
+                insert into subscriber (
+                        email,
+                        group_id,
+                        attributes
+                    )
+                select
+                    email,
+                    $3::bigint,
+                    attributes
+                from unnest($1::varchar[], $2::jsonb[]) as new_subscriber(email, attributes)
+                on conflict (email, group_id) do nothing
+                returning
+                    email,
+                    attributes,
+                    created_at
+            "#,
+            &emails,
+            &attributes,
+            group.id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while bulk creating subscribers")
+    }
+
     async fn remove_subscriber_from_group(
         &self,
         email: &str,
@@ -153,7 +252,10 @@ impl SubscriberRepositoryTrait for SubscriberRepository {
                 where 
                     email = $1::varchar 
                     and group_id = $2::bigint
-                returning *
+                returning
+                    email,
+                    attributes,
+                    created_at
             "#,
             email,
             group.id,
@@ -162,4 +264,57 @@ impl SubscriberRepositoryTrait for SubscriberRepository {
         .await
         .context("an unexpected error occured while removing the subscriber")
     }
+
+    async fn remove_subscribers_from_group(
+        &self,
+        emails: &[String],
+        group: &GroupEntity,
+    ) -> anyhow::Result<Vec<SubscriberEntity>> {
+        query_as!(
+            SubscriberEntity,
+            r#"
+                delete from subscriber
+                where
+                    lower(email) = any($1::varchar[])
+                    and group_id = $2::bigint
+                returning
+                    email,
+                    attributes,
+                    created_at
+            "#,
+            emails,
+            group.id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while bulk removing subscribers")
+    }
+
+    async fn list_subscriber_attributes(
+        &self,
+        emails: &[String],
+    ) -> anyhow::Result<HashMap<String, serde_json::Value>> {
+        // A subscriber of several groups has attributes in each of them, the
+        // most recently updated group wins when they disagree.
+        let subscribers = query!(
+            r#"
+                select
+                    s.email,
+                    jsonb_object_agg(a.key, a.value order by s.updated_at) as "attributes!"
+                from subscriber as s
+                cross join lateral jsonb_each(s.attributes) as a
+                where s.email = any($1::varchar[])
+                group by s.email
+            "#,
+            emails,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the subscriber attributes")?;
+
+        Ok(subscribers
+            .into_iter()
+            .map(|subscriber| (subscriber.email, subscriber.attributes))
+            .collect())
+    }
 }
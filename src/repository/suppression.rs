@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::{query, query_as, FromRow};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SuppressionReason {
+    HardBounce,
+    Complaint,
+}
+
+impl SuppressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionReason::HardBounce => "hard_bounce",
+            SuppressionReason::Complaint => "complaint",
+        }
+    }
+}
+
+#[derive(FromRow)]
+pub struct SuppressionEntity {
+    pub reason: String,
+}
+
+pub struct NewSuppression {
+    pub email: String,
+    pub reason: SuppressionReason,
+    pub detail: String,
+    pub message_id: Option<String>,
+}
+
+#[automock]
+#[async_trait]
+pub trait SuppressionRepositoryTrait {
+    async fn add_suppression(
+        &self,
+        suppression: &NewSuppression,
+    ) -> anyhow::Result<Option<SuppressionEntity>>;
+    async fn get_suppression(&self, email: &str) -> anyhow::Result<Option<SuppressionEntity>>;
+    async fn list_suppressed(&self, emails: &[String]) -> anyhow::Result<Vec<String>>;
+}
+
+pub type DynSuppressionRepositoryTrait = Arc<dyn SuppressionRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct SuppressionRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl SuppressionRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SuppressionRepositoryTrait for SuppressionRepository {
+    async fn add_suppression(
+        &self,
+        suppression: &NewSuppression,
+    ) -> anyhow::Result<Option<SuppressionEntity>> {
+        query_as!(
+            SuppressionEntity,
+            r#"
+                insert into suppression (
+                        email,
+                        reason,
+                        detail,
+                        message_id
+                    )
+                values (
+                        lower($1::varchar),
+                        $2::varchar,
+                        $3::varchar,
+                        $4::varchar
+                    )
+                on conflict (email) do nothing
+                returning
+                    reason
+            "#,
+            suppression.email,
+            suppression.reason.as_str(),
+            suppression.detail,
+            suppression.message_id,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while suppressing the address")
+    }
+
+    async fn get_suppression(&self, email: &str) -> anyhow::Result<Option<SuppressionEntity>> {
+        query_as!(
+            SuppressionEntity,
+            r#"
+                select
+                    reason
+                from suppression
+                where email = lower($1::varchar)
+            "#,
+            email,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while searching for suppression")
+    }
+
+    async fn list_suppressed(&self, emails: &[String]) -> anyhow::Result<Vec<String>> {
+        let suppressed = query!(
+            r#"
+                select
+                    target.email as "email!"
+                from unnest($1::varchar[]) as target(email)
+                join suppression as s
+                on s.email = lower(target.email)
+            "#,
+            emails,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the suppressed addresses")?;
+
+        Ok(suppressed.into_iter().map(|row| row.email).collect())
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::{query_as, FromRow};
+
+use crate::proto::email::TemplateResponse;
+
+#[derive(Clone, FromRow)]
+pub struct TemplateEntity {
+    pub name: String,
+    pub locale: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+impl TemplateEntity {
+    pub fn into_template_response(self) -> TemplateResponse {
+        TemplateResponse {
+            name: self.name,
+            locale: self.locale,
+            subject: self.subject,
+            text_body: self.text_body,
+            html_body: self.html_body,
+        }
+    }
+}
+
+pub struct NewTemplate {
+    pub name: String,
+    pub locale: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+#[automock]
+#[async_trait]
+pub trait TemplateRepositoryTrait {
+    async fn save_template(&self, template: &NewTemplate) -> anyhow::Result<TemplateEntity>;
+    async fn list_templates(&self) -> anyhow::Result<Vec<TemplateEntity>>;
+    async fn list_template_variants(&self, name: &str) -> anyhow::Result<Vec<TemplateEntity>>;
+    async fn remove_template(&self, name: &str) -> anyhow::Result<Vec<TemplateEntity>>;
+    async fn remove_template_variant(
+        &self,
+        name: &str,
+        locale: &str,
+    ) -> anyhow::Result<Option<TemplateEntity>>;
+}
+
+pub type DynTemplateRepositoryTrait = Arc<dyn TemplateRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct TemplateRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl TemplateRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TemplateRepositoryTrait for TemplateRepository {
+    async fn save_template(&self, template: &NewTemplate) -> anyhow::Result<TemplateEntity> {
+        query_as!(
+            TemplateEntity,
+            r#"
+                insert into email_template (
+                        name,
+                        locale,
+                        subject,
+                        text_body,
+                        html_body
+                    )
+                values (
+                        $1::varchar,
+                        $2::varchar,
+                        $3::varchar,
+                        $4::text,
+                        $5::text
+                    )
+                on conflict (name, locale) do update
+                set
+                    subject = excluded.subject,
+                    text_body = excluded.text_body,
+                    html_body = excluded.html_body,
+                    updated_at = current_timestamp
+                returning
+                    name,
+                    locale,
+                    subject,
+                    text_body,
+                    html_body
+            "#,
+            template.name,
+            template.locale,
+            template.subject,
+            template.text_body,
+            template.html_body,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("an unexpected error occured while saving the template")
+    }
+
+    async fn list_templates(&self) -> anyhow::Result<Vec<TemplateEntity>> {
+        query_as!(
+            TemplateEntity,
+            r#"
+                select
+                    name,
+                    locale,
+                    subject,
+                    text_body,
+                    html_body
+                from email_template
+                order by name, locale
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the template list")
+    }
+
+    async fn list_template_variants(&self, name: &str) -> anyhow::Result<Vec<TemplateEntity>> {
+        query_as!(
+            TemplateEntity,
+            r#"
+                select
+                    name,
+                    locale,
+                    subject,
+                    text_body,
+                    html_body
+                from email_template
+                where name = $1::varchar
+                order by locale
+            "#,
+            name,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the template variants")
+    }
+
+    async fn remove_template(&self, name: &str) -> anyhow::Result<Vec<TemplateEntity>> {
+        query_as!(
+            TemplateEntity,
+            r#"
+                delete from email_template
+                where name = $1::varchar
+                returning
+                    name,
+                    locale,
+                    subject,
+                    text_body,
+                    html_body
+            "#,
+            name,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while removing the template")
+    }
+
+    async fn remove_template_variant(
+        &self,
+        name: &str,
+        locale: &str,
+    ) -> anyhow::Result<Option<TemplateEntity>> {
+        query_as!(
+            TemplateEntity,
+            r#"
+                delete from email_template
+                where
+                    name = $1::varchar
+                    and locale = $2::varchar
+                returning
+                    name,
+                    locale,
+                    subject,
+                    text_body,
+                    html_body
+            "#,
+            name,
+            locale,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("an unexpected error occured while removing the template variant")
+    }
+}
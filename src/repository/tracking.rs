@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::query;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrackingEventKind {
+    Open,
+    Click,
+}
+
+impl TrackingEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrackingEventKind::Open => "open",
+            TrackingEventKind::Click => "click",
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CampaignEngagement {
+    pub sent: i64,
+    pub opened: i64,
+    pub clicked: i64,
+    pub opens: i64,
+    pub clicks: i64,
+    pub capped: i64,
+}
+
+#[automock]
+#[async_trait]
+pub trait TrackingRepositoryTrait {
+    async fn add_event(
+        &self,
+        delivery_id: i64,
+        kind: TrackingEventKind,
+        url: Option<String>,
+        user_agent: &str,
+    ) -> anyhow::Result<()>;
+    async fn get_campaign_engagement(&self, campaign_id: i64)
+        -> anyhow::Result<CampaignEngagement>;
+}
+
+pub type DynTrackingRepositoryTrait = Arc<dyn TrackingRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct TrackingRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl TrackingRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TrackingRepositoryTrait for TrackingRepository {
+    async fn add_event(
+        &self,
+        delivery_id: i64,
+        kind: TrackingEventKind,
+        url: Option<String>,
+        user_agent: &str,
+    ) -> anyhow::Result<()> {
+        query!(
+            r#"
+                insert into tracking_event (
+                        delivery_id,
+                        kind,
+                        url,
+                        user_agent
+                    )
+                values (
+                        $1::bigint,
+                        $2::varchar,
+                        $3::varchar,
+                        $4::varchar
+                    )
+            "#,
+            delivery_id,
+            kind.as_str(),
+            url,
+            user_agent,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while recording the tracking event")?;
+
+        Ok(())
+    }
+
+    async fn get_campaign_engagement(
+        &self,
+        campaign_id: i64,
+    ) -> anyhow::Result<CampaignEngagement> {
+        // A click implies the message was opened, even when images were blocked
+        // and the pixel never loaded.
+        let engagement = query!(
+            r#"
+                select
+                    count(distinct d.id) filter (where d.status = 'sent') as "sent!",
+                    count(distinct e.delivery_id) as "opened!",
+                    count(distinct e.delivery_id) filter (where e.kind = 'click') as "clicked!",
+                    count(e.id) filter (where e.kind = 'open') as "opens!",
+                    count(e.id) filter (where e.kind = 'click') as "clicks!",
+                    count(distinct d.id) filter (where d.status = 'capped') as "capped!"
+                from delivery as d
+                left join tracking_event as e
+                on e.delivery_id = d.id
+                where d.campaign_id = $1::bigint
+            "#,
+            campaign_id,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the campaign engagement")?;
+
+        Ok(CampaignEngagement {
+            sent: engagement.sent,
+            opened: engagement.opened,
+            clicked: engagement.clicked,
+            opens: engagement.opens,
+            clicks: engagement.clicks,
+            capped: engagement.capped,
+        })
+    }
+}
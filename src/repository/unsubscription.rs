@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use madtofan_microservice_common::repository::connection_pool::ServiceConnectionPool;
+use mockall::automock;
+use sqlx::query;
+
+#[automock]
+#[async_trait]
+pub trait UnsubscriptionRepositoryTrait {
+    async fn unsubscribe(
+        &self,
+        email: &str,
+        delivery_id: i64,
+        campaign_id: Option<i64>,
+    ) -> anyhow::Result<i64>;
+    async fn list_opted_out(&self, emails: &[String]) -> anyhow::Result<Vec<String>>;
+}
+
+pub type DynUnsubscriptionRepositoryTrait = Arc<dyn UnsubscriptionRepositoryTrait + Send + Sync>;
+
+#[derive(Clone)]
+pub struct UnsubscriptionRepository {
+    pool: ServiceConnectionPool,
+}
+
+impl UnsubscriptionRepository {
+    pub fn new(pool: ServiceConnectionPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UnsubscriptionRepositoryTrait for UnsubscriptionRepository {
+    async fn unsubscribe(
+        &self,
+        email: &str,
+        delivery_id: i64,
+        campaign_id: Option<i64>,
+    ) -> anyhow::Result<i64> {
+        // A campaign message only unsubscribes from the groups it was sent to,
+        // anything else unsubscribes from every group.
+        let removed = query!(
+            r#"
+                with targeted as (
+                    select
+                        id,
+                        email,
+                        group_id
+                    from subscriber
+                    where
+                        lower(email) = lower($1::varchar)
+                        and (
+                            $3::bigint is null
+                            or group_id in (
+                                select group_id
+                                from campaign_group
+                                where campaign_id = $3::bigint
+                            )
+                        )
+                ),
+                recorded as (
+                    insert into unsubscription (
+                            email,
+                            group_id,
+                            delivery_id
+                        )
+                    select
+                        email,
+                        group_id,
+                        $2::bigint
+                    from targeted
+                )
+                delete from subscriber
+                where id in (select id from targeted)
+            "#,
+            email,
+            delivery_id,
+            campaign_id,
+        )
+        .execute(&self.pool)
+        .await
+        .context("an unexpected error occured while unsubscribing")?
+        .rows_affected() as i64;
+
+        Ok(removed)
+    }
+
+    async fn list_opted_out(&self, emails: &[String]) -> anyhow::Result<Vec<String>> {
+        // Joining any group again opts an address back in.
+        let opted_out = query!(
+            r#"
+                select
+                    target.email as "email!"
+                from unnest($1::varchar[]) as target(email)
+                where
+                    exists (
+                        select 1
+                        from unsubscription as u
+                        where lower(u.email) = lower(target.email)
+                    )
+                    and not exists (
+                        select 1
+                        from subscriber as s
+                        where lower(s.email) = lower(target.email)
+                    )
+            "#,
+            emails,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("an unexpected error occured while obtaining the opted out addresses")?;
+
+        Ok(opted_out.into_iter().map(|row| row.email).collect())
+    }
+}
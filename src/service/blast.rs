@@ -0,0 +1,320 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::stream::BoxStream;
+use tokio::sync::watch;
+
+use crate::proto::email::BlastProgressResponse;
+use crate::repository::delivery::DeliveryStatus;
+
+/// Most errors a blast keeps around for its watchers.
+const RECENT_ERRORS: usize = 10;
+
+/// Where a blast stands. Recipients waiting for their local send time stay
+/// queued, and the ones a cancelled blast never got to count as skipped.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlastProgress {
+    pub queued: i64,
+    pub sent: i64,
+    pub failed: i64,
+    pub skipped: i64,
+    pub recent_errors: VecDeque<String>,
+    pub finished: bool,
+    pub cancelled: bool,
+}
+
+impl BlastProgress {
+    /// Progress of a blast as told by the deliveries it recorded, counted the
+    /// way a running blast counts them. Recipients without a delivery are
+    /// still to be sent to while the blast runs, and were skipped once it was
+    /// cancelled.
+    pub fn recorded(
+        counts: &HashMap<String, i64>,
+        recipient_count: i64,
+        finished: bool,
+        cancelled: bool,
+    ) -> Self {
+        let count = |status: DeliveryStatus| counts.get(status.as_str()).copied().unwrap_or(0);
+        let queued = count(DeliveryStatus::Queued) + count(DeliveryStatus::Sending);
+        let sent = count(DeliveryStatus::Sent) + count(DeliveryStatus::Bounced);
+        let failed = count(DeliveryStatus::Failed);
+        let skipped = count(DeliveryStatus::Capped)
+            + count(DeliveryStatus::Skipped)
+            + count(DeliveryStatus::Cancelled);
+        let unrecorded = (recipient_count - queued - sent - failed - skipped).max(0);
+
+        let mut progress = Self {
+            queued,
+            sent,
+            failed,
+            skipped,
+            recent_errors: Default::default(),
+            finished,
+            cancelled,
+        };
+        match (finished, cancelled) {
+            (false, _) => progress.queued += unrecorded,
+            (true, true) => progress.skipped += unrecorded,
+            // Only a recipient whose delivery could not be recorded at all
+            // is left without one by a finished blast.
+            (true, false) => progress.failed += unrecorded,
+        }
+        progress
+    }
+
+    pub fn into_blast_progress_response(self, id: i64) -> BlastProgressResponse {
+        BlastProgressResponse {
+            id,
+            total: self.queued + self.sent + self.failed + self.skipped,
+            queued: self.queued,
+            sent: self.sent,
+            failed: self.failed,
+            skipped: self.skipped,
+            recent_errors: self.recent_errors.into(),
+            finished: self.finished,
+            cancelled: self.cancelled,
+        }
+    }
+}
+
+/// A blast being sent, followed by its watchers and stopped between two
+/// recipients once cancelled.
+pub struct BlastRun {
+    progress: watch::Sender<BlastProgress>,
+    cancelled: AtomicBool,
+}
+
+impl BlastRun {
+    fn new(queued: i64, skipped: i64) -> Self {
+        let (progress, _) = watch::channel(BlastProgress {
+            queued,
+            skipped,
+            ..Default::default()
+        });
+
+        Self {
+            progress,
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn sent(&self) {
+        self.progress.send_modify(|progress| {
+            progress.queued -= 1;
+            progress.sent += 1;
+        });
+    }
+
+    pub fn failed(&self, error: String) {
+        self.progress.send_modify(|progress| {
+            progress.queued -= 1;
+            progress.failed += 1;
+            if progress.recent_errors.len() == RECENT_ERRORS {
+                progress.recent_errors.pop_front();
+            }
+            progress.recent_errors.push_back(error);
+        });
+    }
+
+    fn finish(&self) {
+        let cancelled = self.is_cancelled();
+        self.progress.send_modify(|progress| {
+            if cancelled {
+                progress.skipped += progress.queued;
+                progress.queued = 0;
+            }
+            progress.finished = true;
+            progress.cancelled = cancelled;
+        });
+    }
+}
+
+/// Blasts running in this instance, by campaign.
+#[derive(Default)]
+pub struct BlastTracker {
+    runs: Mutex<HashMap<i64, Arc<BlastRun>>>,
+}
+
+/// A blast from its start until it is dropped, which finishes it and stops
+/// following it.
+pub struct ActiveBlast<'a> {
+    tracker: &'a BlastTracker,
+    campaign_id: Option<i64>,
+    run: Arc<BlastRun>,
+}
+
+impl Deref for ActiveBlast<'_> {
+    type Target = BlastRun;
+
+    fn deref(&self) -> &BlastRun {
+        &self.run
+    }
+}
+
+impl Drop for ActiveBlast<'_> {
+    fn drop(&mut self) {
+        if let Some(campaign_id) = self.campaign_id {
+            self.tracker.runs.lock().unwrap().remove(&campaign_id);
+        }
+        self.run.finish();
+    }
+}
+
+impl BlastTracker {
+    /// Starts a blast with `queued` recipients left to send to after
+    /// `skipped` were left out. Only campaign blasts can be watched.
+    pub fn start(&self, campaign_id: Option<i64>, queued: i64, skipped: i64) -> ActiveBlast<'_> {
+        let run = Arc::new(BlastRun::new(queued, skipped));
+        if let Some(campaign_id) = campaign_id {
+            self.runs.lock().unwrap().insert(campaign_id, run.clone());
+        }
+
+        ActiveBlast {
+            tracker: self,
+            campaign_id,
+            run,
+        }
+    }
+
+    /// Progress of the running blast of a campaign, as it changes until the
+    /// blast finishes. Changes made faster than they are read are merged.
+    pub fn watch(&self, campaign_id: i64) -> Option<BoxStream<'static, BlastProgress>> {
+        let mut progress = self
+            .runs
+            .lock()
+            .unwrap()
+            .get(&campaign_id)?
+            .progress
+            .subscribe();
+
+        Some(Box::pin(async_stream::stream! {
+            loop {
+                let current = progress.borrow_and_update().clone();
+                let finished = current.finished;
+                yield current;
+                if finished || progress.changed().await.is_err() {
+                    break;
+                }
+            }
+        }))
+    }
+
+    /// Asks the running blast of a campaign to stop before its next
+    /// recipient, false when no such blast runs here.
+    pub fn cancel(&self, campaign_id: i64) -> bool {
+        match self.runs.lock().unwrap().get(&campaign_id) {
+            Some(run) => {
+                run.cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::collections::HashMap;
+
+    use futures::StreamExt;
+
+    use super::{BlastProgress, BlastTracker};
+
+    #[tokio::test]
+    async fn blast_tracker_test() {
+        let tracker = BlastTracker::default();
+
+        let blast = tracker.start(Some(1), 3, 1);
+        let progress = tracker.watch(1).unwrap();
+        blast.sent();
+        blast.failed("failed to deliver blast email to \"a@test.com\"".to_string());
+        blast.sent();
+        drop(blast);
+        let last = progress.collect::<Vec<BlastProgress>>().await.pop();
+
+        assert_eq!(
+            last,
+            Some(BlastProgress {
+                queued: 0,
+                sent: 2,
+                failed: 1,
+                skipped: 1,
+                recent_errors: vec!["failed to deliver blast email to \"a@test.com\"".to_string()]
+                    .into(),
+                finished: true,
+                cancelled: false,
+            })
+        );
+        assert!(tracker.watch(1).is_none());
+    }
+
+    #[tokio::test]
+    async fn cancel_blast_test() {
+        let tracker = BlastTracker::default();
+
+        let untracked = tracker.start(None, 1, 0);
+        let blast = tracker.start(Some(1), 3, 0);
+        let cancelled = tracker.cancel(1);
+        let progress = tracker.watch(1).unwrap();
+        blast.sent();
+        drop(blast);
+        let last = progress
+            .collect::<Vec<BlastProgress>>()
+            .await
+            .pop()
+            .unwrap();
+
+        assert!(cancelled);
+        assert!(!tracker.cancel(2));
+        assert!(!untracked.is_cancelled());
+        assert!(last.cancelled);
+        assert_eq!((last.queued, last.sent, last.skipped), (0, 1, 2));
+    }
+
+    #[test]
+    fn recorded_progress_test() {
+        let counts = [
+            ("sending", 1),
+            ("sent", 2),
+            ("bounced", 1),
+            ("failed", 1),
+            ("capped", 1),
+            ("skipped", 2),
+        ]
+        .into_iter()
+        .map(|(status, count)| (status.to_string(), count))
+        .collect::<HashMap<String, i64>>();
+
+        let running = BlastProgress::recorded(&counts, 10, false, false);
+        let cancelled = BlastProgress::recorded(&counts, 10, true, true);
+        let finished = BlastProgress::recorded(&counts, 8, true, false);
+
+        assert_eq!(
+            (
+                running.queued,
+                running.sent,
+                running.failed,
+                running.skipped
+            ),
+            (3, 3, 1, 3)
+        );
+        assert!(!running.finished);
+        assert_eq!((cancelled.queued, cancelled.skipped), (1, 5));
+        assert!(cancelled.cancelled);
+        assert_eq!(
+            (finished.queued, finished.failed, finished.skipped),
+            (1, 1, 3)
+        );
+        assert!(finished.finished);
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use madtofan_microservice_common::errors::ServiceResult;
+use mockall::automock;
+use tracing::log::info;
+
+use crate::{
+    repository::{
+        delivery::{DeliveryEntity, DeliveryStatus, DynDeliveryRepositoryTrait},
+        suppression::{DynSuppressionRepositoryTrait, NewSuppression, SuppressionReason},
+    },
+    service::{
+        report::{parse_report, DeliveryReport, ReportKind},
+        verp::DynVerp,
+    },
+};
+
+#[automock]
+#[async_trait]
+pub trait BounceServiceTrait {
+    async fn process_report(&self, raw: Vec<u8>) -> ServiceResult<Vec<DeliveryReport>>;
+}
+
+pub type DynBounceServiceTrait = Arc<dyn BounceServiceTrait + Sync + Send>;
+
+pub struct BounceService {
+    delivery_repository: DynDeliveryRepositoryTrait,
+    suppression_repository: DynSuppressionRepositoryTrait,
+    verp: Option<DynVerp>,
+}
+
+impl BounceService {
+    pub fn new(
+        delivery_repository: DynDeliveryRepositoryTrait,
+        suppression_repository: DynSuppressionRepositoryTrait,
+        verp: Option<DynVerp>,
+    ) -> Self {
+        Self {
+            delivery_repository,
+            suppression_repository,
+            verp,
+        }
+    }
+
+    /// Prefers the VERP token on the address the report came back to, and only
+    /// falls back to the Message-ID quoted in the report when there is none.
+    async fn find_delivery(
+        &self,
+        report: &DeliveryReport,
+    ) -> ServiceResult<Option<DeliveryEntity>> {
+        if let Some(verp) = &self.verp {
+            for verp_token in report
+                .addressed_to
+                .iter()
+                .filter_map(|address| verp.decode(address))
+            {
+                if let Some(delivery) = self
+                    .delivery_repository
+                    .get_delivery_by_verp_token(&verp_token)
+                    .await?
+                {
+                    return Ok(Some(delivery));
+                }
+            }
+        }
+
+        match &report.message_id {
+            Some(message_id) => Ok(self
+                .delivery_repository
+                .get_delivery_by_message_id(message_id)
+                .await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Reports that cannot be tied to a delivery this service sent are skipped,
+    /// so a forged or misrouted report cannot suppress an arbitrary address.
+    async fn apply_report(
+        &self,
+        mut report: DeliveryReport,
+    ) -> ServiceResult<Option<DeliveryReport>> {
+        let delivery = match self.find_delivery(&report).await? {
+            Some(delivery) => delivery,
+            None => {
+                info!(
+                    "report for {:?} does not match a sent message, skipping",
+                    &report.recipient
+                );
+                return Ok(None);
+            }
+        };
+
+        // The delivery log is more reliable than a recipient the remote MTA may
+        // have rewritten.
+        report.recipient = delivery.recipient.clone();
+        report.message_id = Some(delivery.message_id.clone());
+
+        let reason = match report.kind {
+            ReportKind::HardBounce => {
+                self.delivery_repository
+                    .update_delivery_status(
+                        delivery.id,
+                        DeliveryStatus::Bounced,
+                        &report.diagnostic,
+                    )
+                    .await?;
+                SuppressionReason::HardBounce
+            }
+            ReportKind::Complaint => SuppressionReason::Complaint,
+            ReportKind::SoftBounce => {
+                info!(
+                    "soft bounce for {:?}: {}",
+                    &report.recipient, &report.diagnostic
+                );
+                return Ok(Some(report));
+            }
+        };
+
+        info!(
+            "suppressing {:?} after {}",
+            &report.recipient,
+            reason.as_str()
+        );
+        self.suppression_repository
+            .add_suppression(&NewSuppression {
+                email: report.recipient.clone(),
+                reason,
+                detail: report.diagnostic.clone(),
+                message_id: report.message_id.clone(),
+            })
+            .await?;
+
+        Ok(Some(report))
+    }
+}
+
+#[async_trait]
+impl BounceServiceTrait for BounceService {
+    async fn process_report(&self, raw: Vec<u8>) -> ServiceResult<Vec<DeliveryReport>> {
+        let reports = parse_report(&raw);
+        if reports.is_empty() {
+            info!("inbound message is not a delivery or complaint report");
+        }
+
+        let mut processed = Vec::with_capacity(reports.len());
+        for report in reports {
+            if let Some(report) = self.apply_report(report).await? {
+                processed.push(report);
+            }
+        }
+
+        Ok(processed)
+    }
+}
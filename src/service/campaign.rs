@@ -0,0 +1,788 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use lettre::message::Mailbox;
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use mockall::automock;
+use sqlx::types::time::OffsetDateTime;
+use tracing::log::{error, info};
+
+use crate::{
+    proto::email::{
+        BlastProgressResponse, CampaignResponse, CampaignsResponse, PreviewCampaignResponse,
+        PreviewEmailResponse, TestBlastResponse,
+    },
+    repository::{
+        campaign::{CampaignEntity, CampaignStatus, DynCampaignRepositoryTrait, NewCampaign},
+        delivery::MessageClass,
+        group::{DynGroupRepositoryTrait, FooterTemplate, GroupEntity},
+        identity::DynSenderIdentityRepositoryTrait,
+        template::DynTemplateRepositoryTrait,
+    },
+    service::{
+        email::{DynEmailServiceTrait, EmailContent},
+        locale::normalize_locale,
+        schedule::parse_time_of_day,
+    },
+};
+
+#[automock]
+#[async_trait]
+pub trait CampaignServiceTrait {
+    async fn create_campaign(
+        &self,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+    ) -> ServiceResult<CampaignResponse>;
+    async fn edit_campaign(
+        &self,
+        id: i64,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+    ) -> ServiceResult<CampaignResponse>;
+    async fn preview_campaign(&self, id: i64) -> ServiceResult<PreviewCampaignResponse>;
+    async fn send_campaign(
+        &self,
+        id: i64,
+        scheduled_at: Option<i64>,
+    ) -> ServiceResult<CampaignResponse>;
+    async fn start_campaign(&self, id: i64) -> ServiceResult<CampaignResponse>;
+    async fn cancel_campaign(&self, id: i64) -> ServiceResult<CampaignResponse>;
+    async fn list_campaigns(
+        &self,
+        status: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> ServiceResult<CampaignsResponse>;
+    async fn send_due_campaigns(&self) -> ServiceResult<()>;
+    async fn preview_email(
+        &self,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+        recipient: String,
+    ) -> ServiceResult<PreviewEmailResponse>;
+    async fn test_blast(
+        &self,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+        sample: String,
+    ) -> ServiceResult<TestBlastResponse>;
+    async fn watch_blast(
+        &self,
+        id: i64,
+    ) -> ServiceResult<BoxStream<'static, BlastProgressResponse>>;
+    async fn cancel_blast(&self, id: i64) -> ServiceResult<CampaignResponse>;
+}
+
+pub type DynCampaignServiceTrait = Arc<dyn CampaignServiceTrait + Sync + Send>;
+
+/// How often the status of a campaign is checked while its blast is sent, or
+/// watched from an instance other than the one sending it.
+const BLAST_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+pub struct CampaignService {
+    campaign_repository: DynCampaignRepositoryTrait,
+    group_repository: DynGroupRepositoryTrait,
+    identity_repository: DynSenderIdentityRepositoryTrait,
+    template_repository: DynTemplateRepositoryTrait,
+    email_service: DynEmailServiceTrait,
+    test_subject_prefix: String,
+}
+
+impl CampaignService {
+    pub fn new(
+        campaign_repository: DynCampaignRepositoryTrait,
+        group_repository: DynGroupRepositoryTrait,
+        identity_repository: DynSenderIdentityRepositoryTrait,
+        template_repository: DynTemplateRepositoryTrait,
+        email_service: DynEmailServiceTrait,
+        test_subject_prefix: String,
+    ) -> Self {
+        Self {
+            campaign_repository,
+            group_repository,
+            identity_repository,
+            template_repository,
+            email_service,
+            test_subject_prefix,
+        }
+    }
+
+    fn validate_campaign(campaign: &NewCampaign, groups: &[String]) -> ServiceResult<()> {
+        // A templated campaign takes its subject and bodies from the template.
+        if campaign.template.is_empty() && campaign.subject.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "campaign subject cannot be empty",
+            )));
+        }
+        if campaign.template.is_empty()
+            && campaign.text_body.trim().is_empty()
+            && campaign.markdown_body.trim().is_empty()
+        {
+            return Err(ServiceError::BadRequest(String::from(
+                "campaign text body cannot be empty",
+            )));
+        }
+        if !campaign.markdown_body.is_empty() && !campaign.html_body.is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "campaign cannot have both a markdown and an html body",
+            )));
+        }
+        if !campaign.locale.is_empty() && normalize_locale(&campaign.locale).is_none() {
+            return Err(ServiceError::BadRequest(String::from(
+                "campaign locale is invalid",
+            )));
+        }
+        if !campaign.local_send_time.is_empty()
+            && parse_time_of_day(&campaign.local_send_time).is_none()
+        {
+            return Err(ServiceError::BadRequest(String::from(
+                "campaign local send time must be written as HH:MM",
+            )));
+        }
+        if groups.is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "campaign must target at least one group",
+            )));
+        }
+        if !campaign.sender.is_empty() && campaign.sender.parse::<Mailbox>().is_err() {
+            return Err(ServiceError::BadRequest(String::from(
+                "campaign sender is invalid",
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn get_groups(&self, names: &[String]) -> ServiceResult<Vec<GroupEntity>> {
+        let mut groups = Vec::with_capacity(names.len());
+        for name in names {
+            match self.group_repository.get_group(name).await? {
+                Some(group) => groups.push(group),
+                None => {
+                    error!("group {:?} does not exists", name);
+                    return Err(ServiceError::ObjectConflict(String::from(
+                        "group name does not exist",
+                    )));
+                }
+            }
+        }
+
+        Ok(groups)
+    }
+
+    async fn ensure_identity_exists(&self, campaign: &NewCampaign) -> ServiceResult<()> {
+        if campaign.identity.is_empty() {
+            return Ok(());
+        }
+
+        if self
+            .identity_repository
+            .get_identity(&campaign.identity)
+            .await?
+            .is_none()
+        {
+            error!("sender identity {:?} does not exist", &campaign.identity);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "sender identity does not exist",
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_template_exists(&self, campaign: &NewCampaign) -> ServiceResult<()> {
+        if campaign.template.is_empty() {
+            return Ok(());
+        }
+
+        if self
+            .template_repository
+            .list_template_variants(&campaign.template)
+            .await?
+            .is_empty()
+        {
+            error!("template {:?} does not exist", &campaign.template);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "template does not exist",
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Identity of the first targeted group that has a default one, used when
+    /// the campaign names neither an identity nor a sender of its own.
+    async fn get_group_identity(&self, groups: &[GroupEntity]) -> ServiceResult<Option<String>> {
+        for group in groups {
+            if let Some(identity) = self
+                .identity_repository
+                .get_group_identity(group.id)
+                .await?
+            {
+                return Ok(Some(identity.name));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Footer override of the first targeted group that has one.
+    async fn get_group_footer(
+        &self,
+        groups: &[GroupEntity],
+    ) -> ServiceResult<Option<FooterTemplate>> {
+        for group in groups {
+            if let Some(footer) = self.group_repository.get_group_footer(group.id).await? {
+                return Ok(Some(footer));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Content the campaign is sent with, falling back to the defaults of
+    /// its groups.
+    async fn campaign_content(
+        &self,
+        campaign: NewCampaign,
+        groups: &[GroupEntity],
+    ) -> ServiceResult<EmailContent> {
+        let identity = match Some(campaign.identity).filter(|identity| !identity.is_empty()) {
+            Some(identity) => Some(identity),
+            None if campaign.sender.is_empty() => self.get_group_identity(groups).await?,
+            None => None,
+        };
+        let footer = self.get_group_footer(groups).await?;
+
+        Ok(EmailContent {
+            subject: campaign.subject,
+            text_body: campaign.text_body,
+            html_body: Some(campaign.html_body).filter(|body| !body.is_empty()),
+            markdown_body: Some(campaign.markdown_body).filter(|body| !body.is_empty()),
+            sender: Some(campaign.sender).filter(|sender| !sender.is_empty()),
+            identity,
+            footer,
+            template: Some(campaign.template).filter(|template| !template.is_empty()),
+            locale: Some(campaign.locale).filter(|locale| !locale.is_empty()),
+            local_send_time: Some(campaign.local_send_time)
+                .filter(|local_send_time| !local_send_time.is_empty()),
+            message_class: campaign.message_class,
+            ..Default::default()
+        })
+    }
+
+    /// Content a stored campaign is sent with.
+    async fn stored_content(&self, campaign: CampaignEntity) -> ServiceResult<EmailContent> {
+        let groups = self
+            .campaign_repository
+            .list_campaign_groups(campaign.id)
+            .await?;
+
+        self.campaign_content(
+            NewCampaign {
+                name: campaign.name,
+                subject: campaign.subject,
+                text_body: campaign.text_body,
+                html_body: campaign.html_body,
+                markdown_body: campaign.markdown_body,
+                sender: campaign.sender,
+                identity: campaign.identity,
+                template: campaign.template,
+                locale: campaign.locale,
+                local_send_time: campaign.local_send_time,
+                message_class: MessageClass::parse(&campaign.message_class).unwrap_or_default(),
+            },
+            &groups,
+        )
+        .await
+    }
+
+    async fn get_existing_campaign(&self, id: i64) -> ServiceResult<CampaignEntity> {
+        match self.campaign_repository.get_campaign(id).await? {
+            Some(campaign) => Ok(campaign),
+            None => {
+                error!("campaign {:?} does not exist", id);
+                Err(ServiceError::ObjectConflict(String::from(
+                    "campaign does not exist",
+                )))
+            }
+        }
+    }
+
+    async fn campaign_response(&self, campaign: CampaignEntity) -> ServiceResult<CampaignResponse> {
+        let groups = self
+            .campaign_repository
+            .list_campaign_groups(campaign.id)
+            .await?
+            .into_iter()
+            .map(|group| group.name)
+            .collect::<Vec<String>>();
+
+        Ok(campaign.into_campaign_response(groups))
+    }
+
+    async fn transition(
+        &self,
+        id: i64,
+        from: &[CampaignStatus],
+        to: CampaignStatus,
+        scheduled_at: Option<OffsetDateTime>,
+    ) -> ServiceResult<CampaignEntity> {
+        match self
+            .campaign_repository
+            .transition_campaign(id, from, to, scheduled_at)
+            .await?
+        {
+            Some(campaign) => Ok(campaign),
+            None => {
+                error!("campaign {:?} cannot be moved to {:?}", id, to.as_str());
+                Err(ServiceError::ObjectConflict(format!(
+                    "campaign cannot be moved to {}",
+                    to.as_str()
+                )))
+            }
+        }
+    }
+
+    /// Moves a campaign to sending, noting how many recipients its blast is
+    /// about to go out to.
+    async fn begin_dispatch(&self, id: i64) -> ServiceResult<(CampaignEntity, Vec<String>)> {
+        let campaign = self
+            .transition(
+                id,
+                &[CampaignStatus::Draft, CampaignStatus::Scheduled],
+                CampaignStatus::Sending,
+                None,
+            )
+            .await?;
+
+        info!("sending campaign {:?}", id);
+        let recipients = self
+            .campaign_repository
+            .list_campaign_recipients(id)
+            .await?;
+        self.campaign_repository
+            .set_recipient_count(id, recipients.len() as i64)
+            .await?;
+
+        Ok((campaign, recipients))
+    }
+
+    /// Blasts a campaign, stopping the blast once the campaign is cancelled
+    /// from another instance, which only its status tells.
+    async fn blast_until_cancelled(
+        &self,
+        id: i64,
+        recipients: Vec<String>,
+        content: EmailContent,
+    ) -> ServiceResult<()> {
+        let blast = self
+            .email_service
+            .blast_email(recipients, content, Some(id));
+        tokio::pin!(blast);
+        let mut poll = tokio::time::interval(BLAST_POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                blast_result = &mut blast => return blast_result,
+                _ = poll.tick() => {
+                    if let Ok(Some(campaign)) = self.campaign_repository.get_campaign(id).await {
+                        if CampaignStatus::parse(&campaign.status) == Some(CampaignStatus::Cancelled) {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        info!("campaign {:?} was cancelled, stopping its blast", id);
+        if let Err(err) = self.email_service.cancel_blast(id).await {
+            error!("failed to cancel the blast of campaign {:?}: {:?}", id, err);
+        }
+        blast.await
+    }
+
+    /// Sends the blast of a campaign moved to sending, then marks it sent.
+    async fn finish_dispatch(
+        &self,
+        campaign: CampaignEntity,
+        recipients: Vec<String>,
+    ) -> ServiceResult<CampaignEntity> {
+        let id = campaign.id;
+        let blast_result = match self.stored_content(campaign).await {
+            Ok(content) => self.blast_until_cancelled(id, recipients, content).await,
+            Err(err) => Err(err),
+        };
+
+        let campaign = match self
+            .campaign_repository
+            .transition_campaign(id, &[CampaignStatus::Sending], CampaignStatus::Sent, None)
+            .await?
+        {
+            Some(campaign) => campaign,
+            // Its blast was cancelled while being sent.
+            None => self.get_existing_campaign(id).await?,
+        };
+        blast_result?;
+
+        info!("campaign successfully sent");
+        Ok(campaign)
+    }
+
+    async fn dispatch_campaign(&self, id: i64) -> ServiceResult<CampaignEntity> {
+        let (campaign, recipients) = self.begin_dispatch(id).await?;
+
+        self.finish_dispatch(campaign, recipients).await
+    }
+
+    /// Sends the deliveries of a campaign that were queued for a later local
+    /// time or held back by quiet hours, now that they are due.
+    async fn send_queued(&self, campaign: CampaignEntity) -> ServiceResult<()> {
+        let id = campaign.id;
+        let content = self.stored_content(campaign).await?;
+
+        self.email_service.send_queued_deliveries(id, content).await
+    }
+}
+
+#[async_trait]
+impl CampaignServiceTrait for CampaignService {
+    async fn create_campaign(
+        &self,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+    ) -> ServiceResult<CampaignResponse> {
+        Self::validate_campaign(&campaign, &groups)?;
+        self.ensure_identity_exists(&campaign).await?;
+        self.ensure_template_exists(&campaign).await?;
+        let groups = self.get_groups(&groups).await?;
+
+        info!("creating campaign {:?}", &campaign.name);
+        let campaign = self
+            .campaign_repository
+            .add_campaign(&campaign, &groups)
+            .await?;
+
+        info!("campaign successfully created");
+        self.campaign_response(campaign).await
+    }
+
+    async fn edit_campaign(
+        &self,
+        id: i64,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+    ) -> ServiceResult<CampaignResponse> {
+        Self::validate_campaign(&campaign, &groups)?;
+        self.ensure_identity_exists(&campaign).await?;
+        self.ensure_template_exists(&campaign).await?;
+        let groups = self.get_groups(&groups).await?;
+        self.get_existing_campaign(id).await?;
+
+        info!("editing campaign {:?}", id);
+        let campaign = match self
+            .campaign_repository
+            .update_campaign(id, &campaign, &groups)
+            .await?
+        {
+            Some(campaign) => campaign,
+            None => {
+                error!("campaign {:?} is no longer editable", id);
+                return Err(ServiceError::ObjectConflict(String::from(
+                    "only draft or scheduled campaigns can be edited",
+                )));
+            }
+        };
+
+        info!("campaign successfully edited");
+        self.campaign_response(campaign).await
+    }
+
+    async fn preview_campaign(&self, id: i64) -> ServiceResult<PreviewCampaignResponse> {
+        let campaign = self.get_existing_campaign(id).await?;
+        let recipient_count = self
+            .campaign_repository
+            .list_campaign_recipients(id)
+            .await?
+            .len() as i64;
+
+        Ok(PreviewCampaignResponse {
+            subject: campaign.subject,
+            text_body: campaign.text_body,
+            html_body: campaign.html_body,
+            sender: campaign.sender,
+            recipient_count,
+        })
+    }
+
+    async fn send_campaign(
+        &self,
+        id: i64,
+        scheduled_at: Option<i64>,
+    ) -> ServiceResult<CampaignResponse> {
+        self.get_existing_campaign(id).await?;
+
+        let scheduled_at = scheduled_at
+            .map(OffsetDateTime::from_unix_timestamp)
+            .transpose()
+            .map_err(|_| ServiceError::BadRequest(String::from("scheduled time is invalid")))?
+            .filter(|scheduled_at| *scheduled_at > OffsetDateTime::now_utc());
+
+        let campaign = match scheduled_at {
+            Some(scheduled_at) => {
+                info!("scheduling campaign {:?} at {:?}", id, scheduled_at);
+                self.transition(
+                    id,
+                    &[CampaignStatus::Draft, CampaignStatus::Scheduled],
+                    CampaignStatus::Scheduled,
+                    Some(scheduled_at),
+                )
+                .await?
+            }
+            None => self.dispatch_campaign(id).await?,
+        };
+
+        self.campaign_response(campaign).await
+    }
+
+    async fn start_campaign(&self, id: i64) -> ServiceResult<CampaignResponse> {
+        self.get_existing_campaign(id).await?;
+
+        // The campaign is sending by the time this returns, so its blast can
+        // be watched and cancelled right away.
+        let (campaign, recipients) = self.begin_dispatch(id).await?;
+        let response = self.campaign_response(campaign.clone()).await?;
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = service.finish_dispatch(campaign, recipients).await {
+                error!("blast of campaign {:?} failed: {:?}", id, err);
+            }
+        });
+
+        Ok(response)
+    }
+
+    async fn cancel_campaign(&self, id: i64) -> ServiceResult<CampaignResponse> {
+        self.get_existing_campaign(id).await?;
+
+        info!("cancelling campaign {:?}", id);
+        let campaign = self
+            .transition(
+                id,
+                &[CampaignStatus::Draft, CampaignStatus::Scheduled],
+                CampaignStatus::Cancelled,
+                None,
+            )
+            .await?;
+
+        info!("campaign successfully cancelled");
+        self.campaign_response(campaign).await
+    }
+
+    async fn list_campaigns(
+        &self,
+        status: Option<String>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> ServiceResult<CampaignsResponse> {
+        let status = match status.filter(|status| !status.is_empty()) {
+            Some(status) => Some(CampaignStatus::parse(&status).ok_or_else(|| {
+                ServiceError::BadRequest(format!("campaign status {:?} is unknown", status))
+            })?),
+            None => None,
+        };
+
+        let campaign_entities = self
+            .campaign_repository
+            .list_campaigns(status, offset, limit)
+            .await?;
+        let count = self.campaign_repository.get_campaigns_count(status).await?;
+
+        let mut campaigns = Vec::with_capacity(campaign_entities.len());
+        for campaign in campaign_entities {
+            campaigns.push(self.campaign_response(campaign).await?);
+        }
+
+        Ok(CampaignsResponse { campaigns, count })
+    }
+
+    async fn send_due_campaigns(&self) -> ServiceResult<()> {
+        let due_campaigns = self.campaign_repository.list_due_campaigns().await?;
+
+        for campaign in due_campaigns {
+            if let Err(err) = self.dispatch_campaign(campaign.id).await {
+                error!("scheduled campaign {:?} failed: {:?}", campaign.id, err);
+            }
+        }
+
+        let queued_campaigns = self
+            .campaign_repository
+            .list_campaigns_with_due_deliveries()
+            .await?;
+
+        for campaign in queued_campaigns {
+            let id = campaign.id;
+            if let Err(err) = self.send_queued(campaign).await {
+                error!("queued deliveries of campaign {:?} failed: {:?}", id, err);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn preview_email(
+        &self,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+        recipient: String,
+    ) -> ServiceResult<PreviewEmailResponse> {
+        Self::validate_campaign(&campaign, &groups)?;
+        self.ensure_identity_exists(&campaign).await?;
+        self.ensure_template_exists(&campaign).await?;
+        let groups = self.get_groups(&groups).await?;
+
+        let content = self.campaign_content(campaign, &groups).await?;
+        let preview = self
+            .email_service
+            .preview_email(recipient.clone(), content)
+            .await?;
+
+        Ok(PreviewEmailResponse {
+            recipient,
+            subject: preview.subject,
+            raw_message: preview.raw_message,
+            text_body: preview.text_body,
+            html_body: preview.html_body.unwrap_or_default(),
+        })
+    }
+
+    async fn test_blast(
+        &self,
+        campaign: NewCampaign,
+        groups: Vec<String>,
+        sample: String,
+    ) -> ServiceResult<TestBlastResponse> {
+        Self::validate_campaign(&campaign, &groups)?;
+        self.ensure_identity_exists(&campaign).await?;
+        self.ensure_template_exists(&campaign).await?;
+        let groups = self.get_groups(&groups).await?;
+
+        let mut seeds = Vec::new();
+        for group in &groups {
+            for seed in self.group_repository.list_group_seeds(group.id).await? {
+                if !seeds.contains(&seed) {
+                    seeds.push(seed);
+                }
+            }
+        }
+        if seeds.is_empty() {
+            error!("no seeds for a test blast of {:?}", &campaign.name);
+            return Err(ServiceError::BadRequest(String::from(
+                "the targeted groups have no seeds",
+            )));
+        }
+
+        info!(
+            "test blasting {:?} to {} seeds as {:?}",
+            &campaign.name,
+            seeds.len(),
+            &sample
+        );
+        let content = self.campaign_content(campaign, &groups).await?;
+        self.email_service
+            .test_blast(
+                seeds.clone(),
+                sample.clone(),
+                content,
+                self.test_subject_prefix.clone(),
+            )
+            .await?;
+
+        info!("test blast successfully sent");
+        Ok(TestBlastResponse { sample, seeds })
+    }
+
+    async fn watch_blast(
+        &self,
+        id: i64,
+    ) -> ServiceResult<BoxStream<'static, BlastProgressResponse>> {
+        let campaign = self.get_existing_campaign(id).await?;
+        if matches!(
+            CampaignStatus::parse(&campaign.status),
+            Some(CampaignStatus::Draft | CampaignStatus::Scheduled)
+        ) {
+            error!("campaign {:?} has not been sent", id);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "campaign has not been sent",
+            )));
+        }
+
+        if let Some(progress) = self.email_service.watch_blast(id).await {
+            return Ok(Box::pin(
+                progress.map(move |progress| progress.into_blast_progress_response(id)),
+            ));
+        }
+
+        // The blast finished, or runs in another instance, so its progress is
+        // read back from its deliveries until the campaign stops sending.
+        let service = self.clone();
+        Ok(Box::pin(async_stream::stream! {
+            let mut campaign = campaign;
+            loop {
+                let status = CampaignStatus::parse(&campaign.status);
+                let finished = status != Some(CampaignStatus::Sending);
+                let progress = match service
+                    .email_service
+                    .recorded_blast_progress(
+                        id,
+                        campaign.recipient_count.unwrap_or_default(),
+                        finished,
+                        status == Some(CampaignStatus::Cancelled),
+                    )
+                    .await
+                {
+                    Ok(progress) => progress,
+                    Err(err) => {
+                        error!("failed to read the progress of campaign {:?}: {:?}", id, err);
+                        break;
+                    }
+                };
+                yield progress.into_blast_progress_response(id);
+                if finished {
+                    break;
+                }
+
+                tokio::time::sleep(BLAST_POLL_INTERVAL).await;
+                campaign = match service.campaign_repository.get_campaign(id).await {
+                    Ok(Some(campaign)) => campaign,
+                    _ => {
+                        error!("campaign {:?} could not be read back", id);
+                        break;
+                    }
+                };
+            }
+        }))
+    }
+
+    async fn cancel_blast(&self, id: i64) -> ServiceResult<CampaignResponse> {
+        self.get_existing_campaign(id).await?;
+
+        info!("cancelling blast of campaign {:?}", id);
+        let stopped = self.email_service.cancel_blast(id).await?;
+        // A sent campaign may still have deliveries waiting for the local
+        // send time of their recipients.
+        let from: &[CampaignStatus] = if stopped {
+            &[CampaignStatus::Sending, CampaignStatus::Sent]
+        } else {
+            &[CampaignStatus::Sending]
+        };
+        let campaign = self
+            .transition(id, from, CampaignStatus::Cancelled, None)
+            .await?;
+
+        info!("blast successfully cancelled");
+        self.campaign_response(campaign).await
+    }
+}
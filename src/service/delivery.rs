@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use mockall::automock;
+use tracing::log::error;
+
+use crate::proto::email::{DeliveriesResponse, DeliveryResponse};
+use crate::repository::delivery::DynDeliveryRepositoryTrait;
+
+#[automock]
+#[async_trait]
+pub trait DeliveryServiceTrait {
+    async fn get_message_status(&self, message_id: String) -> ServiceResult<DeliveryResponse>;
+    async fn list_deliveries(
+        &self,
+        recipient: Option<String>,
+        campaign_id: Option<i64>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> ServiceResult<DeliveriesResponse>;
+}
+
+pub type DynDeliveryServiceTrait = Arc<dyn DeliveryServiceTrait + Sync + Send>;
+
+pub struct DeliveryService {
+    repository: DynDeliveryRepositoryTrait,
+}
+
+impl DeliveryService {
+    pub fn new(repository: DynDeliveryRepositoryTrait) -> Self {
+        Self { repository }
+    }
+}
+
+#[async_trait]
+impl DeliveryServiceTrait for DeliveryService {
+    async fn get_message_status(&self, message_id: String) -> ServiceResult<DeliveryResponse> {
+        match self
+            .repository
+            .get_delivery_by_message_id(&message_id)
+            .await?
+        {
+            Some(delivery) => Ok(delivery.into_delivery_response()),
+            None => {
+                error!("message {:?} does not exist", &message_id);
+                Err(ServiceError::ObjectConflict(String::from(
+                    "message does not exist",
+                )))
+            }
+        }
+    }
+
+    async fn list_deliveries(
+        &self,
+        recipient: Option<String>,
+        campaign_id: Option<i64>,
+        offset: Option<i64>,
+        limit: Option<i64>,
+    ) -> ServiceResult<DeliveriesResponse> {
+        let delivery_entities = self
+            .repository
+            .list_deliveries(recipient.clone(), campaign_id, offset, limit)
+            .await?;
+        let count = self
+            .repository
+            .get_deliveries_count(recipient, campaign_id)
+            .await?;
+
+        Ok(DeliveriesResponse {
+            deliveries: delivery_entities
+                .into_iter()
+                .map(|delivery| delivery.into_delivery_response())
+                .collect::<Vec<DeliveryResponse>>(),
+            count,
+        })
+    }
+}
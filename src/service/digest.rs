@@ -0,0 +1,289 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use mockall::automock;
+use sqlx::types::time::OffsetDateTime;
+use tracing::log::{error, info};
+
+use crate::{
+    proto::email::DigestTypeResponse,
+    repository::{
+        digest::{
+            DigestCadence, DigestItemEntity, DigestItemStatus, DigestTypeEntity,
+            DynDigestRepositoryTrait, NewDigestItem, NewDigestType,
+        },
+        template::DynTemplateRepositoryTrait,
+    },
+    service::{email::DynEmailServiceTrait, merge::escape_html, validation::DynEmailValidator},
+};
+
+/// Placeholder in a digest template that the list of items is rendered in.
+pub const DIGEST_ITEMS_PLACEHOLDER: &str = "{{items}}";
+
+/// Items as a plain text list, one entry per item with its body indented
+/// below the title.
+pub fn render_items_text(items: &[DigestItemEntity]) -> String {
+    items
+        .iter()
+        .map(|item| {
+            let mut entry = format!("- {}", item.title);
+            for line in item.body.lines() {
+                entry.push_str("\n  ");
+                entry.push_str(line);
+            }
+            entry
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Items as an HTML list, their titles and bodies escaped.
+pub fn render_items_html(items: &[DigestItemEntity]) -> String {
+    let entries = items
+        .iter()
+        .map(|item| {
+            let body = item
+                .body
+                .lines()
+                .map(escape_html)
+                .collect::<Vec<String>>()
+                .join("<br>");
+            if body.is_empty() {
+                format!("<li><strong>{}</strong></li>", escape_html(&item.title))
+            } else {
+                format!(
+                    "<li><strong>{}</strong><br>{}</li>",
+                    escape_html(&item.title),
+                    body
+                )
+            }
+        })
+        .collect::<String>();
+
+    format!("<ul>{}</ul>", entries)
+}
+
+#[automock]
+#[async_trait]
+pub trait DigestServiceTrait {
+    async fn save_digest_type(
+        &self,
+        name: String,
+        cadence: String,
+        template: String,
+    ) -> ServiceResult<DigestTypeResponse>;
+    async fn enqueue_digest_item(
+        &self,
+        digest_type: String,
+        item: NewDigestItem,
+    ) -> ServiceResult<()>;
+    async fn send_due_digests(&self) -> ServiceResult<()>;
+}
+
+pub type DynDigestServiceTrait = Arc<dyn DigestServiceTrait + Sync + Send>;
+
+pub struct DigestService {
+    digest_repository: DynDigestRepositoryTrait,
+    template_repository: DynTemplateRepositoryTrait,
+    validator: DynEmailValidator,
+    email_service: DynEmailServiceTrait,
+}
+
+impl DigestService {
+    pub fn new(
+        digest_repository: DynDigestRepositoryTrait,
+        template_repository: DynTemplateRepositoryTrait,
+        validator: DynEmailValidator,
+        email_service: DynEmailServiceTrait,
+    ) -> Self {
+        Self {
+            digest_repository,
+            template_repository,
+            validator,
+            email_service,
+        }
+    }
+
+    /// Claims every pending item of a digest type for one recipient and sends
+    /// them in a single email. Items of a digest that failed to send are
+    /// released back to pending, so the next tick tries them again, unless the
+    /// digest could never be sent.
+    async fn send_digest(
+        &self,
+        digest_type: &DigestTypeEntity,
+        recipient: String,
+    ) -> ServiceResult<()> {
+        let items = self
+            .digest_repository
+            .claim_pending_items(digest_type.id, &recipient)
+            .await?;
+        let ids = items.iter().map(|item| item.id).collect::<Vec<i64>>();
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        match self
+            .email_service
+            .send_digest(recipient, digest_type.template.clone(), items)
+            .await
+        {
+            Ok(message_id) => {
+                self.digest_repository
+                    .update_items_status(&ids, DigestItemStatus::Sent, Some(message_id))
+                    .await?;
+                Ok(())
+            }
+            Err(err) => {
+                let status = match err {
+                    ServiceError::BadRequest(_) | ServiceError::ObjectConflict(_) => {
+                        DigestItemStatus::Failed
+                    }
+                    _ => DigestItemStatus::Pending,
+                };
+                self.digest_repository
+                    .update_items_status(&ids, status, None)
+                    .await?;
+                Err(err)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DigestServiceTrait for DigestService {
+    async fn save_digest_type(
+        &self,
+        name: String,
+        cadence: String,
+        template: String,
+    ) -> ServiceResult<DigestTypeResponse> {
+        if name.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "digest type name cannot be empty",
+            )));
+        }
+        let cadence = DigestCadence::parse(&cadence).ok_or_else(|| {
+            ServiceError::BadRequest(format!("digest cadence {:?} is unknown", cadence))
+        })?;
+        if self
+            .template_repository
+            .list_template_variants(&template)
+            .await?
+            .is_empty()
+        {
+            error!("template {:?} does not exist", &template);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "template does not exist",
+            )));
+        }
+
+        info!("saving digest type {:?}", &name);
+        let digest_type = self
+            .digest_repository
+            .save_digest_type(&NewDigestType {
+                name,
+                cadence,
+                template,
+            })
+            .await?;
+
+        info!("digest type successfully saved");
+        Ok(digest_type.into_digest_type_response())
+    }
+
+    async fn enqueue_digest_item(
+        &self,
+        digest_type: String,
+        item: NewDigestItem,
+    ) -> ServiceResult<()> {
+        if item.title.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "digest item title cannot be empty",
+            )));
+        }
+        let recipient = self.validator.validate(&item.recipient)?;
+        let digest_type = match self.digest_repository.get_digest_type(&digest_type).await? {
+            Some(digest_type) => digest_type,
+            None => {
+                error!("digest type {:?} does not exist", &digest_type);
+                return Err(ServiceError::ObjectConflict(String::from(
+                    "digest type does not exist",
+                )));
+            }
+        };
+
+        self.digest_repository
+            .add_digest_item(digest_type.id, &NewDigestItem { recipient, ..item })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn send_due_digests(&self) -> ServiceResult<()> {
+        let now = OffsetDateTime::now_utc();
+
+        for digest_type in self.digest_repository.list_digest_types().await? {
+            let cadence = match DigestCadence::parse(&digest_type.cadence) {
+                Some(cadence) => cadence,
+                None => {
+                    error!("digest type {:?} has no valid cadence", &digest_type.name);
+                    continue;
+                }
+            };
+            // A recipient's digest goes out once their oldest pending item
+            // has waited a full period, so they get at most one per period.
+            let recipients = self
+                .digest_repository
+                .list_due_recipients(digest_type.id, now - cadence.period())
+                .await?;
+
+            info!(
+                "sending {:?} digests to {} recipients",
+                &digest_type.name,
+                recipients.len()
+            );
+            for recipient in recipients {
+                if let Err(err) = self.send_digest(&digest_type, recipient.clone()).await {
+                    error!(
+                        "{:?} digest to {:?} failed: {:?}",
+                        &digest_type.name, &recipient, err
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{render_items_html, render_items_text};
+    use crate::repository::digest::DigestItemEntity;
+
+    fn item(title: &str, body: &str) -> DigestItemEntity {
+        DigestItemEntity {
+            id: 0,
+            title: title.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn render_items_test() {
+        let items = vec![
+            item("Aminah commented", "Nice <b>work</b>\nSee you soon"),
+            item("Build passed", ""),
+        ];
+
+        assert_eq!(
+            render_items_text(&items),
+            "- Aminah commented\n  Nice <b>work</b>\n  See you soon\n- Build passed"
+        );
+        assert_eq!(
+            render_items_html(&items),
+            "<ul><li><strong>Aminah commented</strong><br>Nice &lt;b&gt;work&lt;/b&gt;<br>See you soon</li><li><strong>Build passed</strong></li></ul>"
+        );
+    }
+}
@@ -1,26 +1,245 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
-use lettre::message::Mailbox;
+use futures::stream::{self, BoxStream, StreamExt};
+use lettre::message::{
+    header::{HeaderName, HeaderValue},
+    Mailbox, MultiPart, SinglePart,
+};
+#[cfg(not(test))]
+use lettre::AsyncTransport;
 use lettre::{
-    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
-    AsyncTransport, Message, Tokio1Executor,
+    address::Envelope, transport::smtp::authentication::Credentials, Address, AsyncSmtpTransport,
+    Message, Tokio1Executor,
 };
 use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
 use mockall::automock;
+use sqlx::types::time::{OffsetDateTime, Time};
+use time::Duration;
+use time_tz::Tz;
+use tracing::log::{error, info};
+use uuid::Uuid;
 
-use crate::config::AppConfig;
+use crate::{
+    config::AppConfig,
+    repository::{
+        delivery::{
+            DeliveryEntity, DeliveryStatus, DynDeliveryRepositoryTrait, MessageClass, NewDelivery,
+        },
+        digest::DigestItemEntity,
+        group::FooterTemplate,
+        identity::{DynSenderIdentityRepositoryTrait, SenderIdentityEntity},
+        subcriber::DynSubscriberRepositoryTrait,
+        suppression::DynSuppressionRepositoryTrait,
+        template::{DynTemplateRepositoryTrait, TemplateEntity},
+        unsubscription::DynUnsubscriptionRepositoryTrait,
+    },
+    service::{
+        blast::{BlastProgress, BlastRun, BlastTracker},
+        digest::{render_items_html, render_items_text, DIGEST_ITEMS_PLACEHOLDER},
+        dkim::DynDkimSigner,
+        footer::DynFooter,
+        locale::{fallback_chain, normalize_locale},
+        markdown::DynMarkdownRenderer,
+        merge::{merge_fields, merge_html, merge_text, merge_with_block},
+        outbox::{MessagePriority, Outbox},
+        rate_limit::RateLimiter,
+        schedule::{next_local_time, parse_time_of_day, parse_timezone, QuietHours},
+        tracker::DynTracker,
+        validation::DynEmailValidator,
+        verp::{DynVerp, Verp},
+    },
+};
 
 #[automock]
 #[async_trait]
 pub trait EmailServiceTrait {
-    async fn send_email(&self, address: String, title: String, body: String) -> ServiceResult<()>;
+    async fn send_email(
+        &self,
+        recipients: EmailRecipients,
+        content: EmailContent,
+    ) -> ServiceResult<String>;
     async fn blast_email(
         &self,
         addresses: Vec<String>,
-        title: String,
-        body: String,
+        content: EmailContent,
+        campaign_id: Option<i64>,
+    ) -> ServiceResult<()>;
+    async fn preview_email(
+        &self,
+        recipient: String,
+        content: EmailContent,
+    ) -> ServiceResult<EmailPreview>;
+    async fn test_blast(
+        &self,
+        seeds: Vec<String>,
+        sample: String,
+        content: EmailContent,
+        subject_prefix: String,
     ) -> ServiceResult<()>;
+    async fn send_queued_deliveries(
+        &self,
+        campaign_id: i64,
+        content: EmailContent,
+    ) -> ServiceResult<()>;
+    async fn send_digest(
+        &self,
+        recipient: String,
+        template: String,
+        items: Vec<DigestItemEntity>,
+    ) -> ServiceResult<String>;
+    async fn watch_blast(&self, campaign_id: i64) -> Option<BoxStream<'static, BlastProgress>>;
+    async fn recorded_blast_progress(
+        &self,
+        campaign_id: i64,
+        recipient_count: i64,
+        finished: bool,
+        cancelled: bool,
+    ) -> ServiceResult<BlastProgress>;
+    async fn cancel_blast(&self, campaign_id: i64) -> ServiceResult<bool>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EmailContent {
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+    pub markdown_body: Option<String>,
+    pub sender: Option<String>,
+    pub identity: Option<String>,
+    pub reply_to: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub footer: Option<FooterTemplate>,
+    pub without_footer: bool,
+    pub template: Option<String>,
+    pub locale: Option<String>,
+    pub local_send_time: Option<String>,
+    pub message_class: MessageClass,
+    pub priority: Option<MessagePriority>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EmailRecipients {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+}
+
+/// A message rendered for one recipient the way it would be sent to them.
+#[derive(Clone, Debug)]
+pub struct EmailPreview {
+    pub subject: String,
+    pub raw_message: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+/// Headers callers may set themselves, everything else is owned by this
+/// service.
+const ALLOWED_HEADERS: [&str; 3] = ["X-Entity-Ref-ID", "In-Reply-To", "References"];
+
+/// Validated recipients of a single message.
+struct MessageRecipients {
+    to: Vec<Mailbox>,
+    cc: Vec<Mailbox>,
+    bcc: Vec<Mailbox>,
+}
+
+impl MessageRecipients {
+    fn single(recipient: Mailbox) -> Self {
+        Self {
+            to: vec![recipient],
+            cc: Vec::new(),
+            bcc: Vec::new(),
+        }
+    }
+
+    fn addresses(&self) -> Vec<Address> {
+        self.to
+            .iter()
+            .chain(&self.cc)
+            .chain(&self.bcc)
+            .map(|mailbox| mailbox.email.clone())
+            .collect()
+    }
+}
+
+/// Delivery a message is built for, recorded before the message exists so
+/// that its links can be signed with the delivery id.
+struct PendingDelivery {
+    id: i64,
+    message_id: String,
+    recipient: String,
+    verp_token: Option<String>,
+    tracked: bool,
+    message_class: MessageClass,
+}
+
+impl PendingDelivery {
+    fn from_entity(delivery: DeliveryEntity) -> Self {
+        Self {
+            id: delivery.id,
+            message_id: delivery.message_id,
+            recipient: delivery.recipient,
+            verp_token: delivery.verp_token,
+            tracked: delivery.campaign_id.is_some(),
+            message_class: MessageClass::parse(&delivery.message_class).unwrap_or_default(),
+        }
+    }
+}
+
+/// Most marketing emails a subscriber may get within a rolling window.
+struct FrequencyCap {
+    window: Duration,
+    limit: i64,
+}
+
+/// How the messages of one class are sent: the identity they go out as when
+/// the sender did not pick one, and the rate limiter they queue behind.
+struct ClassPolicy {
+    identity: Option<String>,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl ClassPolicy {
+    fn new(identity: Option<String>, rate_limit_per_second: Option<u32>) -> Self {
+        Self {
+            identity,
+            rate_limiter: rate_limit_per_second.map(RateLimiter::per_second),
+        }
+    }
+}
+
+/// Identity a message is sent as, together with the credentials used to
+/// relay it.
+#[derive(Clone)]
+struct Sender {
+    from: Mailbox,
+    reply_to: Option<Mailbox>,
+    creds: Credentials,
+}
+
+/// Message of a blast, shared by the recipients it is sent to.
+struct BlastMessage<'a> {
+    sender: &'a Sender,
+    content: &'a EmailContent,
+    variants: &'a [TemplateEntity],
+    attributes: &'a HashMap<String, serde_json::Value>,
+    campaign_id: Option<i64>,
+    now: OffsetDateTime,
+    local_send_time: Option<Time>,
+}
+
+/// What became of one recipient of a blast.
+#[derive(Clone, Copy, PartialEq)]
+enum BlastOutcome {
+    Sent,
+    Queued,
+    Failed,
+    Cancelled,
 }
 
 pub type DynEmailServiceTrait = Arc<dyn EmailServiceTrait + Send + Sync>;
@@ -28,10 +247,46 @@ pub type DynEmailServiceTrait = Arc<dyn EmailServiceTrait + Send + Sync>;
 pub struct EmailService {
     creds: Credentials,
     from: Mailbox,
+    validator: DynEmailValidator,
+    delivery_repository: DynDeliveryRepositoryTrait,
+    suppression_repository: DynSuppressionRepositoryTrait,
+    identity_repository: DynSenderIdentityRepositoryTrait,
+    template_repository: DynTemplateRepositoryTrait,
+    subscriber_repository: DynSubscriberRepositoryTrait,
+    unsubscription_repository: DynUnsubscriptionRepositoryTrait,
+    default_locale: String,
+    markdown: DynMarkdownRenderer,
+    verp: Option<DynVerp>,
+    tracker: Option<DynTracker>,
+    dkim: Option<DynDkimSigner>,
+    footer: Option<DynFooter>,
+    frequency_caps: Vec<FrequencyCap>,
+    default_timezone: &'static Tz,
+    quiet_hours: Option<QuietHours>,
+    transactional: ClassPolicy,
+    marketing: ClassPolicy,
+    outbox: Outbox,
+    blast_concurrency: usize,
+    blasts: BlastTracker,
 }
 
 impl EmailService {
-    pub fn new(config: &Arc<AppConfig>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        config: &Arc<AppConfig>,
+        validator: DynEmailValidator,
+        delivery_repository: DynDeliveryRepositoryTrait,
+        suppression_repository: DynSuppressionRepositoryTrait,
+        identity_repository: DynSenderIdentityRepositoryTrait,
+        template_repository: DynTemplateRepositoryTrait,
+        subscriber_repository: DynSubscriberRepositoryTrait,
+        unsubscription_repository: DynUnsubscriptionRepositoryTrait,
+        markdown: DynMarkdownRenderer,
+        verp: Option<DynVerp>,
+        tracker: Option<DynTracker>,
+        dkim: Option<DynDkimSigner>,
+        footer: Option<DynFooter>,
+    ) -> Self {
         let email_address = &config.service_email_address;
         let email_password = &config.service_email_password;
         let creds = Credentials::new(email_address.to_owned(), email_password.to_owned());
@@ -43,66 +298,1493 @@ impl EmailService {
         .parse::<Mailbox>()
         .unwrap();
 
-        Self { creds, from }
+        let frequency_caps = [
+            (Duration::days(1), config.frequency_cap_daily),
+            (Duration::days(7), config.frequency_cap_weekly),
+        ]
+        .into_iter()
+        .filter_map(|(window, limit)| limit.map(|limit| FrequencyCap { window, limit }))
+        .collect();
+
+        let default_timezone =
+            parse_timezone(&config.default_timezone).expect("the default timezone is unknown");
+        let quiet_hours = match (&config.quiet_hours_start, &config.quiet_hours_end) {
+            (Some(start), Some(end)) => {
+                Some(QuietHours::parse(start, end).expect("the quiet hours are invalid"))
+            }
+            (None, None) => None,
+            _ => panic!("quiet hours need both a start and an end"),
+        };
+
+        Self {
+            creds,
+            from,
+            validator,
+            delivery_repository,
+            suppression_repository,
+            identity_repository,
+            template_repository,
+            subscriber_repository,
+            unsubscription_repository,
+            default_locale: config.default_locale.clone(),
+            markdown,
+            verp,
+            tracker,
+            dkim,
+            footer,
+            frequency_caps,
+            default_timezone,
+            quiet_hours,
+            transactional: ClassPolicy::new(
+                config.transactional_identity.clone(),
+                config.transactional_rate_limit_per_second,
+            ),
+            marketing: ClassPolicy::new(
+                config.marketing_identity.clone(),
+                config.marketing_rate_limit_per_second,
+            ),
+            outbox: Outbox::new(config.outbox_workers, config.low_priority_share_percent),
+            blast_concurrency: config.outbox_workers.max(1),
+            blasts: BlastTracker::default(),
+        }
+    }
+
+    fn policy(&self, message_class: MessageClass) -> &ClassPolicy {
+        match message_class {
+            MessageClass::Transactional => &self.transactional,
+            MessageClass::Marketing => &self.marketing,
+        }
+    }
+
+    /// Priority the message was given, or else the one of its class.
+    fn priority(content: &EmailContent) -> MessagePriority {
+        content
+            .priority
+            .unwrap_or_else(|| MessagePriority::of_class(content.message_class))
+    }
+
+    /// Only transactional messages, which nobody can opt out of, may go out
+    /// without the footer and its unsubscribe link.
+    fn validate_class(content: &EmailContent) -> ServiceResult<()> {
+        if content.without_footer && content.message_class == MessageClass::Marketing {
+            return Err(ServiceError::BadRequest(
+                "The footer can only be left out of transactional emails".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn recipient_mailbox(&self, address: &str) -> ServiceResult<Mailbox> {
+        self.validator
+            .validate(address)?
+            .parse::<Mailbox>()
+            .map_err(|_| ServiceError::BadRequest("Email address is invalid".to_string()))
+    }
+
+    fn recipient_mailboxes(&self, addresses: &[String]) -> ServiceResult<Vec<Mailbox>> {
+        addresses
+            .iter()
+            .map(|address| self.recipient_mailbox(address))
+            .collect()
+    }
+
+    fn reply_to_mailbox(reply_to: &str) -> ServiceResult<Mailbox> {
+        reply_to
+            .parse::<Mailbox>()
+            .map_err(|_| ServiceError::BadRequest("Reply-To address is invalid".to_string()))
+    }
+
+    fn custom_headers(headers: &[(String, String)]) -> ServiceResult<Vec<HeaderValue>> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let name = ALLOWED_HEADERS
+                    .into_iter()
+                    .find(|allowed| allowed.eq_ignore_ascii_case(name.trim()))
+                    .ok_or_else(|| {
+                        error!("header {:?} is not allowed", name);
+                        ServiceError::BadRequest(format!("Header {} is not allowed", name))
+                    })?;
+                if value.contains(['\r', '\n']) {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Header {} has an invalid value",
+                        name
+                    )));
+                }
+
+                Ok(HeaderValue::new(
+                    HeaderName::new_from_ascii_str(name),
+                    value.trim().to_string(),
+                ))
+            })
+            .collect()
+    }
+
+    fn validate_locale(content: &EmailContent) -> ServiceResult<()> {
+        match &content.locale {
+            Some(locale) if normalize_locale(locale).is_none() => {
+                error!("locale {:?} is invalid", locale);
+                Err(ServiceError::BadRequest("Locale is invalid".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn local_send_time(content: &EmailContent) -> ServiceResult<Option<Time>> {
+        match &content.local_send_time {
+            Some(local_send_time) => {
+                parse_time_of_day(local_send_time).map(Some).ok_or_else(|| {
+                    error!("local send time {:?} is invalid", local_send_time);
+                    ServiceError::BadRequest("Local send time is invalid".to_string())
+                })
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Replaces a Markdown body with the HTML and plain text rendered from it.
+    fn render_markdown(&self, content: EmailContent) -> ServiceResult<EmailContent> {
+        let markdown_body = match &content.markdown_body {
+            Some(markdown_body) => markdown_body,
+            None => return Ok(content),
+        };
+        if content.html_body.is_some() {
+            return Err(ServiceError::BadRequest(
+                "A Markdown body cannot be combined with an HTML body".to_string(),
+            ));
+        }
+
+        let rendered = self.markdown.render(&content.subject, markdown_body);
+        Ok(EmailContent {
+            text_body: rendered.text,
+            html_body: Some(rendered.html),
+            markdown_body: None,
+            ..content
+        })
+    }
+
+    /// Variants of the template the content is rendered from, empty when the
+    /// content carries its own subject and bodies.
+    async fn template_variants(
+        &self,
+        content: &EmailContent,
+    ) -> ServiceResult<Vec<TemplateEntity>> {
+        let name = match &content.template {
+            Some(name) => name,
+            None => return Ok(Vec::new()),
+        };
+
+        let variants = self
+            .template_repository
+            .list_template_variants(name)
+            .await?;
+        if variants.is_empty() {
+            error!("template {:?} does not exist", name);
+            return Err(ServiceError::ObjectConflict(
+                "Template does not exist".to_string(),
+            ));
+        }
+
+        Ok(variants)
+    }
+
+    /// Content of the variant found first along the fallback chain of the
+    /// explicit locale, or else the recipient's stored one.
+    fn localize(
+        &self,
+        content: &EmailContent,
+        variants: &[TemplateEntity],
+        stored_locale: Option<&str>,
+    ) -> ServiceResult<EmailContent> {
+        if variants.is_empty() {
+            return Ok(content.clone());
+        }
+
+        let locale = content
+            .locale
+            .as_deref()
+            .or(stored_locale)
+            .unwrap_or_default();
+        let variant = fallback_chain(locale, &self.default_locale)
+            .iter()
+            .find_map(|fallback| variants.iter().find(|variant| &variant.locale == fallback))
+            .ok_or_else(|| {
+                error!("template has no variant for locale {:?}", locale);
+                ServiceError::ObjectConflict("Template has no variant for the locale".to_string())
+            })?;
+
+        Ok(EmailContent {
+            subject: variant.subject.clone(),
+            text_body: variant.text_body.clone(),
+            html_body: Some(variant.html_body.clone()).filter(|body| !body.is_empty()),
+            ..content.clone()
+        })
+    }
+
+    fn stored_locale(attributes: Option<&serde_json::Value>) -> Option<&str> {
+        attributes
+            .and_then(|attributes| attributes.get("locale"))
+            .and_then(|locale| locale.as_str())
+    }
+
+    /// Content as one recipient reads it, in their locale and with their
+    /// merge fields filled in from the stored subscriber attributes.
+    fn personalize(
+        &self,
+        content: &EmailContent,
+        variants: &[TemplateEntity],
+        email: &str,
+        attributes: Option<&serde_json::Value>,
+    ) -> ServiceResult<EmailContent> {
+        let content = self.localize(content, variants, Self::stored_locale(attributes))?;
+        let fields = merge_fields(email, attributes);
+
+        Ok(EmailContent {
+            subject: merge_text(&content.subject, &fields),
+            text_body: merge_text(&content.text_body, &fields),
+            html_body: content
+                .html_body
+                .as_deref()
+                .map(|html_body| merge_html(html_body, &fields)),
+            ..content
+        })
+    }
+
+    /// Stored timezone of a recipient, or the default one when they have
+    /// none or it is unknown.
+    fn recipient_timezone(&self, attributes: Option<&serde_json::Value>) -> &'static Tz {
+        attributes
+            .and_then(|attributes| attributes.get("timezone"))
+            .and_then(|timezone| timezone.as_str())
+            .and_then(parse_timezone)
+            .unwrap_or(self.default_timezone)
+    }
+
+    /// Earliest an email may reach a recipient in `tz`: their next local send
+    /// time if there is one, held back past the quiet hours unless the email
+    /// is transactional.
+    fn send_after(
+        &self,
+        message_class: MessageClass,
+        now: OffsetDateTime,
+        local_send_time: Option<Time>,
+        tz: &Tz,
+    ) -> OffsetDateTime {
+        let at = match local_send_time {
+            Some(local_send_time) => next_local_time(now, local_send_time, tz),
+            None => now,
+        };
+
+        match (&self.quiet_hours, message_class) {
+            (Some(quiet_hours), MessageClass::Marketing) => quiet_hours.defer(at, tz),
+            _ => at,
+        }
+    }
+
+    /// Recipients who opted out of marketing email by unsubscribing, none
+    /// for transactional email.
+    async fn opted_out_recipients(
+        &self,
+        message_class: MessageClass,
+        addresses: &[String],
+    ) -> ServiceResult<HashSet<String>> {
+        if message_class == MessageClass::Transactional {
+            return Ok(HashSet::new());
+        }
+
+        Ok(self
+            .unsubscription_repository
+            .list_opted_out(addresses)
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    /// Refuses a single message that its class keeps from one of its
+    /// recipients. Unlike a blast it cannot be queued for later, so quiet
+    /// hours refuse it as well.
+    async fn ensure_class_allows(
+        &self,
+        message_class: MessageClass,
+        recipients: &MessageRecipients,
+        attributes: &HashMap<String, serde_json::Value>,
+    ) -> ServiceResult<()> {
+        let addresses = recipients
+            .addresses()
+            .iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<String>>();
+        if !self
+            .opted_out_recipients(message_class, &addresses)
+            .await?
+            .is_empty()
+        {
+            error!(
+                "a recipient of {:?} opted out of marketing email",
+                &addresses
+            );
+            return Err(ServiceError::BadRequest(
+                "Recipient opted out of marketing email".to_string(),
+            ));
+        }
+        if !self
+            .capped_recipients(message_class, &addresses, None)
+            .await?
+            .is_empty()
+        {
+            error!("a recipient of {:?} reached a frequency cap", &addresses);
+            return Err(ServiceError::BadRequest(
+                "Recipient reached the marketing frequency cap".to_string(),
+            ));
+        }
+        let now = OffsetDateTime::now_utc();
+        for address in &addresses {
+            let tz = self.recipient_timezone(attributes.get(address));
+            if self.send_after(message_class, now, None, tz) > now {
+                error!("recipient {:?} is within their quiet hours", address);
+                return Err(ServiceError::BadRequest(
+                    "Marketing email cannot be sent during quiet hours".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn ensure_not_suppressed(&self, recipient: &Mailbox) -> ServiceResult<()> {
+        let email = recipient.email.to_string();
+        if let Some(suppression) = self.suppression_repository.get_suppression(&email).await? {
+            error!(
+                "recipient {:?} is suppressed after {}",
+                &email, &suppression.reason
+            );
+            return Err(ServiceError::BadRequest(
+                "Recipient address is suppressed".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn generate_message_id(&self) -> String {
+        format!("<{}@{}>", Uuid::new_v4(), self.from.email.domain())
+    }
+
+    fn sender_mailbox(&self, sender: Option<&str>) -> ServiceResult<Mailbox> {
+        match sender {
+            Some(sender) => sender
+                .parse::<Mailbox>()
+                .map_err(|_| ServiceError::BadRequest("Sender address is invalid".to_string())),
+            None => Ok(self.from.clone()),
+        }
+    }
+
+    fn identity_sender(&self, identity: SenderIdentityEntity) -> ServiceResult<Sender> {
+        let address = identity.address.parse::<Address>().map_err(|_| {
+            ServiceError::InternalServerErrorWithContext(
+                "Sender identity address is invalid".to_string(),
+            )
+        })?;
+        let reply_to = identity
+            .reply_to
+            .map(|reply_to| reply_to.parse::<Mailbox>())
+            .transpose()
+            .map_err(|_| {
+                ServiceError::InternalServerErrorWithContext(
+                    "Sender identity reply-to is invalid".to_string(),
+                )
+            })?;
+        let creds = match (identity.smtp_username, identity.smtp_password) {
+            (Some(username), Some(password)) => Credentials::new(username, password),
+            _ => self.creds.clone(),
+        };
+
+        Ok(Sender {
+            from: Mailbox::new(
+                Some(identity.display_name).filter(|name| !name.is_empty()),
+                address,
+            ),
+            reply_to,
+            creds,
+        })
+    }
+
+    /// A named identity wins over a raw sender address, which in turn wins
+    /// over the identity of the message class and then the service's own
+    /// address.
+    async fn resolve_sender(&self, content: &EmailContent) -> ServiceResult<Sender> {
+        let identity = match &content.sender {
+            Some(_) => content.identity.as_ref(),
+            None => content
+                .identity
+                .as_ref()
+                .or(self.policy(content.message_class).identity.as_ref()),
+        };
+        if let Some(name) = identity {
+            return match self.identity_repository.get_identity(name).await? {
+                Some(identity) => self.identity_sender(identity),
+                None => {
+                    error!("sender identity {:?} does not exist", name);
+                    Err(ServiceError::ObjectConflict(
+                        "Sender identity does not exist".to_string(),
+                    ))
+                }
+            };
+        }
+
+        Ok(Sender {
+            from: self.sender_mailbox(content.sender.as_deref())?,
+            reply_to: None,
+            creds: self.creds.clone(),
+        })
+    }
+
+    /// Envelope addressed to the recipient of the delivery alone. With VERP
+    /// the return path is used instead of the `From` address, so bounces land
+    /// on an address that identifies this very delivery.
+    fn delivery_envelope(
+        &self,
+        sender: &Sender,
+        recipient: &str,
+        verp_token: Option<&str>,
+    ) -> ServiceResult<Envelope> {
+        let return_path = match (&self.verp, verp_token) {
+            (Some(verp), Some(verp_token)) => verp.encode(verp_token).parse::<Address>().ok(),
+            _ => Some(sender.from.email.clone()),
+        };
+
+        return_path
+            .zip(recipient.parse::<Address>().ok())
+            .and_then(|(return_path, recipient)| {
+                Envelope::new(Some(return_path), vec![recipient]).ok()
+            })
+            .ok_or_else(|| {
+                ServiceError::InternalServerErrorWithContext(
+                    "Building email envelope failed".to_string(),
+                )
+            })
+    }
+
+    fn build_message(
+        &self,
+        sender: &Sender,
+        recipients: &MessageRecipients,
+        content: &EmailContent,
+        delivery: &PendingDelivery,
+        unsubscribe_url: Option<&str>,
+    ) -> ServiceResult<Message> {
+        let mut builder = Message::builder()
+            .message_id(Some(delivery.message_id.clone()))
+            .from(sender.from.clone())
+            .envelope(self.delivery_envelope(
+                sender,
+                &delivery.recipient,
+                delivery.verp_token.as_deref(),
+            )?);
+        let reply_to = match &content.reply_to {
+            Some(reply_to) => Some(Self::reply_to_mailbox(reply_to)?),
+            None => sender.reply_to.clone(),
+        };
+        if let Some(reply_to) = reply_to {
+            builder = builder.reply_to(reply_to);
+        }
+        for to in &recipients.to {
+            builder = builder.to(to.clone());
+        }
+        for cc in &recipients.cc {
+            builder = builder.cc(cc.clone());
+        }
+        for bcc in &recipients.bcc {
+            builder = builder.bcc(bcc.clone());
+        }
+        for header in Self::custom_headers(&content.headers)? {
+            builder = builder.raw_header(header);
+        }
+        if let Some(unsubscribe_url) = unsubscribe_url {
+            builder = builder
+                .raw_header(HeaderValue::new(
+                    HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                    format!("<{}>", unsubscribe_url),
+                ))
+                .raw_header(HeaderValue::new(
+                    HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                    "List-Unsubscribe=One-Click".to_string(),
+                ));
+        }
+        let builder = builder.subject(&content.subject);
+
+        let mut email = match &content.html_body {
+            Some(html_body) => builder.multipart(MultiPart::alternative_plain_html(
+                content.text_body.clone(),
+                html_body.clone(),
+            )),
+            // A single part keeps the MIME-Version header, without it clients
+            // may not honour the charset of non-ASCII bodies.
+            None => builder.singlepart(SinglePart::plain(content.text_body.clone())),
+        }
+        .map_err(|_| {
+            ServiceError::InternalServerErrorWithContext("Building email failed".to_string())
+        })?;
+
+        if let Some(dkim) = &self.dkim {
+            dkim.sign(&mut email);
+        }
+
+        Ok(email)
+    }
+
+    /// Instruments, foots and builds the message of a delivery, everything
+    /// short of handing it to the transport. Returns the final content along
+    /// with the message.
+    fn render_message(
+        &self,
+        sender: &Sender,
+        recipients: &MessageRecipients,
+        content: &EmailContent,
+        delivery: &PendingDelivery,
+    ) -> ServiceResult<(EmailContent, Message)> {
+        // Tracking links are signed with the delivery id, so the content can
+        // only be instrumented once the delivery has been recorded.
+        let mut content = content.clone();
+        if let (Some(tracker), Some(html_body), true) =
+            (&self.tracker, &content.html_body, delivery.tracked)
+        {
+            content.html_body = Some(tracker.instrument_html(html_body, delivery.id));
+        }
+
+        // The footer goes in after instrumentation so that its unsubscribe link
+        // is never turned into a click redirect.
+        let unsubscribe_url = match (&self.footer, &self.tracker) {
+            (Some(_), Some(tracker)) if !content.without_footer => {
+                Some(tracker.unsubscribe_url(delivery.id))
+            }
+            _ => None,
+        };
+        if let (Some(footer), Some(unsubscribe_url)) = (&self.footer, &unsubscribe_url) {
+            content.text_body =
+                footer.append_text(&content.text_body, content.footer.as_ref(), unsubscribe_url);
+            content.html_body = content.html_body.as_ref().map(|html_body| {
+                footer.append_html(html_body, content.footer.as_ref(), unsubscribe_url)
+            });
+        }
+
+        let email = self.build_message(
+            sender,
+            recipients,
+            &content,
+            delivery,
+            unsubscribe_url.as_deref(),
+        )?;
+
+        Ok((content, email))
+    }
+
+    /// Recipients who already got as many marketing campaign emails as one
+    /// of the caps allows, none for transactional email. Deliveries of the
+    /// campaign being sent are left out of the count.
+    async fn capped_recipients(
+        &self,
+        message_class: MessageClass,
+        addresses: &[String],
+        campaign_id: Option<i64>,
+    ) -> ServiceResult<HashSet<String>> {
+        let mut capped = HashSet::new();
+        if message_class == MessageClass::Transactional {
+            return Ok(capped);
+        }
+        for cap in &self.frequency_caps {
+            let counts = self
+                .delivery_repository
+                .count_campaign_deliveries_since(
+                    addresses,
+                    OffsetDateTime::now_utc() - cap.window,
+                    campaign_id,
+                )
+                .await?;
+            capped.extend(
+                addresses
+                    .iter()
+                    .filter(|address| {
+                        counts
+                            .get(&address.to_lowercase())
+                            .is_some_and(|count| *count >= cap.limit)
+                    })
+                    .cloned(),
+            );
+        }
+
+        Ok(capped)
+    }
+
+    /// Logs a blast recipient nothing is sent to, so that the deliveries of
+    /// the blast account for every one of its recipients.
+    async fn record_unsent(
+        &self,
+        address: &str,
+        content: &EmailContent,
+        campaign_id: Option<i64>,
+        status: DeliveryStatus,
+        reason: &str,
+    ) -> ServiceResult<()> {
+        let delivery = self
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: self.generate_message_id(),
+                recipient: address.to_string(),
+                subject: content.subject.clone(),
+                campaign_id,
+                verp_token: None,
+                send_after: None,
+                message_class: content.message_class,
+            })
+            .await?;
+        self.delivery_repository
+            .update_delivery_status(delivery.id, status, reason)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Records a delivery to one recipient, to be sent right away or once
+    /// `send_after` has passed.
+    async fn record_delivery(
+        &self,
+        recipient: &Address,
+        content: &EmailContent,
+        campaign_id: Option<i64>,
+        send_after: Option<OffsetDateTime>,
+    ) -> ServiceResult<PendingDelivery> {
+        let delivery = self
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: self.generate_message_id(),
+                recipient: recipient.to_string(),
+                subject: content.subject.clone(),
+                campaign_id,
+                verp_token: self.verp.as_ref().map(|_| Verp::generate_token()),
+                send_after,
+                message_class: content.message_class,
+            })
+            .await?;
+
+        Ok(PendingDelivery::from_entity(delivery))
+    }
+
+    async fn send_delivery(
+        &self,
+        sender: &Sender,
+        recipients: &MessageRecipients,
+        content: &EmailContent,
+        delivery: &PendingDelivery,
+    ) -> ServiceResult<String> {
+        let email = match self.render_message(sender, recipients, content, delivery) {
+            Ok((_, email)) => email,
+            Err(err) => {
+                self.delivery_repository
+                    .update_delivery_status(
+                        delivery.id,
+                        DeliveryStatus::Failed,
+                        "message could not be built",
+                    )
+                    .await?;
+                return Err(err);
+            }
+        };
+
+        // Each class queues behind its own limiter, so a blast never holds up
+        // a transactional email.
+        if let Some(rate_limiter) = &self.policy(delivery.message_class).rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        let worker = self.outbox.acquire(Self::priority(content)).await;
+        let sent = self.send_message_email(email, &sender.creds).await;
+        drop(worker);
+        match sent {
+            Ok(smtp_response) => {
+                self.delivery_repository
+                    .update_delivery_status(delivery.id, DeliveryStatus::Sent, &smtp_response)
+                    .await?;
+                Ok(delivery.message_id.clone())
+            }
+            Err(smtp_error) => {
+                error!(
+                    "failed to deliver {:?}: {}",
+                    &delivery.message_id, &smtp_error
+                );
+                self.delivery_repository
+                    .update_delivery_status(delivery.id, DeliveryStatus::Failed, &smtp_error)
+                    .await?;
+                Err(ServiceError::InternalServerErrorWithContext(
+                    "Sending email failed".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Sends the message to every recipient on it as a delivery of its own,
+    /// returning the message id of the first delivery that went out. Once any
+    /// copy went out the send succeeds, the copies that failed are left
+    /// recorded as failed deliveries, so an idempotent replay returns the
+    /// stored message id instead of sending the others again.
+    async fn deliver(
+        &self,
+        sender: &Sender,
+        recipients: &MessageRecipients,
+        content: &EmailContent,
+        campaign_id: Option<i64>,
+    ) -> ServiceResult<String> {
+        let mut addresses = recipients.addresses();
+        let mut seen = HashSet::new();
+        addresses.retain(|address| seen.insert(address.clone()));
+
+        let mut message_ids = Vec::new();
+        let mut failed = Vec::new();
+        for address in &addresses {
+            let delivery = self
+                .record_delivery(address, content, campaign_id, None)
+                .await?;
+            match self
+                .send_delivery(sender, recipients, content, &delivery)
+                .await
+            {
+                Ok(message_id) => message_ids.push(message_id),
+                Err(_) => failed.push(address.to_string()),
+            }
+        }
+
+        match message_ids.into_iter().next() {
+            Some(message_id) => {
+                if !failed.is_empty() {
+                    error!(
+                        "{:?} went out to {} of {} recipients, failed for {:?}",
+                        &message_id,
+                        addresses.len() - failed.len(),
+                        addresses.len(),
+                        &failed
+                    );
+                }
+                Ok(message_id)
+            }
+            None => Err(ServiceError::InternalServerErrorWithContext(
+                "Sending email failed".to_string(),
+            )),
+        }
+    }
+
+    /// Gives up on a blast recipient before their delivery was recorded,
+    /// recording it as failed.
+    async fn fail_unrecorded(
+        &self,
+        blast: &BlastRun,
+        message: &BlastMessage<'_>,
+        address: &str,
+        error: String,
+    ) -> BlastOutcome {
+        if let Err(err) = self
+            .record_unsent(
+                address,
+                message.content,
+                message.campaign_id,
+                DeliveryStatus::Failed,
+                &error,
+            )
+            .await
+        {
+            error!("failed to record the delivery to {:?}: {:?}", address, err);
+        }
+        blast.failed(error);
+        BlastOutcome::Failed
+    }
+
+    /// Sends a blast message to one of its recipients, or queues it for their
+    /// local send time, and reports the outcome to the blast's watchers.
+    async fn blast_recipient(
+        &self,
+        blast: &BlastRun,
+        message: &BlastMessage<'_>,
+        address: String,
+    ) -> BlastOutcome {
+        if blast.is_cancelled() {
+            return BlastOutcome::Cancelled;
+        }
+        let recipient = match self.recipient_mailbox(&address) {
+            Ok(recipient) => recipient,
+            Err(_) => {
+                error!("skipping invalid blast recipient {:?}", &address);
+                return self
+                    .fail_unrecorded(
+                        blast,
+                        message,
+                        &address,
+                        format!("invalid recipient {:?}", &address),
+                    )
+                    .await;
+            }
+        };
+        let recipient_attributes = message.attributes.get(&address);
+        let personalized_content = match self.personalize(
+            message.content,
+            message.variants,
+            &address,
+            recipient_attributes,
+        ) {
+            Ok(personalized_content) => personalized_content,
+            Err(_) => {
+                error!("no template variant for blast recipient {:?}", &address);
+                return self
+                    .fail_unrecorded(
+                        blast,
+                        message,
+                        &address,
+                        format!("no template variant for {:?}", &address),
+                    )
+                    .await;
+            }
+        };
+
+        let recipients = MessageRecipients::single(recipient);
+        let send_after = self.send_after(
+            message.content.message_class,
+            message.now,
+            message.local_send_time,
+            self.recipient_timezone(recipient_attributes),
+        );
+        if send_after > message.now {
+            // Only campaign deliveries can be queued, the worker sending
+            // them later reads their content back from the campaign.
+            if message.campaign_id.is_none() {
+                error!("blast recipient {:?} is within their quiet hours", &address);
+                return self
+                    .fail_unrecorded(
+                        blast,
+                        message,
+                        &address,
+                        format!("{:?} is within their quiet hours", &address),
+                    )
+                    .await;
+            }
+            return match self
+                .record_delivery(
+                    &recipients.to[0].email,
+                    &personalized_content,
+                    message.campaign_id,
+                    Some(send_after),
+                )
+                .await
+            {
+                // Stays queued for its watchers until it is sent.
+                Ok(_) => BlastOutcome::Queued,
+                Err(_) => {
+                    error!("failed to queue blast email to {:?}", &address);
+                    blast.failed(format!("failed to queue email to {:?}", &address));
+                    BlastOutcome::Failed
+                }
+            };
+        }
+
+        match self
+            .deliver(
+                message.sender,
+                &recipients,
+                &personalized_content,
+                message.campaign_id,
+            )
+            .await
+        {
+            Ok(_) => {
+                blast.sent();
+                BlastOutcome::Sent
+            }
+            Err(_) => {
+                error!("failed to deliver blast email to {:?}", &address);
+                blast.failed(format!("failed to deliver email to {:?}", &address));
+                BlastOutcome::Failed
+            }
+        }
     }
 
     #[cfg(not(test))]
-    async fn send_message_email(&self, email: Message) -> ServiceResult<()> {
+    async fn send_message_email(
+        &self,
+        email: Message,
+        creds: &Credentials,
+    ) -> Result<String, String> {
         let mailer: AsyncSmtpTransport<Tokio1Executor> =
             AsyncSmtpTransport::<Tokio1Executor>::starttls_relay("smtp.gmail.com")
                 .unwrap()
-                .credentials(self.creds.clone())
+                .credentials(creds.clone())
                 .build();
 
         match mailer.send(email).await {
-            Ok(_) => Ok(()),
-            Err(_e) => Err(ServiceError::InternalServerErrorWithContext(
-                "Sending email failed".to_string(),
+            Ok(response) => Ok(format!(
+                "{} {}",
+                response.code(),
+                response.message().collect::<Vec<&str>>().join(" ")
             )),
+            Err(err) => Err(err.to_string()),
         }
     }
 
     #[cfg(test)]
-    async fn send_message_email(&self, _email: Message) -> ServiceResult<()> {
+    async fn send_message_email(
+        &self,
+        _email: Message,
+        creds: &Credentials,
+    ) -> Result<String, String> {
         let mailer: AsyncSmtpTransport<Tokio1Executor> =
             AsyncSmtpTransport::<Tokio1Executor>::starttls_relay("smtp.gmail.com")
                 .unwrap()
-                .credentials(self.creds.clone())
+                .credentials(creds.clone())
                 .build();
 
-        mailer.test_connection().await.map_err(|_| {
-            ServiceError::InternalServerErrorWithContext(
-                "Can't communicate with SMTP server".to_string(),
-            )
-        })?;
-        Ok(())
+        mailer
+            .test_connection()
+            .await
+            .map_err(|_| "Can't communicate with SMTP server".to_string())?;
+        Ok("250 test connection established".to_string())
     }
 }
 
 #[async_trait]
 impl EmailServiceTrait for EmailService {
-    async fn send_email(&self, address: String, title: String, body: String) -> ServiceResult<()> {
-        let recipient = address
-            .parse::<Mailbox>()
-            .map_err(|_| ServiceError::BadRequest("Email address is invalid".to_string()))?;
+    async fn send_email(
+        &self,
+        recipients: EmailRecipients,
+        content: EmailContent,
+    ) -> ServiceResult<String> {
+        if recipients.to.is_empty() {
+            return Err(ServiceError::BadRequest(
+                "At least one recipient is required".to_string(),
+            ));
+        }
+
+        let recipients = MessageRecipients {
+            to: self.recipient_mailboxes(&recipients.to)?,
+            cc: self.recipient_mailboxes(&recipients.cc)?,
+            bcc: self.recipient_mailboxes(&recipients.bcc)?,
+        };
+        for recipient in recipients
+            .to
+            .iter()
+            .chain(&recipients.cc)
+            .chain(&recipients.bcc)
+        {
+            self.ensure_not_suppressed(recipient).await?;
+        }
+        if let Some(reply_to) = &content.reply_to {
+            Self::reply_to_mailbox(reply_to)?;
+        }
+        Self::custom_headers(&content.headers)?;
+        Self::validate_class(&content)?;
+        Self::validate_locale(&content)?;
+        let attributes = self
+            .subscriber_repository
+            .list_subscriber_attributes(
+                &recipients
+                    .addresses()
+                    .iter()
+                    .map(|address| address.to_string())
+                    .collect::<Vec<String>>(),
+            )
+            .await?;
+        self.ensure_class_allows(content.message_class, &recipients, &attributes)
+            .await?;
+        let content = self.render_markdown(content)?;
 
-        let email = Message::builder()
-            .from(self.from.clone())
-            .to(recipient)
-            .subject(&title)
-            .header(ContentType::TEXT_PLAIN)
-            .body(body)
-            .unwrap();
+        let sender = self.resolve_sender(&content).await?;
+        // Everyone on the message reads the same content, personalized for
+        // the first recipient.
+        let variants = self.template_variants(&content).await?;
+        let address = recipients.to[0].email.to_string();
+        let content = self.personalize(&content, &variants, &address, attributes.get(&address))?;
 
-        self.send_message_email(email).await
+        self.deliver(&sender, &recipients, &content, None).await
     }
 
     async fn blast_email(
         &self,
-        _addresses: Vec<String>,
-        _title: String,
-        _body: String,
+        addresses: Vec<String>,
+        content: EmailContent,
+        campaign_id: Option<i64>,
+    ) -> ServiceResult<()> {
+        Self::validate_class(&content)?;
+        Self::validate_locale(&content)?;
+        let local_send_time = Self::local_send_time(&content)?;
+        if local_send_time.is_some() && campaign_id.is_none() {
+            return Err(ServiceError::BadRequest(
+                "Only a campaign can be sent at a local send time".to_string(),
+            ));
+        }
+        let content = self.render_markdown(content)?;
+
+        let sender = self.resolve_sender(&content).await?;
+        let variants = self.template_variants(&content).await?;
+        let suppressed = self
+            .suppression_repository
+            .list_suppressed(&addresses)
+            .await?;
+        let opted_out = self
+            .opted_out_recipients(content.message_class, &addresses)
+            .await?;
+        let (skipped, addresses): (Vec<String>, Vec<String>) = addresses
+            .into_iter()
+            .partition(|address| suppressed.contains(address) || opted_out.contains(address));
+        for address in &skipped {
+            let reason = if suppressed.contains(address) {
+                "recipient is suppressed"
+            } else {
+                "recipient opted out"
+            };
+            self.record_unsent(
+                address,
+                &content,
+                campaign_id,
+                DeliveryStatus::Skipped,
+                reason,
+            )
+            .await?;
+        }
+        let capped = self
+            .capped_recipients(content.message_class, &addresses, campaign_id)
+            .await?;
+        for address in &capped {
+            self.record_unsent(
+                address,
+                &content,
+                campaign_id,
+                DeliveryStatus::Capped,
+                "frequency cap reached",
+            )
+            .await?;
+        }
+        let addresses = addresses
+            .into_iter()
+            .filter(|address| !capped.contains(address))
+            .collect::<Vec<String>>();
+        let total = addresses.len();
+        let attributes = self
+            .subscriber_repository
+            .list_subscriber_attributes(&addresses)
+            .await?;
+
+        info!(
+            "blasting {} email {:?} to {} recipients, {} suppressed, {} opted out, {} capped",
+            content.message_class.as_str(),
+            &content.subject,
+            total,
+            suppressed.len(),
+            opted_out.len(),
+            capped.len()
+        );
+        let blast = self.blasts.start(
+            campaign_id,
+            total as i64,
+            (skipped.len() + capped.len()) as i64,
+        );
+        let message = BlastMessage {
+            sender: &sender,
+            content: &content,
+            variants: &variants,
+            attributes: &attributes,
+            campaign_id,
+            now: OffsetDateTime::now_utc(),
+            local_send_time,
+        };
+        // As many recipients are sent to at once as there are outbox workers,
+        // so the blast keeps them busy while waiting its turn behind messages
+        // of a higher priority.
+        let outcomes = stream::iter(addresses)
+            .map(|address| self.blast_recipient(&blast, &message, address))
+            .buffer_unordered(self.blast_concurrency)
+            .collect::<Vec<BlastOutcome>>()
+            .await;
+        let count =
+            |outcome: BlastOutcome| outcomes.iter().filter(|other| **other == outcome).count();
+        let (failed, queued) = (count(BlastOutcome::Failed), count(BlastOutcome::Queued));
+
+        if blast.is_cancelled() {
+            // Recipients queued for their local time before the blast was
+            // cancelled are not sent either.
+            if let Some(campaign_id) = campaign_id {
+                self.delivery_repository
+                    .cancel_queued_deliveries(campaign_id)
+                    .await?;
+            }
+            info!("blast email cancelled");
+            return Ok(());
+        }
+
+        if failed > 0 {
+            return Err(ServiceError::InternalServerErrorWithContext(format!(
+                "Blasting email failed for {} of {} recipients",
+                failed, total
+            )));
+        }
+
+        info!(
+            "blast email successfully delivered, {} recipients queued for later",
+            queued
+        );
+        Ok(())
+    }
+
+    async fn preview_email(
+        &self,
+        recipient: String,
+        content: EmailContent,
+    ) -> ServiceResult<EmailPreview> {
+        let recipients = MessageRecipients::single(self.recipient_mailbox(&recipient)?);
+        if let Some(reply_to) = &content.reply_to {
+            Self::reply_to_mailbox(reply_to)?;
+        }
+        Self::custom_headers(&content.headers)?;
+        Self::validate_locale(&content)?;
+        let content = self.render_markdown(content)?;
+
+        let sender = self.resolve_sender(&content).await?;
+        let variants = self.template_variants(&content).await?;
+        let attributes = self
+            .subscriber_repository
+            .list_subscriber_attributes(std::slice::from_ref(&recipient))
+            .await?;
+        let content =
+            self.personalize(&content, &variants, &recipient, attributes.get(&recipient))?;
+
+        // Nothing is recorded for a preview, its links carry a delivery id
+        // that does not exist.
+        let (content, email) = self.render_message(
+            &sender,
+            &recipients,
+            &content,
+            &PendingDelivery {
+                id: 0,
+                message_id: self.generate_message_id(),
+                recipient: recipient.clone(),
+                verp_token: None,
+                tracked: true,
+                message_class: content.message_class,
+            },
+        )?;
+
+        info!(
+            "previewed email {:?} for {:?}",
+            &content.subject, &recipient
+        );
+        Ok(EmailPreview {
+            subject: content.subject,
+            raw_message: String::from_utf8_lossy(&email.formatted()).into_owned(),
+            text_body: content.text_body,
+            html_body: content.html_body,
+        })
+    }
+
+    async fn test_blast(
+        &self,
+        seeds: Vec<String>,
+        sample: String,
+        content: EmailContent,
+        subject_prefix: String,
     ) -> ServiceResult<()> {
-        todo!()
+        Self::validate_class(&content)?;
+        Self::validate_locale(&content)?;
+        let content = self.render_markdown(content)?;
+
+        let sender = self.resolve_sender(&content).await?;
+        let variants = self.template_variants(&content).await?;
+        let attributes = self
+            .subscriber_repository
+            .list_subscriber_attributes(std::slice::from_ref(&sample))
+            .await?;
+        // Every seed reads the message the sample subscriber would get.
+        let content = self.personalize(&content, &variants, &sample, attributes.get(&sample))?;
+        let content = EmailContent {
+            subject: format!("{}{}", subject_prefix, content.subject),
+            ..content
+        };
+        let total = seeds.len();
+
+        info!(
+            "test blasting email {:?} to {} seeds",
+            &content.subject, total
+        );
+        let mut failed = 0;
+        for seed in seeds {
+            let recipient = match self.recipient_mailbox(&seed) {
+                Ok(recipient) => recipient,
+                Err(_) => {
+                    error!("skipping invalid seed {:?}", &seed);
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            // Test sends stay out of the campaign, so they never count
+            // towards its engagement.
+            if self
+                .deliver(
+                    &sender,
+                    &MessageRecipients::single(recipient),
+                    &content,
+                    None,
+                )
+                .await
+                .is_err()
+            {
+                error!("failed to deliver test blast to {:?}", &seed);
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            return Err(ServiceError::InternalServerErrorWithContext(format!(
+                "Test blast failed for {} of {} seeds",
+                failed, total
+            )));
+        }
+
+        info!("test blast successfully delivered");
+        Ok(())
+    }
+
+    async fn send_queued_deliveries(
+        &self,
+        campaign_id: i64,
+        content: EmailContent,
+    ) -> ServiceResult<()> {
+        Self::validate_locale(&content)?;
+        let content = self.render_markdown(content)?;
+
+        let sender = self.resolve_sender(&content).await?;
+        let variants = self.template_variants(&content).await?;
+        let deliveries = self
+            .delivery_repository
+            .claim_due_deliveries(campaign_id)
+            .await?;
+        let addresses = deliveries
+            .iter()
+            .map(|delivery| delivery.recipient.clone())
+            .collect::<Vec<String>>();
+        let suppressed = self
+            .suppression_repository
+            .list_suppressed(&addresses)
+            .await?;
+        let opted_out = self
+            .opted_out_recipients(content.message_class, &addresses)
+            .await?;
+        // Other campaigns may have reached the recipient since this one was
+        // queued.
+        let capped = self
+            .capped_recipients(content.message_class, &addresses, Some(campaign_id))
+            .await?;
+        let attributes = self
+            .subscriber_repository
+            .list_subscriber_attributes(&addresses)
+            .await?;
+        let total = deliveries.len();
+
+        info!(
+            "sending {} queued deliveries of campaign {:?}",
+            total, campaign_id
+        );
+        let now = OffsetDateTime::now_utc();
+        let mut failed = 0;
+        for delivery in deliveries {
+            let address = delivery.recipient.clone();
+            let recipient_attributes = attributes.get(&address);
+            let delivery = PendingDelivery::from_entity(delivery);
+            // Quiet hours may have begun since the delivery was queued, for
+            // instance after the recipient moved to another timezone.
+            let send_after = self.send_after(
+                delivery.message_class,
+                now,
+                None,
+                self.recipient_timezone(recipient_attributes),
+            );
+            if send_after > now {
+                self.delivery_repository
+                    .defer_delivery(delivery.id, send_after)
+                    .await?;
+                continue;
+            }
+            if suppressed.contains(&address) {
+                self.delivery_repository
+                    .update_delivery_status(
+                        delivery.id,
+                        DeliveryStatus::Skipped,
+                        "recipient is suppressed",
+                    )
+                    .await?;
+                continue;
+            }
+            if opted_out.contains(&address) {
+                self.delivery_repository
+                    .update_delivery_status(
+                        delivery.id,
+                        DeliveryStatus::Skipped,
+                        "recipient opted out",
+                    )
+                    .await?;
+                continue;
+            }
+            if capped.contains(&address) {
+                self.delivery_repository
+                    .update_delivery_status(
+                        delivery.id,
+                        DeliveryStatus::Capped,
+                        "frequency cap reached",
+                    )
+                    .await?;
+                continue;
+            }
+
+            let prepared = self.recipient_mailbox(&address).and_then(|recipient| {
+                self.personalize(&content, &variants, &address, recipient_attributes)
+                    .map(|personalized_content| (recipient, personalized_content))
+            });
+            let (recipient, personalized_content) = match prepared {
+                Ok(prepared) => prepared,
+                Err(_) => {
+                    error!("skipping queued delivery to {:?}", &address);
+                    self.delivery_repository
+                        .update_delivery_status(
+                            delivery.id,
+                            DeliveryStatus::Failed,
+                            "message could not be built",
+                        )
+                        .await?;
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            if self
+                .send_delivery(
+                    &sender,
+                    &MessageRecipients::single(recipient),
+                    &personalized_content,
+                    &delivery,
+                )
+                .await
+                .is_err()
+            {
+                error!("failed to deliver queued email to {:?}", &address);
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            return Err(ServiceError::InternalServerErrorWithContext(format!(
+                "Sending queued email failed for {} of {} recipients",
+                failed, total
+            )));
+        }
+
+        info!("queued email successfully delivered");
+        Ok(())
+    }
+
+    async fn send_digest(
+        &self,
+        recipient: String,
+        template: String,
+        items: Vec<DigestItemEntity>,
+    ) -> ServiceResult<String> {
+        let recipients = MessageRecipients::single(self.recipient_mailbox(&recipient)?);
+        for recipient in &recipients.to {
+            self.ensure_not_suppressed(recipient).await?;
+        }
+        // Digests are notifications the recipient signed up for, though none
+        // as urgent as a password reset.
+        let content = EmailContent {
+            template: Some(template),
+            message_class: MessageClass::Transactional,
+            priority: Some(MessagePriority::Normal),
+            ..Default::default()
+        };
+
+        let sender = self.resolve_sender(&content).await?;
+        let variants = self.template_variants(&content).await?;
+        let attributes = self
+            .subscriber_repository
+            .list_subscriber_attributes(std::slice::from_ref(&recipient))
+            .await?;
+        let recipient_attributes = attributes.get(&recipient);
+        let content = self.localize(
+            &content,
+            &variants,
+            Self::stored_locale(recipient_attributes),
+        )?;
+        // The items are rendered here rather than merged, merging would
+        // escape the HTML list.
+        let mut fields = merge_fields(&recipient, recipient_attributes);
+        fields.insert("item_count".to_string(), items.len().to_string());
+        let items_text = render_items_text(&items);
+        let items_html = render_items_html(&items);
+        let content = EmailContent {
+            subject: merge_text(&content.subject, &fields),
+            text_body: merge_with_block(
+                &content.text_body,
+                &fields,
+                DIGEST_ITEMS_PLACEHOLDER,
+                &items_text,
+                merge_text,
+            ),
+            html_body: content.html_body.as_deref().map(|html_body| {
+                merge_with_block(
+                    html_body,
+                    &fields,
+                    DIGEST_ITEMS_PLACEHOLDER,
+                    &items_html,
+                    merge_html,
+                )
+            }),
+            ..content
+        };
+
+        info!(
+            "sending a digest of {} items to {:?}",
+            items.len(),
+            &recipient
+        );
+        self.deliver(&sender, &recipients, &content, None).await
+    }
+
+    async fn watch_blast(&self, campaign_id: i64) -> Option<BoxStream<'static, BlastProgress>> {
+        self.blasts.watch(campaign_id)
+    }
+
+    async fn recorded_blast_progress(
+        &self,
+        campaign_id: i64,
+        recipient_count: i64,
+        finished: bool,
+        cancelled: bool,
+    ) -> ServiceResult<BlastProgress> {
+        let counts = self
+            .delivery_repository
+            .count_campaign_deliveries_by_status(campaign_id)
+            .await?;
+
+        Ok(BlastProgress::recorded(
+            &counts,
+            recipient_count,
+            finished,
+            cancelled,
+        ))
+    }
+
+    async fn cancel_blast(&self, campaign_id: i64) -> ServiceResult<bool> {
+        let running = self.blasts.cancel(campaign_id);
+        let cancelled = self
+            .delivery_repository
+            .cancel_queued_deliveries(campaign_id)
+            .await?;
+
+        info!(
+            "cancelled blast of campaign {:?}, {} queued deliveries stopped",
+            campaign_id, cancelled
+        );
+        Ok(running || cancelled > 0)
     }
 }
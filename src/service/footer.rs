@@ -0,0 +1,174 @@
+use std::{fs, sync::Arc};
+
+use anyhow::Context;
+
+use crate::{repository::group::FooterTemplate, service::merge::escape_html};
+
+const DEFAULT_TEXT_TEMPLATE: &str = "--\n{{postal_address}}\nUnsubscribe: {{unsubscribe_url}}";
+const DEFAULT_HTML_TEMPLATE: &str = "<p style=\"color:#666666;font-size:12px\">\
+    {{postal_address}}<br /><a href=\"{{unsubscribe_url}}\">Unsubscribe</a></p>";
+
+/// Compliance footer appended to outgoing messages, carrying the
+/// organisation's postal address and the recipient's unsubscribe link.
+pub struct Footer {
+    template: FooterTemplate,
+    postal_address: String,
+}
+
+pub type DynFooter = Arc<Footer>;
+
+impl Footer {
+    /// Empty templates fall back to the built-in ones.
+    pub fn new(postal_address: &str, template: FooterTemplate) -> Self {
+        Self {
+            template: FooterTemplate {
+                text: Some(template.text)
+                    .filter(|text| !text.trim().is_empty())
+                    .unwrap_or_else(|| DEFAULT_TEXT_TEMPLATE.to_string()),
+                html: Some(template.html)
+                    .filter(|html| !html.trim().is_empty())
+                    .unwrap_or_else(|| DEFAULT_HTML_TEMPLATE.to_string()),
+            },
+            postal_address: postal_address.trim().to_string(),
+        }
+    }
+
+    pub fn from_files(
+        postal_address: &str,
+        text_template_path: Option<&str>,
+        html_template_path: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let read = |path: Option<&str>| -> anyhow::Result<String> {
+            match path {
+                Some(path) => fs::read_to_string(path)
+                    .with_context(|| format!("could not read the footer template {:?}", path)),
+                None => Ok(String::new()),
+            }
+        };
+
+        Ok(Self::new(
+            postal_address,
+            FooterTemplate {
+                text: read(text_template_path)?,
+                html: read(html_template_path)?,
+            },
+        ))
+    }
+
+    pub fn render_text(
+        &self,
+        group_template: Option<&FooterTemplate>,
+        unsubscribe_url: &str,
+    ) -> String {
+        let template = group_template
+            .map(|template| template.text.as_str())
+            .filter(|text| !text.trim().is_empty())
+            .unwrap_or(&self.template.text);
+
+        template
+            .replace("{{postal_address}}", &self.postal_address)
+            .replace("{{unsubscribe_url}}", unsubscribe_url)
+    }
+
+    pub fn render_html(
+        &self,
+        group_template: Option<&FooterTemplate>,
+        unsubscribe_url: &str,
+    ) -> String {
+        let template = group_template
+            .map(|template| template.html.as_str())
+            .filter(|html| !html.trim().is_empty())
+            .unwrap_or(&self.template.html);
+
+        template
+            .replace(
+                "{{postal_address}}",
+                &escape_html(&self.postal_address).replace('\n', "<br />"),
+            )
+            .replace("{{unsubscribe_url}}", &escape_html(unsubscribe_url))
+    }
+
+    pub fn append_text(
+        &self,
+        text_body: &str,
+        group_template: Option<&FooterTemplate>,
+        unsubscribe_url: &str,
+    ) -> String {
+        format!(
+            "{}\n\n{}",
+            text_body.trim_end(),
+            self.render_text(group_template, unsubscribe_url)
+        )
+    }
+
+    /// Places the footer right before `</body>` when the body has one.
+    pub fn append_html(
+        &self,
+        html_body: &str,
+        group_template: Option<&FooterTemplate>,
+        unsubscribe_url: &str,
+    ) -> String {
+        let footer = self.render_html(group_template, unsubscribe_url);
+        let mut html_body = html_body.to_string();
+        match html_body.to_ascii_lowercase().rfind("</body>") {
+            Some(body_end) => html_body.insert_str(body_end, &footer),
+            None => html_body.push_str(&footer),
+        }
+
+        html_body
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use crate::repository::group::FooterTemplate;
+
+    use super::Footer;
+
+    #[test]
+    fn default_footer_test() {
+        let footer = Footer::new("1 Jalan Example\nKuala Lumpur", FooterTemplate::default());
+
+        let text = footer.append_text("Hello there\n", None, "https://t.example.com/u?a=1&b=2");
+        let html = footer.append_html(
+            "<html><BODY><p>Hello there</p></BODY></html>",
+            None,
+            "https://t.example.com/u?a=1&b=2",
+        );
+
+        assert_eq!(
+            text,
+            "Hello there\n\n--\n1 Jalan Example\nKuala Lumpur\n\
+            Unsubscribe: https://t.example.com/u?a=1&b=2"
+        );
+        assert!(html.starts_with("<html><BODY><p>Hello there</p><p "));
+        assert!(html.contains("1 Jalan Example<br />Kuala Lumpur"));
+        assert!(html.contains("href=\"https://t.example.com/u?a=1&amp;b=2\""));
+        assert!(html.ends_with("</p></BODY></html>"));
+    }
+
+    #[test]
+    fn group_footer_test() {
+        let footer = Footer::new(
+            "<Example> Sdn Bhd",
+            FooterTemplate {
+                text: "Sent by {{postal_address}}".to_string(),
+                html: String::new(),
+            },
+        );
+        let group_template = FooterTemplate {
+            text: String::new(),
+            html: "<footer>{{postal_address}} | <a href=\"{{unsubscribe_url}}\">Leave</a></footer>"
+                .to_string(),
+        };
+
+        let text = footer.render_text(Some(&group_template), "https://u");
+        let html = footer.render_html(Some(&group_template), "https://u");
+
+        assert_eq!(text, "Sent by <Example> Sdn Bhd");
+        assert_eq!(
+            html,
+            "<footer>&lt;Example&gt; Sdn Bhd | <a href=\"https://u\">Leave</a></footer>"
+        );
+    }
+}
@@ -1,14 +1,20 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use madtofan_microservice_common::{
-    email::{groups_response::Group, GroupsResponse},
-    errors::{ServiceError, ServiceResult},
-};
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
 use mockall::automock;
 use tracing::log::{error, info};
 
-use crate::repository::group::{DynGroupRepositoryTrait, GroupEntity};
+use crate::{
+    proto::email::{
+        groups_response::Group, GroupOperationResponse, GroupSeedsResponse, GroupsResponse,
+    },
+    repository::group::{DynGroupRepositoryTrait, FooterTemplate, GroupEntity},
+    service::validation::DynEmailValidator,
+};
+
+/// Seeds are a handful of reviewers, not a second audience.
+const MAX_GROUP_SEEDS: usize = 25;
 
 #[automock]
 #[async_trait]
@@ -21,17 +27,77 @@ pub trait GroupServiceTrait {
         offset: Option<i64>,
         limit: Option<i64>,
     ) -> ServiceResult<GroupsResponse>;
+    async fn copy_group(
+        &self,
+        from_group: String,
+        to_group: String,
+    ) -> ServiceResult<GroupOperationResponse>;
+    async fn move_subscriber(
+        &self,
+        email: String,
+        from_group: String,
+        to_group: String,
+    ) -> ServiceResult<GroupOperationResponse>;
+    async fn merge_groups(
+        &self,
+        into_group: String,
+        from_group: String,
+    ) -> ServiceResult<GroupOperationResponse>;
+    async fn set_group_footer(
+        &self,
+        group: String,
+        footer: Option<FooterTemplate>,
+    ) -> ServiceResult<()>;
+    async fn set_group_seeds(&self, group: String, emails: Vec<String>) -> ServiceResult<()>;
+    async fn list_group_seeds(&self, group: String) -> ServiceResult<GroupSeedsResponse>;
 }
 
 pub type DynGroupServiceTrait = Arc<dyn GroupServiceTrait + Sync + Send>;
 
 pub struct GroupService {
     repository: DynGroupRepositoryTrait,
+    validator: DynEmailValidator,
 }
 
 impl GroupService {
-    pub fn new(repository: DynGroupRepositoryTrait) -> Self {
-        Self { repository }
+    pub fn new(repository: DynGroupRepositoryTrait, validator: DynEmailValidator) -> Self {
+        Self {
+            repository,
+            validator,
+        }
+    }
+
+    async fn get_existing_group(&self, name: &str) -> ServiceResult<GroupEntity> {
+        match self.repository.get_group(name).await? {
+            Some(group) => Ok(group),
+            None => {
+                error!("group {:?} does not exist", name);
+                Err(ServiceError::ObjectConflict(String::from(
+                    "group does not exist",
+                )))
+            }
+        }
+    }
+
+    async fn get_distinct_groups(
+        &self,
+        first_name: &str,
+        second_name: &str,
+    ) -> ServiceResult<(GroupEntity, GroupEntity)> {
+        if first_name == second_name {
+            error!(
+                "group {:?} cannot be used as both source and target",
+                first_name
+            );
+            return Err(ServiceError::BadRequest(String::from(
+                "source and target groups must be different",
+            )));
+        }
+
+        let first_group = self.get_existing_group(first_name).await?;
+        let second_group = self.get_existing_group(second_name).await?;
+
+        Ok((first_group, second_group))
     }
 }
 
@@ -93,4 +159,110 @@ impl GroupServiceTrait for GroupService {
             count,
         })
     }
+
+    async fn copy_group(
+        &self,
+        from_group: String,
+        to_group: String,
+    ) -> ServiceResult<GroupOperationResponse> {
+        let (from, to) = self.get_distinct_groups(&from_group, &to_group).await?;
+
+        info!(
+            "copying members of group {:?} into {:?}",
+            &from_group, &to_group
+        );
+        let summary = self.repository.copy_group_members(&from, &to).await?;
+
+        info!("group members successfully copied");
+
+        Ok(summary.into_group_operation_response())
+    }
+
+    async fn move_subscriber(
+        &self,
+        email: String,
+        from_group: String,
+        to_group: String,
+    ) -> ServiceResult<GroupOperationResponse> {
+        let (from, to) = self.get_distinct_groups(&from_group, &to_group).await?;
+
+        info!(
+            "moving subscriber from group {:?} into {:?}",
+            &from_group, &to_group
+        );
+        let summary = self.repository.move_subscriber(&email, &from, &to).await?;
+
+        if summary.removed == 0 {
+            error!("subscriber is not a member of group {:?}", &from_group);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "subscriber is not a member of the source group",
+            )));
+        }
+
+        info!("subscriber successfully moved");
+
+        Ok(summary.into_group_operation_response())
+    }
+
+    async fn merge_groups(
+        &self,
+        into_group: String,
+        from_group: String,
+    ) -> ServiceResult<GroupOperationResponse> {
+        let (into, from) = self.get_distinct_groups(&into_group, &from_group).await?;
+
+        info!("merging group {:?} into {:?}", &from_group, &into_group);
+        let summary = self.repository.merge_groups(&into, &from).await?;
+
+        info!("groups successfully merged");
+
+        Ok(summary.into_group_operation_response())
+    }
+
+    async fn set_group_footer(
+        &self,
+        group: String,
+        footer: Option<FooterTemplate>,
+    ) -> ServiceResult<()> {
+        let group_entity = self.get_existing_group(&group).await?;
+
+        info!("setting the footer of group {:?}", &group);
+        self.repository
+            .set_group_footer(&group_entity, footer)
+            .await?;
+
+        info!("group footer successfully set");
+
+        Ok(())
+    }
+
+    async fn set_group_seeds(&self, group: String, emails: Vec<String>) -> ServiceResult<()> {
+        if emails.len() > MAX_GROUP_SEEDS {
+            return Err(ServiceError::BadRequest(format!(
+                "a group can have at most {} seeds",
+                MAX_GROUP_SEEDS
+            )));
+        }
+        let emails = emails
+            .iter()
+            .map(|email| self.validator.validate(email))
+            .collect::<Result<Vec<String>, _>>()?;
+        let group_entity = self.get_existing_group(&group).await?;
+
+        info!("setting {} seeds of group {:?}", emails.len(), &group);
+        self.repository
+            .set_group_seeds(&group_entity, &emails)
+            .await?;
+
+        info!("group seeds successfully set");
+
+        Ok(())
+    }
+
+    async fn list_group_seeds(&self, group: String) -> ServiceResult<GroupSeedsResponse> {
+        let group_entity = self.get_existing_group(&group).await?;
+        let emails = self.repository.list_group_seeds(group_entity.id).await?;
+
+        Ok(GroupSeedsResponse { group, emails })
+    }
 }
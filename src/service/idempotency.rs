@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use mockall::automock;
+use sqlx::types::time::OffsetDateTime;
+use time::Duration;
+use tracing::log::{error, info};
+
+use crate::repository::idempotency::{DynIdempotencyRepositoryTrait, NewIdempotencyKey};
+
+const MAX_KEY_LENGTH: usize = 255;
+
+#[automock]
+#[async_trait]
+pub trait IdempotencyServiceTrait {
+    /// Claims the key for a request. Returns the stored response when an
+    /// earlier request with the same key already succeeded.
+    async fn begin(
+        &self,
+        operation: String,
+        key: String,
+        fingerprint: String,
+    ) -> ServiceResult<Option<String>>;
+    async fn finish(
+        &self,
+        operation: String,
+        key: String,
+        outcome: &ServiceResult<String>,
+    ) -> ServiceResult<()>;
+}
+
+pub type DynIdempotencyServiceTrait = Arc<dyn IdempotencyServiceTrait + Sync + Send>;
+
+pub struct IdempotencyService {
+    repository: DynIdempotencyRepositoryTrait,
+    window: Duration,
+}
+
+impl IdempotencyService {
+    pub fn new(repository: DynIdempotencyRepositoryTrait, window_seconds: u64) -> Self {
+        Self {
+            repository,
+            window: Duration::seconds(window_seconds as i64),
+        }
+    }
+
+    fn validate_key(key: &str) -> ServiceResult<()> {
+        if key.len() > MAX_KEY_LENGTH {
+            return Err(ServiceError::BadRequest(format!(
+                "idempotency key cannot be longer than {} characters",
+                MAX_KEY_LENGTH
+            )));
+        }
+        if !key.chars().all(|char| char.is_ascii_graphic()) {
+            return Err(ServiceError::BadRequest(String::from(
+                "idempotency key can only contain visible ascii characters",
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IdempotencyServiceTrait for IdempotencyService {
+    async fn begin(
+        &self,
+        operation: String,
+        key: String,
+        fingerprint: String,
+    ) -> ServiceResult<Option<String>> {
+        Self::validate_key(&key)?;
+
+        let existing = match self
+            .repository
+            .claim_key(&NewIdempotencyKey {
+                operation: operation.clone(),
+                key: key.clone(),
+                fingerprint: fingerprint.clone(),
+                expires_at: OffsetDateTime::now_utc() + self.window,
+            })
+            .await?
+        {
+            Some(existing) => existing,
+            None => return Ok(None),
+        };
+
+        if existing.fingerprint != fingerprint {
+            error!("idempotency key {:?} was reused for {:?}", &key, &operation);
+            return Err(ServiceError::BadRequest(String::from(
+                "idempotency key was already used for a different request",
+            )));
+        }
+
+        info!("replaying {:?} for idempotency key {:?}", &operation, &key);
+        match (existing.response, existing.error) {
+            (Some(response), _) => Ok(Some(response)),
+            (None, Some(error)) => Err(ServiceError::InternalServerErrorWithContext(error)),
+            (None, None) => Err(ServiceError::ObjectConflict(String::from(
+                "a request with this idempotency key is still in progress",
+            ))),
+        }
+    }
+
+    async fn finish(
+        &self,
+        operation: String,
+        key: String,
+        outcome: &ServiceResult<String>,
+    ) -> ServiceResult<()> {
+        match outcome {
+            Ok(response) => {
+                self.repository
+                    .complete_key(&operation, &key, Some(response.clone()), None)
+                    .await?
+            }
+            // Failures past validation may have sent part of the mail, so a
+            // retry gets the failure instead of sending it again.
+            Err(ServiceError::InternalServerErrorWithContext(context)) => {
+                self.repository
+                    .complete_key(&operation, &key, None, Some(context.clone()))
+                    .await?
+            }
+            // Rejected requests sent nothing and can be retried once fixed.
+            Err(_) => self.repository.remove_key(&operation, &key).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use lettre::{message::Mailbox, Address};
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use mockall::automock;
+use tracing::log::{error, info};
+
+use crate::proto::email::{SenderIdentitiesResponse, SenderIdentityResponse};
+use crate::repository::{
+    group::DynGroupRepositoryTrait,
+    identity::{DynSenderIdentityRepositoryTrait, NewSenderIdentity, SenderIdentityEntity},
+};
+
+#[automock]
+#[async_trait]
+pub trait SenderIdentityServiceTrait {
+    async fn add_identity(
+        &self,
+        identity: NewSenderIdentity,
+    ) -> ServiceResult<SenderIdentityResponse>;
+    async fn remove_identity(&self, name: String) -> ServiceResult<()>;
+    async fn list_identities(&self) -> ServiceResult<SenderIdentitiesResponse>;
+    async fn set_group_identity(
+        &self,
+        group: String,
+        identity: Option<String>,
+    ) -> ServiceResult<()>;
+}
+
+pub type DynSenderIdentityServiceTrait = Arc<dyn SenderIdentityServiceTrait + Sync + Send>;
+
+pub struct SenderIdentityService {
+    identity_repository: DynSenderIdentityRepositoryTrait,
+    group_repository: DynGroupRepositoryTrait,
+}
+
+impl SenderIdentityService {
+    pub fn new(
+        identity_repository: DynSenderIdentityRepositoryTrait,
+        group_repository: DynGroupRepositoryTrait,
+    ) -> Self {
+        Self {
+            identity_repository,
+            group_repository,
+        }
+    }
+
+    fn validate_identity(identity: &NewSenderIdentity) -> ServiceResult<()> {
+        if identity.name.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "sender identity name cannot be empty",
+            )));
+        }
+        if identity.address.parse::<Address>().is_err() {
+            return Err(ServiceError::BadRequest(String::from(
+                "sender identity address is invalid",
+            )));
+        }
+        if let Some(reply_to) = &identity.reply_to {
+            if reply_to.parse::<Mailbox>().is_err() {
+                return Err(ServiceError::BadRequest(String::from(
+                    "sender identity reply-to is invalid",
+                )));
+            }
+        }
+        if identity.smtp_username.is_some() != identity.smtp_password.is_some() {
+            return Err(ServiceError::BadRequest(String::from(
+                "sender identity SMTP credentials need both a username and a password",
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn get_existing_identity(&self, name: &str) -> ServiceResult<SenderIdentityEntity> {
+        match self.identity_repository.get_identity(name).await? {
+            Some(identity) => Ok(identity),
+            None => {
+                error!("sender identity {:?} does not exist", name);
+                Err(ServiceError::ObjectConflict(String::from(
+                    "sender identity does not exist",
+                )))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SenderIdentityServiceTrait for SenderIdentityService {
+    async fn add_identity(
+        &self,
+        identity: NewSenderIdentity,
+    ) -> ServiceResult<SenderIdentityResponse> {
+        Self::validate_identity(&identity)?;
+
+        if self
+            .identity_repository
+            .get_identity(&identity.name)
+            .await?
+            .is_some()
+        {
+            error!("sender identity {:?} already exists", &identity.name);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "sender identity already exists",
+            )));
+        }
+
+        info!("creating sender identity {:?}", &identity.name);
+        let identity = self.identity_repository.add_identity(&identity).await?;
+
+        info!("sender identity successfully created");
+        Ok(identity.into_sender_identity_response())
+    }
+
+    async fn remove_identity(&self, name: String) -> ServiceResult<()> {
+        self.get_existing_identity(&name).await?;
+
+        info!("removing sender identity {:?}", &name);
+        self.identity_repository.remove_identity(&name).await?;
+
+        info!("sender identity successfully removed");
+        Ok(())
+    }
+
+    async fn list_identities(&self) -> ServiceResult<SenderIdentitiesResponse> {
+        let identities = self
+            .identity_repository
+            .list_identities()
+            .await?
+            .into_iter()
+            .map(|identity| identity.into_sender_identity_response())
+            .collect::<Vec<SenderIdentityResponse>>();
+
+        Ok(SenderIdentitiesResponse { identities })
+    }
+
+    async fn set_group_identity(
+        &self,
+        group: String,
+        identity: Option<String>,
+    ) -> ServiceResult<()> {
+        let group_entity = match self.group_repository.get_group(&group).await? {
+            Some(group_entity) => group_entity,
+            None => {
+                error!("group {:?} does not exist", &group);
+                return Err(ServiceError::ObjectConflict(String::from(
+                    "group does not exist",
+                )));
+            }
+        };
+        let identity_id = match identity {
+            Some(identity) => Some(self.get_existing_identity(&identity).await?.id),
+            None => None,
+        };
+
+        info!("setting the default sender identity of group {:?}", &group);
+        self.identity_repository
+            .set_group_identity(group_entity.id, identity_id)
+            .await?;
+
+        Ok(())
+    }
+}
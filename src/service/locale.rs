@@ -0,0 +1,84 @@
+/// Canonical form of a BCP 47 style locale tag, so that `ms_my` and `MS-my`
+/// both become `ms-MY`. Returns `None` for anything that is not a tag.
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let mut subtags = Vec::new();
+    for (position, subtag) in locale.trim().split(['-', '_']).enumerate() {
+        if subtag.is_empty()
+            || subtag.len() > 8
+            || !subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return None;
+        }
+
+        let alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+        let subtag = match (position, subtag.len()) {
+            (0, 2..=3) if alphabetic => subtag.to_ascii_lowercase(),
+            (0, _) => return None,
+            // Script, as in zh-Hant.
+            (_, 4) if alphabetic => {
+                let (first, rest) = subtag.split_at(1);
+                first.to_ascii_uppercase() + &rest.to_ascii_lowercase()
+            }
+            // Region, as in ms-MY.
+            (_, 2) if alphabetic => subtag.to_ascii_uppercase(),
+            _ => subtag.to_ascii_lowercase(),
+        };
+        subtags.push(subtag);
+    }
+
+    Some(subtags.join("-"))
+}
+
+/// Locales to try in order when picking a template variant, from the most
+/// specific one down to the default, e.g. `ms-MY`, `ms`, `en`. Invalid
+/// locales are skipped, leaving only the default.
+pub fn fallback_chain(locale: &str, default_locale: &str) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    for locale in [locale, default_locale]
+        .into_iter()
+        .filter_map(normalize_locale)
+    {
+        let subtags = locale.split('-').collect::<Vec<&str>>();
+        for end in (1..=subtags.len()).rev() {
+            let fallback = subtags[..end].join("-");
+            if !chain.contains(&fallback) {
+                chain.push(fallback);
+            }
+        }
+    }
+
+    chain
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{fallback_chain, normalize_locale};
+
+    #[test]
+    fn normalize_locale_test() {
+        assert_eq!(normalize_locale("en"), Some("en".to_string()));
+        assert_eq!(normalize_locale("ms_my"), Some("ms-MY".to_string()));
+        assert_eq!(
+            normalize_locale(" ZH-hant-tw "),
+            Some("zh-Hant-TW".to_string())
+        );
+        assert_eq!(normalize_locale("es-419"), Some("es-419".to_string()));
+        assert_eq!(normalize_locale(""), None);
+        assert_eq!(normalize_locale("english"), None);
+        assert_eq!(normalize_locale("ms--MY"), None);
+        assert_eq!(normalize_locale("zh-<b>"), None);
+    }
+
+    #[test]
+    fn fallback_chain_test() {
+        assert_eq!(fallback_chain("ms-MY", "en"), vec!["ms-MY", "ms", "en"]);
+        assert_eq!(
+            fallback_chain("zh_hant_tw", "en"),
+            vec!["zh-Hant-TW", "zh-Hant", "zh", "en"]
+        );
+        assert_eq!(fallback_chain("en-GB", "en"), vec!["en-GB", "en"]);
+        assert_eq!(fallback_chain("ms", "en-US"), vec!["ms", "en-US", "en"]);
+        assert_eq!(fallback_chain("", "en"), vec!["en"]);
+        assert_eq!(fallback_chain("not a locale", "en"), vec!["en"]);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{fs, sync::Arc};
+
+use anyhow::Context;
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+use crate::service::merge::escape_html;
+
+const DEFAULT_LAYOUT: &str = "<!DOCTYPE html>\
+    <html><head><meta charset=\"utf-8\" />\
+    <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />\
+    <title>{{subject}}</title></head>\
+    <body style=\"margin:0;padding:24px;font-family:Arial,sans-serif;line-height:1.5\">\
+    <div style=\"max-width:600px;margin:0 auto\">{{content}}</div></body></html>";
+
+/// HTML and plain text alternatives rendered from one Markdown body.
+#[derive(Debug, PartialEq)]
+pub struct RenderedMarkdown {
+    pub text: String,
+    pub html: String,
+}
+
+/// Renders Markdown bodies into sanitized HTML wrapped in the configured
+/// layout, together with a readable plain text alternative.
+pub struct MarkdownRenderer {
+    layout: String,
+}
+
+pub type DynMarkdownRenderer = Arc<MarkdownRenderer>;
+
+impl Default for MarkdownRenderer {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl MarkdownRenderer {
+    /// The layout places the rendered body at `{{content}}` and may show the
+    /// `{{subject}}`, an empty layout falls back to the built-in one.
+    pub fn new(layout: &str) -> Self {
+        Self {
+            layout: Some(layout)
+                .filter(|layout| !layout.trim().is_empty())
+                .unwrap_or(DEFAULT_LAYOUT)
+                .to_string(),
+        }
+    }
+
+    pub fn from_file(layout_path: Option<&str>) -> anyhow::Result<Self> {
+        let layout = match layout_path {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("could not read the markdown layout {:?}", path))?,
+            None => String::new(),
+        };
+
+        Ok(Self::new(&layout))
+    }
+
+    fn parser(markdown: &str) -> Parser<'_> {
+        Parser::new_ext(
+            markdown,
+            Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+        )
+    }
+
+    /// Raw HTML in the Markdown is run through the sanitizer like everything
+    /// else, so scripts, styles and event handlers never reach the layout.
+    fn render_html(markdown: &str) -> String {
+        let mut unsafe_html = String::new();
+        html::push_html(&mut unsafe_html, Self::parser(markdown));
+
+        ammonia::clean(&unsafe_html)
+    }
+
+    fn render_text(markdown: &str) -> String {
+        let mut text = String::new();
+        let mut lists: Vec<Option<u64>> = Vec::new();
+        let mut links = Vec::new();
+
+        for event in Self::parser(markdown) {
+            match event {
+                Event::Text(value) | Event::Code(value) => text.push_str(&value),
+                Event::SoftBreak | Event::HardBreak => text.push('\n'),
+                Event::Rule => text.push_str("----------\n\n"),
+                Event::Start(Tag::List(start)) => {
+                    if !lists.is_empty() && !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    lists.push(start);
+                }
+                Event::End(TagEnd::List(_)) => {
+                    lists.pop();
+                    if lists.is_empty() {
+                        text.push('\n');
+                    }
+                }
+                Event::Start(Tag::Item) => {
+                    text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                    match lists.last_mut() {
+                        Some(Some(number)) => {
+                            text.push_str(&format!("{}. ", number));
+                            *number += 1;
+                        }
+                        _ => text.push_str("- "),
+                    }
+                }
+                Event::End(TagEnd::Item) if !text.ends_with('\n') => text.push('\n'),
+                Event::Start(Tag::Link { dest_url, .. }) => {
+                    links.push((text.len(), dest_url));
+                }
+                Event::End(TagEnd::Link) => {
+                    if let Some((start, dest_url)) = links.pop() {
+                        if text[start..] != *dest_url {
+                            text.push_str(&format!(" ({})", dest_url));
+                        }
+                    }
+                }
+                Event::End(TagEnd::TableCell) => text.push('\t'),
+                Event::End(TagEnd::TableHead) | Event::End(TagEnd::TableRow) => {
+                    text.truncate(text.trim_end_matches('\t').len());
+                    text.push('\n');
+                }
+                Event::End(
+                    TagEnd::Paragraph
+                    | TagEnd::Heading(_)
+                    | TagEnd::CodeBlock
+                    | TagEnd::BlockQuote(_)
+                    | TagEnd::Table,
+                ) if lists.is_empty() => {
+                    text.truncate(text.trim_end().len());
+                    text.push_str("\n\n");
+                }
+                _ => {}
+            }
+        }
+
+        text.trim_end().to_string()
+    }
+
+    pub fn render(&self, subject: &str, markdown: &str) -> RenderedMarkdown {
+        RenderedMarkdown {
+            text: Self::render_text(markdown),
+            html: self
+                .layout
+                .replace("{{subject}}", &escape_html(subject))
+                .replace("{{content}}", &Self::render_html(markdown)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::MarkdownRenderer;
+
+    #[test]
+    fn render_markdown_test() {
+        let renderer = MarkdownRenderer::new("<h1>{{subject}}</h1><main>{{content}}</main>");
+
+        let rendered = renderer.render(
+            "News & updates",
+            "# Hello\n\n\
+            Read the [release notes](https://example.com/notes) or visit \
+            <https://example.com>.\n\n\
+            1. First\n2. Second\n   - nested\n\n\
+            <script>alert('hi')</script>\n\n\
+            <a href=\"javascript:alert(1)\" onclick=\"steal()\">click</a>\n",
+        );
+
+        assert_eq!(
+            rendered.text,
+            "Hello\n\n\
+            Read the release notes (https://example.com/notes) or visit https://example.com.\n\n\
+            1. First\n2. Second\n  - nested\n\n\
+            click"
+        );
+        assert!(rendered
+            .html
+            .starts_with("<h1>News &amp; updates</h1><main><h1>Hello</h1>"));
+        assert!(rendered
+            .html
+            .contains("<a href=\"https://example.com/notes\" rel=\"noopener noreferrer\">"));
+        assert!(rendered.html.contains("<li>Second\n<ul>\n<li>nested</li>"));
+        assert!(!rendered.html.contains("script"));
+        assert!(!rendered.html.contains("javascript"));
+        assert!(!rendered.html.contains("onclick"));
+        assert!(rendered.html.ends_with("</main>"));
+    }
+
+    #[test]
+    fn default_layout_test() {
+        let rendered = MarkdownRenderer::default().render("Selamat datang", "**Terima kasih**");
+
+        assert_eq!(rendered.text, "Terima kasih");
+        assert!(rendered.html.starts_with("<!DOCTYPE html>"));
+        assert!(rendered.html.contains("<title>Selamat datang</title>"));
+        assert!(rendered
+            .html
+            .contains("<p><strong>Terima kasih</strong></p>\n</div></body></html>"));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Values available to merge fields: every scalar attribute of the
+/// subscriber, plus their `email`.
+pub fn merge_fields(
+    email: &str,
+    attributes: Option<&serde_json::Value>,
+) -> HashMap<String, String> {
+    let mut fields = attributes
+        .and_then(|attributes| attributes.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| match value {
+            serde_json::Value::String(value) => Some((key.clone(), value.clone())),
+            serde_json::Value::Number(value) => Some((key.clone(), value.to_string())),
+            serde_json::Value::Bool(value) => Some((key.clone(), value.to_string())),
+            _ => None,
+        })
+        .collect::<HashMap<String, String>>();
+    fields.insert("email".to_string(), email.to_string());
+
+    fields
+}
+
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn merge(template: &str, fields: &HashMap<String, String>, escape: fn(&str) -> String) -> String {
+    let mut merged = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        merged.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let end = match placeholder.find("}}") {
+            Some(end) => end,
+            None => {
+                rest = placeholder;
+                break;
+            }
+        };
+
+        // `{{name|there}}` falls back to "there" when the field is empty.
+        let (name, fallback) = match placeholder[2..end].split_once('|') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (placeholder[2..end].trim(), None),
+        };
+        if is_field_name(name) {
+            let value = fields
+                .get(name)
+                .map(String::as_str)
+                .filter(|value| !value.is_empty())
+                .or(fallback)
+                .unwrap_or_default();
+            merged.push_str(&escape(value));
+        } else {
+            merged.push_str(&placeholder[..end + 2]);
+        }
+        rest = &placeholder[end + 2..];
+    }
+    merged.push_str(rest);
+
+    merged
+}
+
+/// Replaces `{{field}}` placeholders, unknown fields render empty.
+pub fn merge_text(template: &str, fields: &HashMap<String, String>) -> String {
+    merge(template, fields, str::to_string)
+}
+
+/// Like `merge_text`, with the values escaped for HTML.
+pub fn merge_html(template: &str, fields: &HashMap<String, String>) -> String {
+    merge(template, fields, escape_html)
+}
+
+/// Merges `template` with `merge`, except for `placeholder` which is
+/// replaced by `block` as is, the caller having rendered and escaped it.
+pub fn merge_with_block(
+    template: &str,
+    fields: &HashMap<String, String>,
+    placeholder: &str,
+    block: &str,
+    merge: fn(&str, &HashMap<String, String>) -> String,
+) -> String {
+    template
+        .split(placeholder)
+        .map(|part| merge(part, fields))
+        .collect::<Vec<String>>()
+        .join(block)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{merge_fields, merge_html, merge_text, merge_with_block};
+
+    #[test]
+    fn merge_fields_test() {
+        let fields = merge_fields(
+            "aminah@email.com",
+            Some(&serde_json::json!({
+                "name": "Aminah <3",
+                "orders": 4,
+                "vip": true,
+                "address": { "city": "Ipoh" },
+            })),
+        );
+
+        assert_eq!(
+            merge_text(
+                "Hi {{ name }}, order #{{orders}} ships to {{email}}{{city}}.",
+                &fields
+            ),
+            "Hi Aminah <3, order #4 ships to aminah@email.com."
+        );
+        assert_eq!(
+            merge_html("<p>Hi {{name}}{{vip|}}</p>", &fields),
+            "<p>Hi Aminah &lt;3true</p>"
+        );
+    }
+
+    #[test]
+    fn merge_fallback_test() {
+        let fields = merge_fields("someone@email.com", None);
+
+        assert_eq!(
+            merge_text("Hi {{name|there}}, {{ not a field }} {{unclosed", &fields),
+            "Hi there, {{ not a field }} {{unclosed"
+        );
+        assert_eq!(merge_html("{{name | <friend>}}", &fields), "&lt;friend&gt;");
+    }
+
+    #[test]
+    fn merge_with_block_test() {
+        let fields = merge_fields("someone@email.com", None);
+
+        assert_eq!(
+            merge_with_block(
+                "<p>For {{email}}</p>{{items}}<p>{{items}}</p>",
+                &fields,
+                "{{items}}",
+                "<ul><li>one</li></ul>",
+                merge_html
+            ),
+            "<p>For someone@email.com</p><ul><li>one</li></ul><p><ul><li>one</li></ul></p>"
+        );
+        assert_eq!(
+            merge_with_block("Nothing to list", &fields, "{{items}}", "- one", merge_text),
+            "Nothing to list"
+        );
+    }
+}
@@ -1,24 +1,82 @@
+pub mod blast;
+pub mod bounce;
+pub mod campaign;
+pub mod delivery;
+pub mod digest;
+pub mod dkim;
 pub mod email;
+pub mod footer;
 pub mod group;
+pub mod idempotency;
+pub mod identity;
+pub mod locale;
+pub mod markdown;
+pub mod merge;
+pub mod outbox;
+pub mod rate_limit;
+pub mod report;
+pub mod schedule;
 pub mod subscriber;
+pub mod template;
+pub mod tracker;
+pub mod tracking;
+pub mod validation;
+pub mod verp;
 
 #[cfg(test)]
 pub mod test {
-    use std::sync::Arc;
+    use std::{collections::HashSet, sync::Arc};
 
     use clap::Parser;
-    use sqlx::PgPool;
+    use futures::{StreamExt, TryStreamExt};
+    use madtofan_microservice_common::errors::ServiceError;
+    use sqlx::{types::time::OffsetDateTime, PgPool};
+    use time::Duration;
+    use time_tz::{timezones::db, OffsetDateTimeExt};
 
     use crate::{
         config::AppConfig,
+        proto::email::{BlastProgressResponse, ExportFormat, SubscriberOutcome},
         repository::{
+            campaign::{CampaignRepository, DynCampaignRepositoryTrait, NewCampaign},
+            delivery::{
+                DeliveryRepository, DeliveryStatus, DynDeliveryRepositoryTrait, MessageClass,
+                NewDelivery,
+            },
+            digest::{DigestItemStatus, DigestRepository, DynDigestRepositoryTrait, NewDigestItem},
             group::{DynGroupRepositoryTrait, GroupRepository},
+            identity::{
+                DynSenderIdentityRepositoryTrait, NewSenderIdentity, SenderIdentityRepository,
+            },
+            subcriber::NewSubscriber,
             subcriber::{DynSubscriberRepositoryTrait, SubscriberRepository},
+            suppression::{
+                DynSuppressionRepositoryTrait, NewSuppression, SuppressionReason,
+                SuppressionRepository,
+            },
+            template::{DynTemplateRepositoryTrait, NewTemplate, TemplateRepository},
+            tracking::{DynTrackingRepositoryTrait, TrackingRepository},
+            unsubscription::{DynUnsubscriptionRepositoryTrait, UnsubscriptionRepository},
         },
         service::{
-            email::{DynEmailServiceTrait, EmailService},
+            bounce::{BounceService, DynBounceServiceTrait},
+            campaign::{CampaignService, CampaignServiceTrait, DynCampaignServiceTrait},
+            delivery::{DeliveryService, DynDeliveryServiceTrait},
+            digest::{DigestService, DigestServiceTrait, DynDigestServiceTrait},
+            email::{
+                DynEmailServiceTrait, EmailContent, EmailRecipients, EmailService,
+                EmailServiceTrait, MockEmailServiceTrait,
+            },
+            footer::Footer,
             group::{DynGroupServiceTrait, GroupService},
+            identity::{DynSenderIdentityServiceTrait, SenderIdentityService},
+            markdown::MarkdownRenderer,
             subscriber::{DynSubscriberServiceTrait, SubscriberService},
+            template::{DynTemplateServiceTrait, TemplateService},
+            tracker::Tracker,
+            tracking::{DynTrackingServiceTrait, TrackingService},
+            validation::EmailValidator,
+            verp::Verp,
         },
     };
 
@@ -28,6 +86,19 @@ pub mod test {
         subscriber_service: DynSubscriberServiceTrait,
         group_service: DynGroupServiceTrait,
         email_service: DynEmailServiceTrait,
+        campaign_repository: DynCampaignRepositoryTrait,
+        campaign_service: DynCampaignServiceTrait,
+        delivery_service: DynDeliveryServiceTrait,
+        delivery_repository: DynDeliveryRepositoryTrait,
+        suppression_repository: DynSuppressionRepositoryTrait,
+        bounce_service: DynBounceServiceTrait,
+        tracking_service: DynTrackingServiceTrait,
+        tracker: Tracker,
+        identity_service: DynSenderIdentityServiceTrait,
+        unsubscription_repository: DynUnsubscriptionRepositoryTrait,
+        template_service: DynTemplateServiceTrait,
+        digest_repository: DynDigestRepositoryTrait,
+        digest_service: DynDigestServiceTrait,
     }
 
     fn initialize_handler(pool: PgPool) -> AllTraits {
@@ -37,13 +108,86 @@ pub mod test {
             Arc::new(SubscriberRepository::new(pool.clone())) as DynSubscriberRepositoryTrait;
         let group_repository =
             Arc::new(GroupRepository::new(pool.clone())) as DynGroupRepositoryTrait;
+        let email_validator = Arc::new(EmailValidator::default());
         let subscriber_service = Arc::new(SubscriberService::new(
             subscriber_repository.clone(),
             group_repository.clone(),
+            email_validator.clone(),
         )) as DynSubscriberServiceTrait;
-        let group_service =
-            Arc::new(GroupService::new(group_repository.clone())) as DynGroupServiceTrait;
-        let email_service = Arc::new(EmailService::new(&config)) as DynEmailServiceTrait;
+        let group_service = Arc::new(GroupService::new(
+            group_repository.clone(),
+            email_validator.clone(),
+        )) as DynGroupServiceTrait;
+        let delivery_repository =
+            Arc::new(DeliveryRepository::new(pool.clone())) as DynDeliveryRepositoryTrait;
+        let suppression_repository =
+            Arc::new(SuppressionRepository::new(pool.clone())) as DynSuppressionRepositoryTrait;
+        let verp = Some(Arc::new(Verp::new("bounce", "email.com")));
+        let tracker = Tracker::new("https://track.email.com", "tracking_secret");
+        let identity_repository = Arc::new(SenderIdentityRepository::new(pool.clone()))
+            as DynSenderIdentityRepositoryTrait;
+        let template_repository =
+            Arc::new(TemplateRepository::new(pool.clone())) as DynTemplateRepositoryTrait;
+        let unsubscription_repository = Arc::new(UnsubscriptionRepository::new(pool.clone()))
+            as DynUnsubscriptionRepositoryTrait;
+        let email_service = Arc::new(EmailService::new(
+            &config,
+            email_validator.clone(),
+            delivery_repository.clone(),
+            suppression_repository.clone(),
+            identity_repository.clone(),
+            template_repository.clone(),
+            subscriber_repository.clone(),
+            unsubscription_repository.clone(),
+            Arc::new(MarkdownRenderer::default()),
+            verp.clone(),
+            Some(Arc::new(tracker.clone())),
+            None,
+            Some(Arc::new(Footer::new(
+                "1 Jalan Example, Kuala Lumpur",
+                Default::default(),
+            ))),
+        )) as DynEmailServiceTrait;
+        let bounce_service = Arc::new(BounceService::new(
+            delivery_repository.clone(),
+            suppression_repository.clone(),
+            verp,
+        )) as DynBounceServiceTrait;
+        let delivery_service =
+            Arc::new(DeliveryService::new(delivery_repository.clone())) as DynDeliveryServiceTrait;
+        let campaign_repository =
+            Arc::new(CampaignRepository::new(pool.clone())) as DynCampaignRepositoryTrait;
+        let campaign_service = Arc::new(CampaignService::new(
+            campaign_repository.clone(),
+            group_repository.clone(),
+            identity_repository.clone(),
+            template_repository.clone(),
+            email_service.clone(),
+            config.test_blast_subject_prefix.clone(),
+        )) as DynCampaignServiceTrait;
+        let digest_repository =
+            Arc::new(DigestRepository::new(pool.clone())) as DynDigestRepositoryTrait;
+        let digest_service = Arc::new(DigestService::new(
+            digest_repository.clone(),
+            template_repository.clone(),
+            email_validator,
+            email_service.clone(),
+        )) as DynDigestServiceTrait;
+        let template_service =
+            Arc::new(TemplateService::new(template_repository)) as DynTemplateServiceTrait;
+        let identity_service = Arc::new(SenderIdentityService::new(
+            identity_repository,
+            group_repository.clone(),
+        )) as DynSenderIdentityServiceTrait;
+        let tracking_repository =
+            Arc::new(TrackingRepository::new(pool.clone())) as DynTrackingRepositoryTrait;
+        let tracking_service = Arc::new(TrackingService::new(
+            tracking_repository,
+            campaign_repository.clone(),
+            delivery_repository.clone(),
+            unsubscription_repository.clone(),
+            Some(Arc::new(tracker.clone())),
+        )) as DynTrackingServiceTrait;
 
         AllTraits {
             subscriber_repository,
@@ -51,6 +195,19 @@ pub mod test {
             group_repository,
             group_service,
             email_service,
+            campaign_repository,
+            campaign_service,
+            delivery_service,
+            delivery_repository,
+            suppression_repository,
+            bounce_service,
+            tracking_service,
+            tracker,
+            identity_service,
+            unsubscription_repository,
+            template_service,
+            digest_repository,
+            digest_service,
         }
     }
 
@@ -104,6 +261,109 @@ pub mod test {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn copy_group_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group1_name = "group1_name";
+        let group1 = traits
+            .group_repository
+            .add_group(group1_name, "group1_description")
+            .await?;
+        let group2_name = "group2_name";
+        let group2 = traits
+            .group_repository
+            .add_group(group2_name, "group2_description")
+            .await?;
+
+        traits
+            .subscriber_repository
+            .add_subscriber("sub1_email", &group1)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub2_email", &group1)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub2_email", &group2)
+            .await?;
+
+        let summary = traits
+            .group_service
+            .copy_group(group1_name.to_string(), group2_name.to_string())
+            .await?;
+
+        assert_eq!(summary.inserted, 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(
+            traits
+                .subscriber_repository
+                .get_subs_by_group_count(&group1)
+                .await?,
+            2
+        );
+        assert_eq!(
+            traits
+                .subscriber_repository
+                .get_subs_by_group_count(&group2)
+                .await?,
+            2
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn move_subscriber_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group1_name = "group1_name";
+        let group1 = traits
+            .group_repository
+            .add_group(group1_name, "group1_description")
+            .await?;
+        let group2_name = "group2_name";
+        traits
+            .group_repository
+            .add_group(group2_name, "group2_description")
+            .await?;
+
+        let sub_email = "sub_email";
+        traits
+            .subscriber_repository
+            .add_subscriber(sub_email, &group1)
+            .await?;
+
+        traits
+            .group_service
+            .move_subscriber(
+                sub_email.to_string(),
+                group1_name.to_string(),
+                group2_name.to_string(),
+            )
+            .await?;
+
+        let groups_list = traits
+            .group_repository
+            .list_groups_by_sub(sub_email, None, None)
+            .await?;
+        assert_eq!(groups_list.len(), 1);
+        assert_eq!(groups_list.first().unwrap().name, group2_name);
+
+        let missing_move = traits
+            .group_service
+            .move_subscriber(
+                sub_email.to_string(),
+                group1_name.to_string(),
+                group2_name.to_string(),
+            )
+            .await;
+        assert!(missing_move.is_err());
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn list_groups_by_sub_test(pool: PgPool) -> anyhow::Result<()> {
         let traits = initialize_handler(pool);
@@ -187,7 +447,7 @@ pub mod test {
             .add_group(group_name, group_description)
             .await?;
 
-        let sub_email = "sub_email";
+        let sub_email = "sub@email.com";
         traits
             .subscriber_service
             .add_subscriber(sub_email.to_string(), group_name.to_string())
@@ -203,7 +463,7 @@ pub mod test {
     }
 
     #[sqlx::test]
-    async fn remove_subcriber_from_group_test(pool: PgPool) -> anyhow::Result<()> {
+    async fn add_invalid_subscriber_test(pool: PgPool) -> anyhow::Result<()> {
         let traits = initialize_handler(pool);
 
         let group_name = "group_name";
@@ -212,51 +472,219 @@ pub mod test {
             .add_group(group_name, "group_description")
             .await?;
 
-        let sub1_email = "sub1_email";
-        traits
+        let added_sub = traits
+            .subscriber_service
+            .add_subscriber("not an email".to_string(), group_name.to_string())
+            .await;
+        let subs_count = traits
             .subscriber_repository
-            .add_subscriber(sub1_email, &group)
+            .get_subs_by_group_count(&group)
+            .await?;
+
+        assert!(matches!(added_sub, Err(ServiceError::BadRequest(_))));
+        assert_eq!(subs_count, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn add_and_remove_subscribers_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        let group = traits
+            .group_repository
+            .add_group(group_name, "group_description")
             .await?;
-        let sub2_email = "sub2_email";
         traits
             .subscriber_repository
-            .add_subscriber(sub2_email, &group)
+            .add_subscriber("existing@email.com", &group)
             .await?;
 
-        traits
+        let added = traits
             .subscriber_service
-            .remove_subscriber_from_group(sub1_email.to_string(), group_name.to_string())
+            .add_subscribers(
+                vec![
+                    "new@email.com".to_string(),
+                    "existing@email.com".to_string(),
+                    "invalid".to_string(),
+                ],
+                group_name.to_string(),
+            )
+            .await?
+            .outcomes
+            .into_iter()
+            .map(|outcome| outcome.status)
+            .collect::<Vec<i32>>();
+
+        assert_eq!(
+            added,
+            vec![
+                SubscriberOutcome::Added as i32,
+                SubscriberOutcome::AlreadyPresent as i32,
+                SubscriberOutcome::Invalid as i32,
+            ]
+        );
+
+        traits
+            .subscriber_repository
+            .add_subscriber("Legacy@Email.com", &group)
             .await?;
+        let removed = traits
+            .subscriber_service
+            .remove_subscribers(
+                vec![
+                    "new@email.com".to_string(),
+                    "missing@email.com".to_string(),
+                    " LEGACY@email.com ".to_string(),
+                    " ".to_string(),
+                ],
+                group_name.to_string(),
+            )
+            .await?
+            .outcomes
+            .into_iter()
+            .map(|outcome| outcome.status)
+            .collect::<Vec<i32>>();
+
+        assert_eq!(
+            removed,
+            vec![
+                SubscriberOutcome::Removed as i32,
+                SubscriberOutcome::NotFound as i32,
+                SubscriberOutcome::Removed as i32,
+                SubscriberOutcome::Invalid as i32,
+            ]
+        );
+
         let subs_list = traits
             .subscriber_repository
-            .list_subs_by_group(&group, Some(0), Some(100))
+            .list_subs_by_group(&group, None, None)
             .await?;
-
         assert_eq!(subs_list.len(), 1);
-        assert_eq!(subs_list.first().unwrap().email, sub2_email);
+        assert_eq!(subs_list.first().unwrap().email, "existing@email.com");
 
         Ok(())
     }
 
     #[sqlx::test]
-    async fn send_email_test(pool: PgPool) -> anyhow::Result<()> {
+    async fn import_subscribers_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        let group_name = "group_name";
+        let group = traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("existing@email.com", &group)
+            .await?;
+
+        let csv = "email,name\n\
+            new@EMAIL.com,New\n\
+            existing@email.com,Existing\n\
+            not an email,Invalid\n\
+            new@email.com,Repeated\n";
+
+        let report = traits
+            .subscriber_service
+            .import_subscribers(group_name.to_string(), csv.as_bytes().to_vec())
+            .await?;
+
+        assert_eq!(report.inserted, 1);
+        assert_eq!(report.duplicates, vec![3, 5]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected.first().unwrap().line, 4);
+
+        let attributes: serde_json::Value =
+            sqlx::query_scalar("select attributes from subscriber where email = $1")
+                .bind("new@email.com")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(attributes, serde_json::json!({ "name": "New" }));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn export_subscribers_test(pool: PgPool) -> anyhow::Result<()> {
         let traits = initialize_handler(pool);
 
+        let group_name = "group_name";
+        let group = traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
         traits
-            .email_service
-            .send_email(
-                "email@test.com".to_string(),
-                "hello".to_string(),
-                "this is a test".to_string(),
+            .subscriber_service
+            .import_subscribers(
+                group_name.to_string(),
+                "email,name,plan\n\
+                    sub1@email.com,\"Doe, Jane\",pro\n\
+                    sub2@email.com,John,free\n"
+                    .as_bytes()
+                    .to_vec(),
+            )
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[NewSubscriber {
+                    email: "other@email.com".to_string(),
+                    attributes: serde_json::json!({ "name": 42, "plan": null }),
+                }],
+                &group,
+            )
+            .await?;
+
+        let csv_rows = traits
+            .subscriber_service
+            .export_subscribers(
+                group_name.to_string(),
+                serde_json::json!({}),
+                ExportFormat::Csv,
+                vec!["name".to_string(), "plan".to_string()],
+            )
+            .await?
+            .try_collect::<Vec<String>>()
+            .await?;
+
+        assert_eq!(
+            csv_rows,
+            vec![
+                "email,name,plan\n",
+                "sub1@email.com,\"Doe, Jane\",pro\n",
+                "sub2@email.com,John,free\n",
+                "other@email.com,42,\n",
+            ]
+        );
+
+        let ndjson_rows = traits
+            .subscriber_service
+            .export_subscribers(
+                group_name.to_string(),
+                serde_json::json!({ "plan": "pro" }),
+                ExportFormat::Ndjson,
+                vec!["name".to_string()],
             )
+            .await?
+            .try_collect::<Vec<String>>()
             .await?;
 
+        assert_eq!(ndjson_rows.len(), 1);
+        let row: serde_json::Value = serde_json::from_str(ndjson_rows.first().unwrap())?;
+        assert_eq!(row["email"], "sub1@email.com");
+        assert_eq!(
+            row["attributes"],
+            serde_json::json!({ "name": "Doe, Jane" })
+        );
+
         Ok(())
     }
 
     #[sqlx::test]
-    #[ignore]
-    async fn blast_email_test(pool: PgPool) -> anyhow::Result<()> {
+    async fn remove_subcriber_from_group_test(pool: PgPool) -> anyhow::Result<()> {
         let traits = initialize_handler(pool);
 
         let group_name = "group_name";
@@ -276,24 +704,2108 @@ pub mod test {
             .add_subscriber(sub2_email, &group)
             .await?;
 
+        traits
+            .subscriber_service
+            .remove_subscriber_from_group(sub1_email.to_string(), group_name.to_string())
+            .await?;
         let subs_list = traits
             .subscriber_repository
-            .list_subs_by_group(&group, None, None)
+            .list_subs_by_group(&group, Some(0), Some(100))
             .await?;
-        let email_list = subs_list
-            .iter()
-            .map(|entity| entity.email.clone())
-            .collect::<Vec<String>>();
 
+        assert_eq!(subs_list.len(), 1);
+        assert_eq!(subs_list.first().unwrap().email, sub2_email);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn campaign_lifecycle_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group1_name = "group1_name";
+        let group1 = traits
+            .group_repository
+            .add_group(group1_name, "group1_description")
+            .await?;
+        let group2_name = "group2_name";
+        let group2 = traits
+            .group_repository
+            .add_group(group2_name, "group2_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub1_email", &group1)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub1_email", &group2)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub2_email", &group2)
+            .await?;
+
+        let campaign = traits
+            .campaign_service
+            .create_campaign(
+                NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec![group1_name.to_string()],
+            )
+            .await?;
+        assert_eq!(campaign.status, "draft");
+
+        let edited = traits
+            .campaign_service
+            .edit_campaign(
+                campaign.id,
+                NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "edited subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: "<p>html body</p>".to_string(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec![group1_name.to_string(), group2_name.to_string()],
+            )
+            .await?;
+        assert_eq!(edited.subject, "edited subject");
+        assert_eq!(edited.groups.len(), 2);
+
+        let preview = traits
+            .campaign_service
+            .preview_campaign(campaign.id)
+            .await?;
+        assert_eq!(preview.recipient_count, 2);
+
+        let tomorrow = sqlx::types::time::OffsetDateTime::now_utc().unix_timestamp() + 86400;
+        let scheduled = traits
+            .campaign_service
+            .send_campaign(campaign.id, Some(tomorrow))
+            .await?;
+        assert_eq!(scheduled.status, "scheduled");
+        assert_eq!(scheduled.scheduled_at, tomorrow);
+        assert!(traits
+            .campaign_repository
+            .list_due_campaigns()
+            .await?
+            .is_empty());
+
+        let cancelled = traits.campaign_service.cancel_campaign(campaign.id).await?;
+        assert_eq!(cancelled.status, "cancelled");
+
+        let edit_cancelled = traits
+            .campaign_service
+            .edit_campaign(
+                campaign.id,
+                NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec![group1_name.to_string()],
+            )
+            .await;
+        assert!(edit_cancelled.is_err());
+
+        let listed = traits
+            .campaign_service
+            .list_campaigns(Some("cancelled".to_string()), None, None)
+            .await?;
+        assert_eq!(listed.count, 1);
+        assert_eq!(listed.campaigns.first().unwrap().id, campaign.id);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn process_bounce_report_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let message_id = "<bounced_message@email.com>";
         traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: message_id.to_string(),
+                recipient: "gone@test.com".to_string(),
+                subject: "hello".to_string(),
+                campaign_id: None,
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+
+        let report = format!(
+            "From: MAILER-DAEMON@mx.test.com\r\n\
+            To: service@email.com\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/report; report-type=delivery-status; boundary=\"dsn\"\r\n\
+            \r\n\
+            --dsn\r\n\
+            Content-Type: message/delivery-status\r\n\
+            \r\n\
+            Reporting-MTA: dns; mx.test.com\r\n\
+            \r\n\
+            Final-Recipient: rfc822; gone@test.com\r\n\
+            Action: failed\r\n\
+            Status: 5.1.1\r\n\
+            --dsn\r\n\
+            Content-Type: text/rfc822-headers\r\n\
+            \r\n\
+            Message-ID: {}\r\n\
+            To: gone@test.com\r\n\
+            --dsn--\r\n",
+            message_id
+        );
+        let reports = traits
+            .bounce_service
+            .process_report(report.into_bytes())
+            .await?;
+
+        let delivery = traits
+            .delivery_service
+            .get_message_status(message_id.to_string())
+            .await?;
+        let suppression = traits
+            .suppression_repository
+            .get_suppression("gone@test.com")
+            .await?;
+        let resend = traits
             .email_service
-            .blast_email(
-                email_list,
-                "hello".to_string(),
-                "this is a test".to_string(),
+            .send_email(
+                EmailRecipients {
+                    to: vec!["gone@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    ..Default::default()
+                },
             )
+            .await;
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(delivery.status, "bounced");
+        assert_eq!(suppression.unwrap().reason, "hard_bounce");
+        assert!(matches!(resend, Err(ServiceError::BadRequest(_))));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn process_verp_bounce_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let message_id = "<verp_message@email.com>";
+        traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: message_id.to_string(),
+                recipient: "forwarded@test.com".to_string(),
+                subject: "hello".to_string(),
+                campaign_id: None,
+                verp_token: Some("verptoken".to_string()),
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+
+        let report = "Delivered-To: bounce+verptoken@email.com\r\n\
+            From: MAILER-DAEMON@mx.test.com\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/report; report-type=delivery-status; boundary=\"dsn\"\r\n\
+            \r\n\
+            --dsn\r\n\
+            Content-Type: message/delivery-status\r\n\
+            \r\n\
+            Reporting-MTA: dns; mx.test.com\r\n\
+            \r\n\
+            Final-Recipient: rfc822; rewritten@forward.test.com\r\n\
+            Action: failed\r\n\
+            Status: 5.1.1\r\n\
+            --dsn--\r\n";
+        let reports = traits
+            .bounce_service
+            .process_report(report.as_bytes().to_vec())
             .await?;
 
+        let delivery = traits
+            .delivery_service
+            .get_message_status(message_id.to_string())
+            .await?;
+        let suppression = traits
+            .suppression_repository
+            .get_suppression("forwarded@test.com")
+            .await?;
+
+        assert_eq!(reports.first().unwrap().recipient, "forwarded@test.com");
+        assert_eq!(delivery.status, "bounced");
+        assert!(suppression.is_some());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn process_unmatched_report_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let report = "From: MAILER-DAEMON@mx.test.com\r\n\
+            To: service@email.com\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/report; report-type=delivery-status; boundary=\"dsn\"\r\n\
+            \r\n\
+            --dsn\r\n\
+            Content-Type: message/delivery-status\r\n\
+            \r\n\
+            Reporting-MTA: dns; mx.test.com\r\n\
+            \r\n\
+            Final-Recipient: rfc822; victim@test.com\r\n\
+            Action: failed\r\n\
+            Status: 5.1.1\r\n\
+            --dsn\r\n\
+            Content-Type: text/rfc822-headers\r\n\
+            \r\n\
+            Message-ID: <forged_message@email.com>\r\n\
+            To: victim@test.com\r\n\
+            --dsn--\r\n";
+        let reports = traits
+            .bounce_service
+            .process_report(report.as_bytes().to_vec())
+            .await?;
+
+        let suppression = traits
+            .suppression_repository
+            .get_suppression("victim@test.com")
+            .await?;
+
+        assert!(reports.is_empty());
+        assert!(suppression.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn campaign_engagement_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("group_name", "group_description")
+            .await?;
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: "<a href=\"https://email.com\">link</a>".to_string(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                &[group],
+            )
+            .await?;
+        let delivery = traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: "<tracked_message@email.com>".to_string(),
+                recipient: "sub@email.com".to_string(),
+                subject: "subject".to_string(),
+                campaign_id: Some(campaign.id),
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+        traits
+            .delivery_repository
+            .update_delivery_status(delivery.id, DeliveryStatus::Sent, "250 OK")
+            .await?;
+
+        let open_url = traits.tracker.open_url(delivery.id);
+        let open_signature = open_url.rsplit('/').next().unwrap();
+        let click_url = traits.tracker.click_url(delivery.id, "https://email.com");
+        let (click_path, encoded_url) = click_url.split_once("?u=").unwrap();
+        let click_signature = click_path.rsplit('/').next().unwrap();
+
+        traits
+            .tracking_service
+            .record_open(delivery.id, open_signature.to_string(), "agent".to_string())
+            .await?;
+        let redirect = traits
+            .tracking_service
+            .record_click(
+                delivery.id,
+                encoded_url.to_string(),
+                click_signature.to_string(),
+                "agent".to_string(),
+            )
+            .await?;
+        let forged = traits
+            .tracking_service
+            .record_open(
+                delivery.id,
+                click_signature.to_string(),
+                "agent".to_string(),
+            )
+            .await;
+        let engagement = traits
+            .tracking_service
+            .get_campaign_engagement(campaign.id)
+            .await?;
+
+        assert_eq!(redirect, "https://email.com");
+        assert!(matches!(forged, Err(ServiceError::BadRequest(_))));
+        assert_eq!(engagement.sent, 1);
+        assert_eq!(engagement.opened, 1);
+        assert_eq!(engagement.clicked, 1);
+        assert_eq!(engagement.open_rate, 1.0);
+        assert_eq!(engagement.click_rate, 1.0);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn send_email_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let message_id = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["email@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let delivery = traits
+            .delivery_service
+            .get_message_status(message_id.clone())
+            .await?;
+        let deliveries = traits
+            .delivery_service
+            .list_deliveries(Some("email@test.com".to_string()), None, None, None)
+            .await?;
+
+        assert_eq!(delivery.status, "sent");
+        assert_eq!(delivery.subject, "hello");
+        assert_eq!(deliveries.count, 1);
+        assert_eq!(
+            deliveries.deliveries.first().unwrap().message_id,
+            message_id
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn send_email_with_copies_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let content = EmailContent {
+            subject: "Re: hello".to_string(),
+            text_body: "this is a test".to_string(),
+            reply_to: Some("Support <support@test.com>".to_string()),
+            headers: vec![
+                ("in-reply-to".to_string(), "<first@test.com>".to_string()),
+                ("References".to_string(), "<first@test.com>".to_string()),
+                ("X-Entity-Ref-ID".to_string(), "ticket-42".to_string()),
+            ],
+            ..Default::default()
+        };
+        let message_id = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["to1@test.com".to_string(), "to2@test.com".to_string()],
+                    cc: vec!["cc@test.com".to_string()],
+                    bcc: vec!["bcc@test.com".to_string()],
+                },
+                content.clone(),
+            )
+            .await?;
+        let delivery = traits
+            .delivery_service
+            .get_message_status(message_id)
+            .await?;
+        assert_eq!(delivery.recipient, "to1@test.com");
+        let mut message_ids = HashSet::new();
+        for recipient in [
+            "to1@test.com",
+            "to2@test.com",
+            "cc@test.com",
+            "bcc@test.com",
+        ] {
+            let deliveries = traits
+                .delivery_service
+                .list_deliveries(Some(recipient.to_string()), None, None, None)
+                .await?;
+            assert_eq!(deliveries.count, 1);
+            message_ids.insert(deliveries.deliveries[0].message_id.clone());
+        }
+        assert_eq!(message_ids.len(), 4);
+
+        let forbidden_header = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["to1@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    headers: vec![("From".to_string(), "spoof@test.com".to_string())],
+                    ..content.clone()
+                },
+            )
+            .await;
+        let injected_header = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["to1@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    headers: vec![(
+                        "References".to_string(),
+                        "<first@test.com>\r\nBcc: spy@test.com".to_string(),
+                    )],
+                    ..content.clone()
+                },
+            )
+            .await;
+        let invalid_cc = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["to1@test.com".to_string()],
+                    cc: vec!["not an address".to_string()],
+                    ..Default::default()
+                },
+                content.clone(),
+            )
+            .await;
+        let no_recipient = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    cc: vec!["cc@test.com".to_string()],
+                    ..Default::default()
+                },
+                content,
+            )
+            .await;
+
+        assert!(matches!(forbidden_header, Err(ServiceError::BadRequest(_))));
+        assert!(matches!(injected_header, Err(ServiceError::BadRequest(_))));
+        assert!(matches!(invalid_cc, Err(ServiceError::BadRequest(_))));
+        assert!(matches!(no_recipient, Err(ServiceError::BadRequest(_))));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore]
+    async fn blast_email_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        let group = traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let sub1_email = "sub1_email";
+        traits
+            .subscriber_repository
+            .add_subscriber(sub1_email, &group)
+            .await?;
+        let sub2_email = "sub2_email";
+        traits
+            .subscriber_repository
+            .add_subscriber(sub2_email, &group)
+            .await?;
+
+        let subs_list = traits
+            .subscriber_repository
+            .list_subs_by_group(&group, None, None)
+            .await?;
+        let email_list = subs_list
+            .iter()
+            .map(|entity| entity.email.clone())
+            .collect::<Vec<String>>();
+
+        traits
+            .email_service
+            .blast_email(
+                email_list,
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn concurrent_blast_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+        let config = Arc::new(AppConfig {
+            outbox_workers: 2,
+            ..AppConfig::parse()
+        });
+        let email_service = EmailService::new(
+            &config,
+            Arc::new(EmailValidator::default()),
+            traits.delivery_repository.clone(),
+            traits.suppression_repository.clone(),
+            Arc::new(SenderIdentityRepository::new(pool.clone())),
+            Arc::new(TemplateRepository::new(pool)),
+            traits.subscriber_repository.clone(),
+            traits.unsubscription_repository.clone(),
+            Arc::new(MarkdownRenderer::default()),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let mut addresses = (0..6)
+            .map(|index| format!("reader{}@test.com", index))
+            .collect::<Vec<String>>();
+        addresses.push("not an address".to_string());
+        // The password reset is sent while the blast keeps both workers busy,
+        // and every blast recipient still gets exactly one delivery.
+        let (blasted, reset) = tokio::join!(
+            email_service.blast_email(
+                addresses.clone(),
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    message_class: MessageClass::Marketing,
+                    ..Default::default()
+                },
+                None,
+            ),
+            email_service.send_email(
+                EmailRecipients {
+                    to: vec!["reader0@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    subject: "reset your password".to_string(),
+                    text_body: "this is a test".to_string(),
+                    message_class: MessageClass::Transactional,
+                    ..Default::default()
+                },
+            ),
+        );
+        let mut blast_deliveries = Vec::new();
+        for address in &addresses[..6] {
+            blast_deliveries.extend(
+                traits
+                    .delivery_repository
+                    .list_deliveries(Some(address.clone()), None, None, None)
+                    .await?
+                    .into_iter()
+                    .filter(|delivery| delivery.subject == "hello"),
+            );
+        }
+
+        assert!(matches!(
+            blasted,
+            Err(ServiceError::InternalServerErrorWithContext(message))
+                if message == "Blasting email failed for 1 of 7 recipients"
+        ));
+        assert!(!reset?.is_empty());
+        assert_eq!(blast_deliveries.len(), 6);
+        assert!(blast_deliveries
+            .iter()
+            .all(|delivery| delivery.status == "sent"));
+        assert_eq!(
+            blast_deliveries
+                .iter()
+                .map(|delivery| delivery.recipient.clone())
+                .collect::<HashSet<String>>()
+                .len(),
+            6
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn sender_identity_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group_name = "group_name";
+        traits
+            .group_repository
+            .add_group(group_name, "group_description")
+            .await?;
+
+        let identity = traits
+            .identity_service
+            .add_identity(NewSenderIdentity {
+                name: "support".to_string(),
+                display_name: "Support".to_string(),
+                address: "support@email.com".to_string(),
+                reply_to: Some("Help Desk <help@email.com>".to_string()),
+                smtp_username: None,
+                smtp_password: None,
+            })
+            .await?;
+        assert_eq!(identity.address, "support@email.com");
+        assert!(!identity.has_credentials);
+
+        let duplicate = traits
+            .identity_service
+            .add_identity(NewSenderIdentity {
+                name: "support".to_string(),
+                display_name: String::new(),
+                address: "other@email.com".to_string(),
+                reply_to: None,
+                smtp_username: None,
+                smtp_password: None,
+            })
+            .await;
+        assert!(matches!(duplicate, Err(ServiceError::ObjectConflict(_))));
+
+        let half_credentials = traits
+            .identity_service
+            .add_identity(NewSenderIdentity {
+                name: "billing".to_string(),
+                display_name: String::new(),
+                address: "billing@email.com".to_string(),
+                reply_to: None,
+                smtp_username: Some("billing".to_string()),
+                smtp_password: None,
+            })
+            .await;
+        assert!(matches!(half_credentials, Err(ServiceError::BadRequest(_))));
+
+        traits
+            .identity_service
+            .set_group_identity(group_name.to_string(), Some("support".to_string()))
+            .await?;
+        let unknown_identity = traits
+            .identity_service
+            .set_group_identity(group_name.to_string(), Some("unknown".to_string()))
+            .await;
+        assert!(matches!(
+            unknown_identity,
+            Err(ServiceError::ObjectConflict(_))
+        ));
+
+        let unknown_campaign_identity = traits
+            .campaign_service
+            .create_campaign(
+                NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: "unknown".to_string(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec![group_name.to_string()],
+            )
+            .await;
+        assert!(matches!(
+            unknown_campaign_identity,
+            Err(ServiceError::ObjectConflict(_))
+        ));
+
+        let message_id = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["email@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    identity: Some("support".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let delivery = traits
+            .delivery_service
+            .get_message_status(message_id)
+            .await?;
+        assert_eq!(delivery.status, "sent");
+
+        let unknown_send_identity = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["email@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    identity: Some("unknown".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(
+            unknown_send_identity,
+            Err(ServiceError::ObjectConflict(_))
+        ));
+
+        let identities = traits.identity_service.list_identities().await?;
+        assert_eq!(identities.identities.len(), 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn footer_unsubscribe_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        let newsletter = traits
+            .group_repository
+            .add_group("newsletter", "group_description")
+            .await?;
+        let announcements = traits
+            .group_repository
+            .add_group("announcements", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub@email.com", &newsletter)
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("sub@email.com", &announcements)
+            .await?;
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                &[newsletter],
+            )
+            .await?;
+        let delivery = traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: "<footed_message@email.com>".to_string(),
+                recipient: "sub@email.com".to_string(),
+                subject: "subject".to_string(),
+                campaign_id: Some(campaign.id),
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+
+        let unsubscribe_url = traits.tracker.unsubscribe_url(delivery.id);
+        let signature = unsubscribe_url.rsplit('/').next().unwrap();
+        let forged = traits
+            .tracking_service
+            .unsubscribe(delivery.id + 1, signature.to_string())
+            .await;
+        let removed = traits
+            .tracking_service
+            .unsubscribe(delivery.id, signature.to_string())
+            .await?;
+
+        let groups = traits
+            .group_repository
+            .list_groups_by_sub("sub@email.com", None, None)
+            .await?;
+        let unsubscriptions: Vec<Option<i64>> =
+            sqlx::query_scalar("select delivery_id from unsubscription where email = $1")
+                .bind("sub@email.com")
+                .fetch_all(&pool)
+                .await?;
+
+        assert!(matches!(forged, Err(ServiceError::BadRequest(_))));
+        assert_eq!(removed, 1);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups.first().unwrap().name, "announcements");
+        assert_eq!(unsubscriptions.len(), 1);
+        assert_eq!(unsubscriptions.first().unwrap(), &Some(delivery.id));
+
+        let blast_without_footer = traits
+            .email_service
+            .blast_email(
+                vec!["sub@email.com".to_string()],
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    without_footer: true,
+                    ..Default::default()
+                },
+                None,
+            )
+            .await;
+        assert!(matches!(
+            blast_without_footer,
+            Err(ServiceError::BadRequest(_))
+        ));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn localized_template_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        for (locale, subject) in [
+            ("en", "Welcome"),
+            ("ms", "Selamat datang"),
+            ("zh", "欢迎光临"),
+        ] {
+            traits
+                .template_service
+                .save_template(NewTemplate {
+                    name: "welcome".to_string(),
+                    locale: locale.to_string(),
+                    subject: subject.to_string(),
+                    text_body: format!("{} to the newsletter", subject),
+                    html_body: String::new(),
+                })
+                .await?;
+        }
+        let invalid_locale = traits
+            .template_service
+            .save_template(NewTemplate {
+                name: "welcome".to_string(),
+                locale: "malay".to_string(),
+                subject: "Selamat datang".to_string(),
+                text_body: "Selamat datang".to_string(),
+                html_body: String::new(),
+            })
+            .await;
+        let group = traits
+            .group_repository
+            .add_group("newsletter", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[NewSubscriber {
+                    email: "malay@test.com".to_string(),
+                    attributes: serde_json::json!({ "locale": "ms-MY" }),
+                }],
+                &group,
+            )
+            .await?;
+
+        let mut subjects = Vec::new();
+        for (to, locale) in [
+            ("malay@test.com", None),
+            ("malay@test.com", Some("zh-Hans-CN")),
+            ("french@test.com", Some("fr-FR")),
+        ] {
+            let message_id = traits
+                .email_service
+                .send_email(
+                    EmailRecipients {
+                        to: vec![to.to_string()],
+                        ..Default::default()
+                    },
+                    EmailContent {
+                        template: Some("welcome".to_string()),
+                        locale: locale.map(str::to_string),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            let delivery = traits
+                .delivery_service
+                .get_message_status(message_id)
+                .await?;
+            subjects.push(delivery.subject);
+        }
+        let unknown_template = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["malay@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    template: Some("unknown".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        let templates = traits
+            .template_service
+            .list_templates(Some("welcome".to_string()))
+            .await?;
+
+        assert!(matches!(invalid_locale, Err(ServiceError::BadRequest(_))));
+        assert_eq!(subjects, vec!["Selamat datang", "欢迎光临", "Welcome"]);
+        assert!(matches!(
+            unknown_template,
+            Err(ServiceError::ObjectConflict(_))
+        ));
+        assert_eq!(templates.templates.len(), 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn markdown_email_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let message_id = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["email@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    subject: "Release notes".to_string(),
+                    markdown_body: Some("# Release notes\n\n- faster blasts".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let delivery = traits
+            .delivery_service
+            .get_message_status(message_id)
+            .await?;
+
+        let markdown_with_html = traits
+            .email_service
+            .send_email(
+                EmailRecipients {
+                    to: vec!["email@test.com".to_string()],
+                    ..Default::default()
+                },
+                EmailContent {
+                    subject: "Release notes".to_string(),
+                    html_body: Some("<p>Release notes</p>".to_string()),
+                    markdown_body: Some("# Release notes".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        let campaign_with_both = traits
+            .campaign_service
+            .create_campaign(
+                NewCampaign {
+                    name: "campaign_name".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: String::new(),
+                    html_body: "<p>body</p>".to_string(),
+                    markdown_body: "body".to_string(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec!["group_name".to_string()],
+            )
+            .await;
+
+        assert_eq!(delivery.status, "sent");
+        assert!(matches!(
+            markdown_with_html,
+            Err(ServiceError::BadRequest(_))
+        ));
+        assert!(matches!(
+            campaign_with_both,
+            Err(ServiceError::BadRequest(_))
+        ));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn preview_email_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("newsletter", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[NewSubscriber {
+                    email: "reader@test.com".to_string(),
+                    attributes: serde_json::json!({ "name": "Aisyah" }),
+                }],
+                &group,
+            )
+            .await?;
+        let campaign = NewCampaign {
+            name: "campaign_name".to_string(),
+            subject: "Hello {{name}}".to_string(),
+            text_body: "Hi {{name|there}}, this is a test".to_string(),
+            html_body: String::new(),
+            markdown_body: String::new(),
+            sender: String::new(),
+            identity: String::new(),
+            template: String::new(),
+            locale: String::new(),
+            local_send_time: String::new(),
+            message_class: MessageClass::Marketing,
+        };
+
+        let preview = traits
+            .campaign_service
+            .preview_email(
+                campaign.clone(),
+                vec!["newsletter".to_string()],
+                "reader@test.com".to_string(),
+            )
+            .await?;
+        let stranger_preview = traits
+            .campaign_service
+            .preview_email(
+                campaign,
+                vec!["newsletter".to_string()],
+                "stranger@test.com".to_string(),
+            )
+            .await?;
+        let deliveries = traits
+            .delivery_repository
+            .get_deliveries_count(None, None)
+            .await?;
+
+        assert_eq!(preview.recipient, "reader@test.com");
+        assert_eq!(preview.subject, "Hello Aisyah");
+        assert!(preview.text_body.starts_with("Hi Aisyah, this is a test"));
+        assert!(preview.text_body.contains("/unsubscribe/"));
+        assert!(preview.raw_message.contains("To: reader@test.com"));
+        assert!(preview.raw_message.contains("List-Unsubscribe: "));
+        assert!(stranger_preview.text_body.starts_with("Hi there, "));
+        assert_eq!(deliveries, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_blast_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("newsletter", "group_description")
+            .await?;
+        traits
+            .group_repository
+            .add_group("announcements", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[NewSubscriber {
+                    email: "reader@test.com".to_string(),
+                    attributes: serde_json::json!({ "name": "Aisyah" }),
+                }],
+                &group,
+            )
+            .await?;
+        let invalid_seeds = traits
+            .group_service
+            .set_group_seeds("newsletter".to_string(), vec!["not an email".to_string()])
+            .await;
+        traits
+            .group_service
+            .set_group_seeds(
+                "newsletter".to_string(),
+                vec!["reviewer@test.com".to_string()],
+            )
+            .await?;
+        let campaign = NewCampaign {
+            name: "campaign_name".to_string(),
+            subject: "Hello {{name}}".to_string(),
+            text_body: "Hi {{name}}, this is a test".to_string(),
+            html_body: String::new(),
+            markdown_body: String::new(),
+            sender: String::new(),
+            identity: String::new(),
+            template: String::new(),
+            locale: String::new(),
+            local_send_time: String::new(),
+            message_class: MessageClass::Marketing,
+        };
+
+        let test_blast = traits
+            .campaign_service
+            .test_blast(
+                campaign.clone(),
+                vec!["newsletter".to_string()],
+                "reader@test.com".to_string(),
+            )
+            .await?;
+        let without_seeds = traits
+            .campaign_service
+            .test_blast(
+                campaign,
+                vec!["announcements".to_string()],
+                "reader@test.com".to_string(),
+            )
+            .await;
+        let seed_deliveries = traits
+            .delivery_repository
+            .list_deliveries(Some("reviewer@test.com".to_string()), None, None, None)
+            .await?;
+        let sample_deliveries = traits
+            .delivery_repository
+            .get_deliveries_count(Some("reader@test.com".to_string()), None)
+            .await?;
+
+        assert!(matches!(invalid_seeds, Err(ServiceError::BadRequest(_))));
+        assert_eq!(test_blast.seeds, vec!["reviewer@test.com"]);
+        assert_eq!(seed_deliveries.len(), 1);
+        assert_eq!(
+            seed_deliveries.first().unwrap().subject,
+            "[TEST] Hello Aisyah"
+        );
+        assert!(seed_deliveries.first().unwrap().campaign_id.is_none());
+        assert_eq!(sample_deliveries, 0);
+        assert!(matches!(without_seeds, Err(ServiceError::BadRequest(_))));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn frequency_cap_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+        let config = Arc::new(AppConfig {
+            frequency_cap_daily: Some(1),
+            ..AppConfig::parse()
+        });
+        let capped_email_service = EmailService::new(
+            &config,
+            Arc::new(EmailValidator::default()),
+            traits.delivery_repository.clone(),
+            traits.suppression_repository.clone(),
+            Arc::new(SenderIdentityRepository::new(pool.clone())),
+            Arc::new(TemplateRepository::new(pool)),
+            traits.subscriber_repository.clone(),
+            traits.unsubscription_repository.clone(),
+            Arc::new(MarkdownRenderer::default()),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let mut campaign_ids = Vec::new();
+        for name in ["monday_campaign", "tuesday_campaign", "wednesday_campaign"] {
+            let campaign = traits
+                .campaign_repository
+                .add_campaign(
+                    &NewCampaign {
+                        name: name.to_string(),
+                        subject: "subject".to_string(),
+                        text_body: "text body".to_string(),
+                        html_body: String::new(),
+                        markdown_body: String::new(),
+                        sender: String::new(),
+                        identity: String::new(),
+                        template: String::new(),
+                        locale: String::new(),
+                        local_send_time: String::new(),
+                        message_class: MessageClass::Marketing,
+                    },
+                    &[],
+                )
+                .await?;
+            campaign_ids.push(campaign.id);
+        }
+        let earlier_delivery = traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: "<earlier_message@email.com>".to_string(),
+                recipient: "Busy@test.com".to_string(),
+                subject: "subject".to_string(),
+                campaign_id: Some(campaign_ids[0]),
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+        traits
+            .delivery_repository
+            .update_delivery_status(earlier_delivery.id, DeliveryStatus::Sent, "250 OK")
+            .await?;
+
+        capped_email_service
+            .blast_email(
+                vec!["busy@test.com".to_string(), "quiet@test.com".to_string()],
+                EmailContent {
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    ..Default::default()
+                },
+                Some(campaign_ids[1]),
+            )
+            .await?;
+        let busy_deliveries = traits
+            .delivery_repository
+            .list_deliveries(
+                Some("busy@test.com".to_string()),
+                Some(campaign_ids[1]),
+                None,
+                None,
+            )
+            .await?;
+        let engagement = traits
+            .tracking_service
+            .get_campaign_engagement(campaign_ids[1])
+            .await?;
+
+        assert_eq!(busy_deliveries.len(), 1);
+        assert_eq!(busy_deliveries.first().unwrap().status, "capped");
+        assert_eq!(engagement.sent, 1);
+        assert_eq!(engagement.capped, 1);
+
+        // Capped sends do not count towards the cap themselves.
+        let counts = traits
+            .delivery_repository
+            .count_campaign_deliveries_since(
+                &["busy@test.com".to_string()],
+                OffsetDateTime::now_utc() - Duration::days(1),
+                None,
+            )
+            .await?;
+        assert_eq!(counts.get("busy@test.com"), Some(&1));
+
+        // The cap is checked again when a queued delivery goes out, as the
+        // blast above reached the recipient after it was queued. Recipients
+        // suppressed in the meantime are skipped.
+        for recipient in ["quiet@test.com", "gone@test.com"] {
+            traits
+                .delivery_repository
+                .add_delivery(&NewDelivery {
+                    message_id: format!("<queued_{}>", recipient),
+                    recipient: recipient.to_string(),
+                    subject: "subject".to_string(),
+                    campaign_id: Some(campaign_ids[2]),
+                    verp_token: None,
+                    send_after: Some(OffsetDateTime::now_utc() - Duration::minutes(1)),
+                    message_class: MessageClass::Marketing,
+                })
+                .await?;
+        }
+        traits
+            .suppression_repository
+            .add_suppression(&NewSuppression {
+                email: "gone@test.com".to_string(),
+                reason: SuppressionReason::HardBounce,
+                detail: "550 5.1.1 user unknown".to_string(),
+                message_id: None,
+            })
+            .await?;
+        capped_email_service
+            .send_queued_deliveries(
+                campaign_ids[2],
+                EmailContent {
+                    subject: "subject".to_string(),
+                    text_body: "this is a test".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let queued_deliveries = traits
+            .delivery_repository
+            .list_deliveries(
+                Some("quiet@test.com".to_string()),
+                Some(campaign_ids[2]),
+                None,
+                None,
+            )
+            .await?;
+        let suppressed_deliveries = traits
+            .delivery_repository
+            .list_deliveries(
+                Some("gone@test.com".to_string()),
+                Some(campaign_ids[2]),
+                None,
+                None,
+            )
+            .await?;
+        assert_eq!(queued_deliveries.first().unwrap().status, "capped");
+        assert_eq!(suppressed_deliveries.first().unwrap().status, "skipped");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn local_send_time_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        let group = traits
+            .group_repository
+            .add_group("asia", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[NewSubscriber {
+                    email: "kl@test.com".to_string(),
+                    attributes: serde_json::json!({ "timezone": "Asia/Kuala_Lumpur" }),
+                }],
+                &group,
+            )
+            .await?;
+        // An hour ago in Kuala Lumpur, so the next one is nearly a day away.
+        let an_hour_ago = (OffsetDateTime::now_utc().to_timezone(db::asia::KUALA_LUMPUR)
+            - Duration::hours(1))
+        .time();
+        let local_send_time = format!("{:02}:{:02}", an_hour_ago.hour(), an_hour_ago.minute());
+        let new_campaign = |local_send_time: &str| NewCampaign {
+            name: "morning_campaign".to_string(),
+            subject: "good morning".to_string(),
+            text_body: "this is a test".to_string(),
+            html_body: String::new(),
+            markdown_body: String::new(),
+            sender: String::new(),
+            identity: String::new(),
+            template: String::new(),
+            locale: String::new(),
+            local_send_time: local_send_time.to_string(),
+            message_class: MessageClass::Marketing,
+        };
+
+        let invalid_campaign = traits
+            .campaign_service
+            .create_campaign(new_campaign("9am"), vec!["asia".to_string()])
+            .await;
+        let campaign = traits
+            .campaign_service
+            .create_campaign(new_campaign(&local_send_time), vec!["asia".to_string()])
+            .await?;
+        traits
+            .campaign_service
+            .send_campaign(campaign.id, None)
+            .await?;
+        let queued = traits
+            .delivery_repository
+            .list_deliveries(
+                Some("kl@test.com".to_string()),
+                Some(campaign.id),
+                None,
+                None,
+            )
+            .await?
+            .remove(0);
+        let queued_send_after: Option<OffsetDateTime> =
+            sqlx::query_scalar("select send_after from delivery where id = $1")
+                .bind(queued.id)
+                .fetch_one(&pool)
+                .await?;
+
+        traits
+            .delivery_repository
+            .defer_delivery(queued.id, OffsetDateTime::now_utc() - Duration::minutes(1))
+            .await?;
+        traits.campaign_service.send_due_campaigns().await?;
+        let sent = traits.delivery_repository.get_delivery(queued.id).await?;
+
+        assert!(matches!(invalid_campaign, Err(ServiceError::BadRequest(_))));
+        assert_eq!(campaign.local_send_time, local_send_time);
+        assert_eq!(queued.status, "queued");
+        assert!(queued_send_after.unwrap() > OffsetDateTime::now_utc() + Duration::hours(22));
+        assert_eq!(sent.unwrap().status, "sent");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn quiet_hours_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+        let now = OffsetDateTime::now_utc();
+        let (start, end) = (now - Duration::hours(1), now + Duration::hours(1));
+        let config = Arc::new(AppConfig {
+            quiet_hours_start: Some(format!("{:02}:{:02}", start.hour(), start.minute())),
+            quiet_hours_end: Some(format!("{:02}:{:02}", end.hour(), end.minute())),
+            ..AppConfig::parse()
+        });
+        let quiet_email_service = EmailService::new(
+            &config,
+            Arc::new(EmailValidator::default()),
+            traits.delivery_repository.clone(),
+            traits.suppression_repository.clone(),
+            Arc::new(SenderIdentityRepository::new(pool.clone())),
+            Arc::new(TemplateRepository::new(pool.clone())),
+            traits.subscriber_repository.clone(),
+            traits.unsubscription_repository.clone(),
+            Arc::new(MarkdownRenderer::default()),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let campaign = traits
+            .campaign_repository
+            .add_campaign(
+                &NewCampaign {
+                    name: "late_campaign".to_string(),
+                    subject: "subject".to_string(),
+                    text_body: "text body".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                &[],
+            )
+            .await?;
+        let content = EmailContent {
+            subject: "hello".to_string(),
+            text_body: "this is a test".to_string(),
+            ..Default::default()
+        };
+        quiet_email_service
+            .blast_email(
+                vec!["sleeping@test.com".to_string()],
+                content.clone(),
+                Some(campaign.id),
+            )
+            .await?;
+        let queued = traits
+            .delivery_repository
+            .list_deliveries(None, Some(campaign.id), None, None)
+            .await?
+            .remove(0);
+        let queued_send_after: Option<OffsetDateTime> =
+            sqlx::query_scalar("select send_after from delivery where id = $1")
+                .bind(queued.id)
+                .fetch_one(&pool)
+                .await?;
+
+        // Still within the quiet hours when the delivery comes due, so it is
+        // deferred again instead of sent.
+        traits
+            .delivery_repository
+            .defer_delivery(queued.id, now - Duration::minutes(1))
+            .await?;
+        quiet_email_service
+            .send_queued_deliveries(campaign.id, content.clone())
+            .await?;
+        let deferred = traits
+            .delivery_repository
+            .get_delivery(queued.id)
+            .await?
+            .unwrap();
+        let deferred_send_after: Option<OffsetDateTime> =
+            sqlx::query_scalar("select send_after from delivery where id = $1")
+                .bind(queued.id)
+                .fetch_one(&pool)
+                .await?;
+
+        // Without a campaign nothing can be queued, so it is not sent at all.
+        let without_campaign = quiet_email_service
+            .blast_email(vec!["insomniac@test.com".to_string()], content, None)
+            .await;
+        let without_campaign_deliveries = traits
+            .delivery_repository
+            .list_deliveries(Some("insomniac@test.com".to_string()), None, None, None)
+            .await?;
+
+        assert!(without_campaign.is_err());
+        assert_eq!(without_campaign_deliveries.len(), 1);
+        assert_eq!(without_campaign_deliveries[0].status, "failed");
+        for (delivery, send_after) in [
+            (&queued, queued_send_after),
+            (&deferred, deferred_send_after),
+        ] {
+            let send_after = send_after.unwrap();
+            assert_eq!(delivery.status, "queued");
+            assert!(send_after > now + Duration::minutes(58));
+            assert!(send_after <= now + Duration::hours(1));
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn digest_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+
+        traits
+            .template_service
+            .save_template(NewTemplate {
+                name: "activity".to_string(),
+                locale: "en".to_string(),
+                subject: "{{item_count}} updates for {{name|you}}".to_string(),
+                text_body: "What happened:\n{{items}}".to_string(),
+                html_body: "<p>Hi {{name}}</p>{{items}}".to_string(),
+            })
+            .await?;
+        let group = traits
+            .group_repository
+            .add_group("members", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[NewSubscriber {
+                    email: "aminah@test.com".to_string(),
+                    attributes: serde_json::json!({ "name": "Aminah" }),
+                }],
+                &group,
+            )
+            .await?;
+
+        let unknown_cadence = traits
+            .digest_service
+            .save_digest_type(
+                "activity".to_string(),
+                "weekly".to_string(),
+                "activity".to_string(),
+            )
+            .await;
+        let unknown_template = traits
+            .digest_service
+            .save_digest_type(
+                "activity".to_string(),
+                "hourly".to_string(),
+                "missing".to_string(),
+            )
+            .await;
+        traits
+            .digest_service
+            .save_digest_type(
+                "activity".to_string(),
+                "hourly".to_string(),
+                "activity".to_string(),
+            )
+            .await?;
+        for title in ["Hakim followed you", "Mei Ling liked your post"] {
+            traits
+                .digest_service
+                .enqueue_digest_item(
+                    "activity".to_string(),
+                    NewDigestItem {
+                        recipient: " aminah@test.com ".to_string(),
+                        title: title.to_string(),
+                        body: String::new(),
+                    },
+                )
+                .await?;
+        }
+        let unknown_digest_type = traits
+            .digest_service
+            .enqueue_digest_item(
+                "billing".to_string(),
+                NewDigestItem {
+                    recipient: "aminah@test.com".to_string(),
+                    title: "Invoice ready".to_string(),
+                    body: String::new(),
+                },
+            )
+            .await;
+
+        // Nothing is due before the oldest item has waited an hour.
+        traits.digest_service.send_due_digests().await?;
+        let digest_type = traits
+            .digest_repository
+            .get_digest_type("activity")
+            .await?
+            .unwrap();
+        let pending = traits
+            .digest_repository
+            .claim_pending_items(digest_type.id, "aminah@test.com")
+            .await?;
+        assert_eq!(pending.len(), 2);
+
+        let ids = pending.iter().map(|item| item.id).collect::<Vec<i64>>();
+        let message_id = traits
+            .email_service
+            .send_digest("aminah@test.com".to_string(), digest_type.template, pending)
+            .await?;
+        traits
+            .digest_repository
+            .update_items_status(&ids, DigestItemStatus::Sent, Some(message_id.clone()))
+            .await?;
+        let delivery = traits
+            .delivery_service
+            .get_message_status(message_id)
+            .await?;
+
+        assert!(matches!(unknown_cadence, Err(ServiceError::BadRequest(_))));
+        assert!(matches!(
+            unknown_template,
+            Err(ServiceError::ObjectConflict(_))
+        ));
+        assert!(matches!(
+            unknown_digest_type,
+            Err(ServiceError::ObjectConflict(_))
+        ));
+        assert_eq!(delivery.subject, "2 updates for Aminah");
+        assert_eq!(delivery.status, "sent");
+
+        // A digest that failed to send is released to pending for the next
+        // tick, unless it could never be sent.
+        for recipient in ["unreachable@test.com", "gone@test.com"] {
+            traits
+                .digest_service
+                .enqueue_digest_item(
+                    "activity".to_string(),
+                    NewDigestItem {
+                        recipient: recipient.to_string(),
+                        title: "Hakim followed you".to_string(),
+                        body: String::new(),
+                    },
+                )
+                .await?;
+        }
+        sqlx::query("update digest_item set created_at = created_at - interval '2 hours'")
+            .execute(&pool)
+            .await?;
+        let mut email_service = MockEmailServiceTrait::new();
+        email_service
+            .expect_send_digest()
+            .times(2)
+            .returning(|recipient, _, _| match recipient.as_str() {
+                "unreachable@test.com" => Err(ServiceError::InternalServerErrorWithContext(
+                    "Sending email failed".to_string(),
+                )),
+                "gone@test.com" => Err(ServiceError::BadRequest(
+                    "Recipient address is suppressed".to_string(),
+                )),
+                _ => Ok("<digest_message@email.com>".to_string()),
+            });
+        let digest_service = DigestService::new(
+            traits.digest_repository.clone(),
+            Arc::new(TemplateRepository::new(pool.clone())),
+            Arc::new(EmailValidator::default()),
+            Arc::new(email_service),
+        );
+        digest_service.send_due_digests().await?;
+        let mut statuses = Vec::new();
+        for recipient in ["aminah@test.com", "unreachable@test.com", "gone@test.com"] {
+            let status: String =
+                sqlx::query_scalar("select status from digest_item where recipient = $1 limit 1")
+                    .bind(recipient)
+                    .fetch_one(&pool)
+                    .await?;
+            statuses.push(status);
+        }
+
+        assert_eq!(statuses, vec!["sent", "pending", "failed"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn message_class_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+        let now = OffsetDateTime::now_utc();
+        let (start, end) = (now - Duration::hours(1), now + Duration::hours(1));
+        let config = Arc::new(AppConfig {
+            quiet_hours_start: Some(format!("{:02}:{:02}", start.hour(), start.minute())),
+            quiet_hours_end: Some(format!("{:02}:{:02}", end.hour(), end.minute())),
+            transactional_rate_limit_per_second: Some(100),
+            ..AppConfig::parse()
+        });
+        let quiet_email_service = EmailService::new(
+            &config,
+            Arc::new(EmailValidator::default()),
+            traits.delivery_repository.clone(),
+            traits.suppression_repository.clone(),
+            Arc::new(SenderIdentityRepository::new(pool.clone())),
+            Arc::new(TemplateRepository::new(pool)),
+            traits.subscriber_repository.clone(),
+            traits.unsubscription_repository.clone(),
+            Arc::new(MarkdownRenderer::default()),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let group = traits
+            .group_repository
+            .add_group("newsletter", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscriber("gone@test.com", &group)
+            .await?;
+        let delivery = traits
+            .delivery_repository
+            .add_delivery(&NewDelivery {
+                message_id: "<newsletter_message@email.com>".to_string(),
+                recipient: "gone@test.com".to_string(),
+                subject: "subject".to_string(),
+                campaign_id: None,
+                verp_token: None,
+                send_after: None,
+                message_class: MessageClass::Marketing,
+            })
+            .await?;
+        traits
+            .unsubscription_repository
+            .unsubscribe("gone@test.com", delivery.id, None)
+            .await?;
+
+        let content = |message_class: MessageClass| EmailContent {
+            subject: "hello".to_string(),
+            text_body: "this is a test".to_string(),
+            message_class,
+            ..Default::default()
+        };
+        let recipients = |address: &str| EmailRecipients {
+            to: vec![address.to_string()],
+            ..Default::default()
+        };
+        let opted_out_marketing = traits
+            .email_service
+            .send_email(
+                recipients("gone@test.com"),
+                content(MessageClass::Marketing),
+            )
+            .await;
+        let opted_out_transactional = traits
+            .email_service
+            .send_email(
+                recipients("gone@test.com"),
+                content(MessageClass::Transactional),
+            )
+            .await?;
+        let marketing_without_footer = traits
+            .email_service
+            .send_email(
+                recipients("reader@test.com"),
+                EmailContent {
+                    without_footer: true,
+                    ..content(MessageClass::Marketing)
+                },
+            )
+            .await;
+        let quiet_marketing = quiet_email_service
+            .send_email(
+                recipients("reader@test.com"),
+                content(MessageClass::Marketing),
+            )
+            .await;
+        let quiet_transactional = quiet_email_service
+            .send_email(
+                recipients("reader@test.com"),
+                content(MessageClass::Transactional),
+            )
+            .await?;
+        for message_class in [MessageClass::Marketing, MessageClass::Transactional] {
+            traits
+                .email_service
+                .blast_email(
+                    vec!["gone@test.com".to_string(), "reader@test.com".to_string()],
+                    content(message_class),
+                    None,
+                )
+                .await?;
+        }
+        let gone_blasts = traits
+            .delivery_repository
+            .list_deliveries(Some("gone@test.com".to_string()), None, None, None)
+            .await?
+            .into_iter()
+            .filter(|delivery| delivery.subject == "hello")
+            .collect::<Vec<_>>();
+        let reader_blasts = traits
+            .delivery_repository
+            .list_deliveries(Some("reader@test.com".to_string()), None, None, None)
+            .await?;
+        let transactional_delivery = traits
+            .delivery_repository
+            .get_delivery_by_message_id(&opted_out_transactional)
+            .await?
+            .unwrap();
+
+        assert!(matches!(
+            opted_out_marketing,
+            Err(ServiceError::BadRequest(_))
+        ));
+        assert!(matches!(
+            marketing_without_footer,
+            Err(ServiceError::BadRequest(_))
+        ));
+        assert!(matches!(quiet_marketing, Err(ServiceError::BadRequest(_))));
+        assert!(!quiet_transactional.is_empty());
+        assert_eq!(transactional_delivery.message_class, "transactional");
+        // Only the transactional blast reaches the opted out address, on top
+        // of the transactional email sent to it directly, the marketing one
+        // records it as skipped.
+        assert_eq!(gone_blasts.len(), 3);
+        assert!(gone_blasts
+            .iter()
+            .all(|delivery| (delivery.message_class == "transactional")
+                != (delivery.status == "skipped")));
+        assert_eq!(reader_blasts.len(), 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn cancel_blast_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool);
+
+        let group = traits
+            .group_repository
+            .add_group("asia", "group_description")
+            .await?;
+        traits
+            .subscriber_repository
+            .add_subscribers(
+                &[NewSubscriber {
+                    email: "kl@test.com".to_string(),
+                    attributes: serde_json::json!({ "timezone": "Asia/Kuala_Lumpur" }),
+                }],
+                &group,
+            )
+            .await?;
+        // An hour ago in Kuala Lumpur, so the delivery waits nearly a day.
+        let an_hour_ago = (OffsetDateTime::now_utc().to_timezone(db::asia::KUALA_LUMPUR)
+            - Duration::hours(1))
+        .time();
+        let campaign = traits
+            .campaign_service
+            .create_campaign(
+                NewCampaign {
+                    name: "morning_campaign".to_string(),
+                    subject: "good morning".to_string(),
+                    text_body: "this is a test".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: format!(
+                        "{:02}:{:02}",
+                        an_hour_ago.hour(),
+                        an_hour_ago.minute()
+                    ),
+                    message_class: MessageClass::Marketing,
+                },
+                vec!["asia".to_string()],
+            )
+            .await?;
+
+        let unsent_watch = traits.campaign_service.watch_blast(campaign.id).await;
+        traits
+            .campaign_service
+            .send_campaign(campaign.id, None)
+            .await?;
+        let sent_progress = traits
+            .campaign_service
+            .watch_blast(campaign.id)
+            .await?
+            .collect::<Vec<_>>()
+            .await;
+        let cancelled = traits.campaign_service.cancel_blast(campaign.id).await?;
+        let cancelled_progress = traits
+            .campaign_service
+            .watch_blast(campaign.id)
+            .await?
+            .collect::<Vec<_>>()
+            .await;
+        let cancelled_again = traits.campaign_service.cancel_blast(campaign.id).await;
+        let delivery = traits
+            .delivery_repository
+            .list_deliveries(
+                Some("kl@test.com".to_string()),
+                Some(campaign.id),
+                None,
+                None,
+            )
+            .await?
+            .remove(0);
+
+        assert!(matches!(unsent_watch, Err(ServiceError::ObjectConflict(_))));
+        assert_eq!(sent_progress.len(), 1);
+        assert_eq!(sent_progress[0].total, 1);
+        assert_eq!(sent_progress[0].queued, 1);
+        assert!(sent_progress[0].finished);
+        assert!(!sent_progress[0].cancelled);
+        assert_eq!(cancelled.status, "cancelled");
+        assert_eq!(cancelled_progress[0].queued, 0);
+        assert_eq!(cancelled_progress[0].skipped, 1);
+        assert!(cancelled_progress[0].cancelled);
+        assert!(matches!(
+            cancelled_again,
+            Err(ServiceError::ObjectConflict(_))
+        ));
+        assert_eq!(delivery.status, "cancelled");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn watch_blast_elsewhere_test(pool: PgPool) -> anyhow::Result<()> {
+        let traits = initialize_handler(pool.clone());
+        // Another instance shares the database, but not the blasts it runs.
+        let config = Arc::new(AppConfig::parse());
+        let other_email_service = EmailService::new(
+            &config,
+            Arc::new(EmailValidator::default()),
+            traits.delivery_repository.clone(),
+            traits.suppression_repository.clone(),
+            Arc::new(SenderIdentityRepository::new(pool.clone())),
+            Arc::new(TemplateRepository::new(pool.clone())),
+            traits.subscriber_repository.clone(),
+            traits.unsubscription_repository.clone(),
+            Arc::new(MarkdownRenderer::default()),
+            None,
+            None,
+            None,
+            None,
+        );
+        let other_campaign_service = CampaignService::new(
+            traits.campaign_repository.clone(),
+            traits.group_repository.clone(),
+            Arc::new(SenderIdentityRepository::new(pool.clone())),
+            Arc::new(TemplateRepository::new(pool)),
+            Arc::new(other_email_service),
+            config.test_blast_subject_prefix.clone(),
+        );
+
+        let group = traits
+            .group_repository
+            .add_group("readers", "group_description")
+            .await?;
+        for email in ["reader@test.com", "bounced@test.com", "not an address"] {
+            traits
+                .subscriber_repository
+                .add_subscriber(email, &group)
+                .await?;
+        }
+        traits
+            .suppression_repository
+            .add_suppression(&NewSuppression {
+                email: "bounced@test.com".to_string(),
+                reason: SuppressionReason::HardBounce,
+                detail: "550 mailbox unavailable".to_string(),
+                message_id: None,
+            })
+            .await?;
+        let campaign = traits
+            .campaign_service
+            .create_campaign(
+                NewCampaign {
+                    name: "newsletter".to_string(),
+                    subject: "hello".to_string(),
+                    text_body: "this is a test".to_string(),
+                    html_body: String::new(),
+                    markdown_body: String::new(),
+                    sender: String::new(),
+                    identity: String::new(),
+                    template: String::new(),
+                    locale: String::new(),
+                    local_send_time: String::new(),
+                    message_class: MessageClass::Marketing,
+                },
+                vec!["readers".to_string()],
+            )
+            .await?;
+
+        let started = traits.campaign_service.start_campaign(campaign.id).await?;
+        let here = traits
+            .campaign_service
+            .watch_blast(campaign.id)
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .pop()
+            .unwrap();
+        let elsewhere = other_campaign_service
+            .watch_blast(campaign.id)
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .pop()
+            .unwrap();
+        let counts = |progress: &BlastProgressResponse| {
+            (
+                progress.total,
+                progress.queued,
+                progress.sent,
+                progress.failed,
+                progress.skipped,
+                progress.finished,
+            )
+        };
+
+        assert_eq!(started.status, "sending");
+        assert_eq!(counts(&here), (3, 0, 1, 1, 1, true));
+        assert_eq!(counts(&elsewhere), counts(&here));
+
         Ok(())
     }
 }
@@ -0,0 +1,235 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::repository::delivery::MessageClass;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessagePriority {
+    High,
+    Normal,
+    Low,
+}
+
+impl MessagePriority {
+    const LEVELS: usize = 3;
+
+    /// Priority of a message that was not given one: transactional email
+    /// jumps ahead of marketing email.
+    pub fn of_class(message_class: MessageClass) -> Self {
+        match message_class {
+            MessageClass::Transactional => MessagePriority::High,
+            MessageClass::Marketing => MessagePriority::Low,
+        }
+    }
+
+    fn level(&self) -> usize {
+        match self {
+            MessagePriority::High => 0,
+            MessagePriority::Normal => 1,
+            MessagePriority::Low => 2,
+        }
+    }
+}
+
+/// One queue per priority, drained highest first, except that low priority
+/// gets at least `low_share` percent of the turns taken while it waits.
+struct PriorityQueues<T> {
+    queues: [VecDeque<T>; MessagePriority::LEVELS],
+    low_share: u64,
+    turns: u64,
+    low_turns: u64,
+}
+
+impl<T> PriorityQueues<T> {
+    fn new(low_share: u32) -> Self {
+        Self {
+            queues: Default::default(),
+            low_share: u64::from(low_share.min(100)),
+            turns: 0,
+            low_turns: 0,
+        }
+    }
+
+    fn push(&mut self, priority: MessagePriority, item: T) {
+        self.queues[priority.level()].push_back(item);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let low = MessagePriority::Low.level();
+        // Only turns taken while low priority waits count towards its share,
+        // otherwise it would make up for an idle spell with a burst.
+        if self.queues[low].is_empty() {
+            self.turns = 0;
+            self.low_turns = 0;
+        }
+        let low_owed = !self.queues[low].is_empty()
+            && (self.low_turns + 1) * 100 <= (self.turns + 1) * self.low_share;
+        let level = if low_owed {
+            low
+        } else {
+            self.queues.iter().position(|queue| !queue.is_empty())?
+        };
+
+        self.turns += 1;
+        if level == low {
+            self.low_turns += 1;
+        }
+        self.queues[level].pop_front()
+    }
+}
+
+struct OutboxState {
+    idle_workers: usize,
+    waiting: PriorityQueues<oneshot::Sender<()>>,
+}
+
+/// Hands outbound messages to a fixed number of workers relaying them. When
+/// every worker is busy, messages wait in per-priority queues so that a
+/// password reset is not stuck behind a large blast, while the blast keeps
+/// its minimum share of the workers.
+pub struct Outbox {
+    state: Mutex<OutboxState>,
+}
+
+/// A worker taken from the outbox, handed to the next waiting message when
+/// dropped.
+pub struct OutboxWorker<'a> {
+    outbox: &'a Outbox,
+}
+
+impl Drop for OutboxWorker<'_> {
+    fn drop(&mut self) {
+        self.outbox.release();
+    }
+}
+
+/// A message waiting for a worker. Should it give up after a worker was
+/// already handed to it, the worker goes to the next message in line.
+struct Waiting<'a> {
+    outbox: &'a Outbox,
+    receiver: oneshot::Receiver<()>,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.receiver.close();
+        if self.receiver.try_recv().is_ok() {
+            self.outbox.release();
+        }
+    }
+}
+
+impl Outbox {
+    pub fn new(workers: usize, low_share: u32) -> Self {
+        Self {
+            state: Mutex::new(OutboxState {
+                idle_workers: workers.max(1),
+                waiting: PriorityQueues::new(low_share),
+            }),
+        }
+    }
+
+    /// Waits for a worker to relay a message of the given priority.
+    pub async fn acquire(&self, priority: MessagePriority) -> OutboxWorker<'_> {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if state.idle_workers > 0 {
+                state.idle_workers -= 1;
+                return OutboxWorker { outbox: self };
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.waiting.push(priority, sender);
+            receiver
+        };
+
+        let mut waiting = Waiting {
+            outbox: self,
+            receiver,
+        };
+        // The sender is only dropped unsent by a release that skips a message
+        // which gave up waiting, so this resolves once a worker is handed over.
+        let _ = (&mut waiting.receiver).await;
+        OutboxWorker { outbox: self }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(sender) = state.waiting.pop() {
+            if sender.send(()).is_ok() {
+                return;
+            }
+        }
+        state.idle_workers += 1;
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::sync::Mutex;
+
+    use super::{MessagePriority, Outbox, PriorityQueues};
+
+    #[test]
+    fn priority_queues_test() {
+        let mut queues = PriorityQueues::new(25);
+        for item in 0..3 {
+            queues.push(MessagePriority::Low, format!("low {}", item));
+        }
+        for item in 0..6 {
+            queues.push(MessagePriority::High, format!("high {}", item));
+        }
+        queues.push(MessagePriority::Normal, "normal 0".to_string());
+
+        let drained = std::iter::from_fn(|| queues.pop()).collect::<Vec<String>>();
+
+        assert_eq!(
+            drained,
+            vec![
+                "high 0", "high 1", "high 2", "low 0", "high 3", "high 4", "high 5", "low 1",
+                "normal 0", "low 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn priority_queues_without_low_share_test() {
+        let mut queues = PriorityQueues::new(0);
+        queues.push(MessagePriority::Low, "low");
+        queues.push(MessagePriority::Normal, "normal");
+        queues.push(MessagePriority::High, "high");
+
+        let drained = std::iter::from_fn(|| queues.pop()).collect::<Vec<&str>>();
+
+        assert_eq!(drained, vec!["high", "normal", "low"]);
+    }
+
+    #[tokio::test]
+    async fn outbox_test() {
+        let outbox = Outbox::new(1, 0);
+        let relayed = Mutex::new(Vec::new());
+        let busy = outbox.acquire(MessagePriority::Low).await;
+
+        let relay = |priority: MessagePriority| {
+            let (outbox, relayed) = (&outbox, &relayed);
+            async move {
+                let _worker = outbox.acquire(priority).await;
+                relayed.lock().unwrap().push(priority);
+            }
+        };
+        let finish = async move {
+            tokio::task::yield_now().await;
+            drop(busy);
+        };
+        futures::join!(
+            relay(MessagePriority::Low),
+            relay(MessagePriority::High),
+            finish
+        );
+
+        assert_eq!(
+            relayed.into_inner().unwrap(),
+            vec![MessagePriority::High, MessagePriority::Low]
+        );
+    }
+}
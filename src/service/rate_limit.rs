@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::Mutex,
+    time::{sleep_until, Instant},
+};
+
+/// Spaces sends out evenly to at most `per_second` messages a second.
+/// Callers wait their turn in the order they arrived, which makes the limiter
+/// the send queue of whatever goes through it.
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn per_second(per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_second.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until the next slot is free and takes it.
+    pub async fn acquire(&self) {
+        let mut next_slot = self.next_slot.lock().await;
+        sleep_until(*next_slot).await;
+        *next_slot = Instant::now().max(*next_slot) + self.interval;
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::RateLimiter;
+
+    #[tokio::test]
+    async fn acquire_test() {
+        let limiter = RateLimiter::per_second(50);
+        let started = Instant::now();
+
+        limiter.acquire().await;
+        let first = started.elapsed();
+        limiter.acquire().await;
+        limiter.acquire().await;
+
+        assert!(first < Duration::from_millis(20));
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,350 @@
+use mail_parser::{Message, MessageParser, MessagePart, MimeHeaders};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportKind {
+    HardBounce,
+    SoftBounce,
+    Complaint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeliveryReport {
+    pub kind: ReportKind,
+    pub recipient: String,
+    pub message_id: Option<String>,
+    pub diagnostic: String,
+    /// Addresses the report itself was delivered to, which carry the VERP
+    /// token when the original message used a per-message return path.
+    pub addressed_to: Vec<String>,
+}
+
+#[derive(Default)]
+struct OriginalMessage {
+    message_id: Option<String>,
+    recipient: Option<String>,
+}
+
+type FieldGroup = Vec<(String, String)>;
+
+/// Parses an inbound RFC 3464 delivery status notification or RFC 5965 (ARF)
+/// complaint report into one report per affected recipient. Messages that are
+/// neither, and recipients that were delivered successfully, yield nothing.
+pub fn parse_report(raw: &[u8]) -> Vec<DeliveryReport> {
+    let message = match MessageParser::default().parse(raw) {
+        Some(message) => message,
+        None => return Vec::new(),
+    };
+
+    let addressed_to = addressed_to(&message);
+    let mut delivery_status = None;
+    let mut feedback_report = None;
+    let mut original = OriginalMessage::default();
+    for part in &message.parts {
+        match content_type(part).as_deref() {
+            Some("message/delivery-status") | Some("message/global-delivery-status") => {
+                delivery_status = part.text_contents();
+            }
+            Some("message/feedback-report") => {
+                feedback_report = part.text_contents();
+            }
+            Some("message/rfc822") | Some("message/global") => {
+                if let Some(nested) = part.message() {
+                    original = original_message(nested);
+                }
+            }
+            Some("text/rfc822-headers") | Some("message/rfc822-headers") => {
+                if let Some(nested) = part
+                    .text_contents()
+                    .and_then(|headers| MessageParser::default().parse(headers.as_bytes()))
+                {
+                    original = original_message(&nested);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(fields) = feedback_report {
+        return parse_feedback_report(fields, original, addressed_to)
+            .into_iter()
+            .collect();
+    }
+    match delivery_status {
+        Some(fields) => parse_delivery_status(fields, original, addressed_to),
+        None => Vec::new(),
+    }
+}
+
+fn content_type(part: &MessagePart) -> Option<String> {
+    let content_type = part.content_type()?;
+    Some(
+        format!(
+            "{}/{}",
+            content_type.ctype(),
+            content_type.subtype().unwrap_or_default()
+        )
+        .to_ascii_lowercase(),
+    )
+}
+
+fn addressed_to(message: &Message) -> Vec<String> {
+    let mut addresses = ["Delivered-To", "X-Original-To"]
+        .into_iter()
+        .filter_map(|name| message.header_raw(name))
+        .map(field_address)
+        .collect::<Vec<String>>();
+    if let Some(to) = message.to() {
+        addresses.extend(
+            to.iter()
+                .filter_map(|addr| addr.address())
+                .map(|address| address.to_string()),
+        );
+    }
+
+    addresses
+}
+
+fn original_message(message: &Message) -> OriginalMessage {
+    OriginalMessage {
+        message_id: message.message_id().map(|id| format!("<{}>", id)),
+        recipient: message
+            .to()
+            .and_then(|to| to.first())
+            .and_then(|addr| addr.address())
+            .map(|address| address.to_string()),
+    }
+}
+
+fn parse_feedback_report(
+    fields: &str,
+    original: OriginalMessage,
+    addressed_to: Vec<String>,
+) -> Option<DeliveryReport> {
+    let fields = parse_field_groups(fields).into_iter().flatten().collect();
+    let recipient = field(&fields, "original-rcpt-to")
+        .map(field_address)
+        .or(original.recipient)?;
+
+    Some(DeliveryReport {
+        kind: ReportKind::Complaint,
+        recipient,
+        message_id: original.message_id,
+        diagnostic: field(&fields, "feedback-type")
+            .unwrap_or("abuse")
+            .to_string(),
+        addressed_to,
+    })
+}
+
+fn parse_delivery_status(
+    fields: &str,
+    original: OriginalMessage,
+    addressed_to: Vec<String>,
+) -> Vec<DeliveryReport> {
+    // The first group holds the per-message fields, every following group
+    // describes a single recipient.
+    parse_field_groups(fields)
+        .into_iter()
+        .skip(1)
+        .filter_map(|recipient_fields| {
+            let recipient = field(&recipient_fields, "final-recipient")
+                .or_else(|| field(&recipient_fields, "original-recipient"))
+                .map(field_address)?;
+            let action = field(&recipient_fields, "action")?.to_ascii_lowercase();
+            let status = field(&recipient_fields, "status").unwrap_or_default();
+
+            let kind = match action.as_str() {
+                "failed" if !status.starts_with('4') => ReportKind::HardBounce,
+                "failed" | "delayed" => ReportKind::SoftBounce,
+                _ => return None,
+            };
+            let diagnostic = match field(&recipient_fields, "diagnostic-code") {
+                Some(diagnostic) => format!("{} {}", status, field_value(diagnostic)),
+                None => status.to_string(),
+            };
+
+            Some(DeliveryReport {
+                kind,
+                recipient,
+                message_id: original.message_id.clone(),
+                diagnostic: diagnostic.trim().to_string(),
+                addressed_to: addressed_to.clone(),
+            })
+        })
+        .collect()
+}
+
+fn parse_field_groups(fields: &str) -> Vec<FieldGroup> {
+    let mut groups = Vec::new();
+    let mut group: FieldGroup = Vec::new();
+
+    for line in fields.lines() {
+        if line.trim().is_empty() {
+            if !group.is_empty() {
+                groups.push(std::mem::take(&mut group));
+            }
+        } else if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = group.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            group.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    if !group.is_empty() {
+        groups.push(group);
+    }
+
+    groups
+}
+
+fn field<'a>(fields: &'a FieldGroup, name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field_name, _)| field_name == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Strips the `rfc822;`/`smtp;` type prefix used by DSN and ARF fields.
+fn field_value(value: &str) -> &str {
+    match value.split_once(';') {
+        Some((_, value)) => value.trim(),
+        None => value.trim(),
+    }
+}
+
+fn field_address(value: &str) -> String {
+    field_value(value)
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{parse_report, DeliveryReport, ReportKind};
+
+    const HARD_BOUNCE: &str = "Delivered-To: bounce+token@example.com\r
+From: MAILER-DAEMON@mx.example.com\r
+To: service@example.com\r
+Subject: Undelivered Mail Returned to Sender\r
+MIME-Version: 1.0\r
+Content-Type: multipart/report; report-type=delivery-status; boundary=\"dsn\"\r
+\r
+--dsn\r
+Content-Type: text/plain\r
+\r
+Your message could not be delivered.\r
+--dsn\r
+Content-Type: message/delivery-status\r
+\r
+Reporting-MTA: dns; mx.example.com\r
+Arrival-Date: Sun, 18 Oct 2026 10:00:00 +0000\r
+\r
+Final-Recipient: rfc822; gone@example.org\r
+Original-Recipient: rfc822;gone@example.org\r
+Action: failed\r
+Status: 5.1.1\r
+Diagnostic-Code: smtp; 550 5.1.1 <gone@example.org>:\r
+ Recipient address rejected: User unknown\r
+\r
+Final-Recipient: rfc822; later@example.org\r
+Action: delayed\r
+Status: 4.4.1\r
+\r
+Final-Recipient: rfc822; fine@example.org\r
+Action: delivered\r
+Status: 2.0.0\r
+--dsn\r
+Content-Type: text/rfc822-headers\r
+\r
+Message-ID: <original-id@example.com>\r
+From: service@example.com\r
+To: gone@example.org\r
+Subject: hello\r
+--dsn--\r
+";
+
+    const COMPLAINT: &str = "From: feedback@isp.example.net\r
+To: abuse@example.com\r
+Subject: FW: hello\r
+MIME-Version: 1.0\r
+Content-Type: multipart/report; report-type=feedback-report; boundary=\"arf\"\r
+\r
+--arf\r
+Content-Type: text/plain\r
+\r
+This is an email abuse report.\r
+--arf\r
+Content-Type: message/feedback-report\r
+\r
+Feedback-Type: abuse\r
+User-Agent: ExampleFBL/1.0\r
+Version: 1\r
+--arf\r
+Content-Type: message/rfc822\r
+\r
+Message-ID: <complained-id@example.com>\r
+From: service@example.com\r
+To: angry@example.net\r
+Subject: hello\r
+\r
+this is a test\r
+--arf--\r
+";
+
+    #[test]
+    fn parse_delivery_status_test() {
+        let reports = parse_report(HARD_BOUNCE.as_bytes());
+        let addressed_to = vec![
+            "bounce+token@example.com".to_string(),
+            "service@example.com".to_string(),
+        ];
+
+        assert_eq!(
+            reports,
+            vec![
+                DeliveryReport {
+                    kind: ReportKind::HardBounce,
+                    recipient: "gone@example.org".to_string(),
+                    message_id: Some("<original-id@example.com>".to_string()),
+                    diagnostic: "5.1.1 550 5.1.1 <gone@example.org>: Recipient address rejected: User unknown".to_string(),
+                    addressed_to: addressed_to.clone(),
+                },
+                DeliveryReport {
+                    kind: ReportKind::SoftBounce,
+                    recipient: "later@example.org".to_string(),
+                    message_id: Some("<original-id@example.com>".to_string()),
+                    diagnostic: "4.4.1".to_string(),
+                    addressed_to,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_feedback_report_test() {
+        let reports = parse_report(COMPLAINT.as_bytes());
+
+        assert_eq!(
+            reports,
+            vec![DeliveryReport {
+                kind: ReportKind::Complaint,
+                recipient: "angry@example.net".to_string(),
+                message_id: Some("<complained-id@example.com>".to_string()),
+                diagnostic: "abuse".to_string(),
+                addressed_to: vec!["abuse@example.com".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_regular_message_test() {
+        let reports = parse_report(
+            b"From: someone@example.com\r\nTo: service@example.com\r\nSubject: hi\r\n\r\nhello\r\n",
+        );
+
+        assert!(reports.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+use sqlx::types::time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+use time::Duration;
+use time_tz::{timezones, OffsetDateTimeExt, PrimitiveDateTimeExt, Tz};
+
+/// Time of day written as `HH:MM` on a 24 hour clock.
+pub fn parse_time_of_day(value: &str) -> Option<Time> {
+    let (hour, minute) = value.trim().split_once(':')?;
+    if !(1..=2).contains(&hour.len()) || minute.len() != 2 {
+        return None;
+    }
+
+    Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()
+}
+
+/// IANA timezone such as `Asia/Kuala_Lumpur`.
+pub fn parse_timezone(name: &str) -> Option<&'static Tz> {
+    timezones::get_by_name(name.trim())
+}
+
+/// Instant the wall clock in `tz` shows `time` on `date`. A time skipped by
+/// a daylight saving change is moved an hour later, and a time that occurs
+/// twice resolves to its first occurrence.
+fn local_instant(date: Date, time: Time, tz: &Tz) -> OffsetDateTime {
+    let local = PrimitiveDateTime::new(date, time);
+    local
+        .assume_timezone(tz)
+        .take_first()
+        .or_else(|| {
+            (local + Duration::hours(1))
+                .assume_timezone(tz)
+                .take_first()
+        })
+        .unwrap_or_else(|| local.assume_utc())
+}
+
+/// First instant at or after `after` when the wall clock in `tz` shows
+/// `time`.
+pub fn next_local_time(after: OffsetDateTime, time: Time, tz: &Tz) -> OffsetDateTime {
+    let mut date = after.to_timezone(tz).date();
+    loop {
+        let instant = local_instant(date, time, tz);
+        match date.next_day() {
+            Some(next_date) if instant < after => date = next_date,
+            _ => return instant,
+        }
+    }
+}
+
+/// Daily window of local time during which marketing email is held back,
+/// wrapping past midnight when it ends before it starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuietHours {
+    start: Time,
+    end: Time,
+}
+
+impl QuietHours {
+    /// Window from `start` until `end`, both written as `HH:MM`. An empty
+    /// window is rejected.
+    pub fn parse(start: &str, end: &str) -> Option<Self> {
+        let start = parse_time_of_day(start)?;
+        let end = parse_time_of_day(end)?;
+        if start == end {
+            return None;
+        }
+
+        Some(Self { start, end })
+    }
+
+    pub fn contains(&self, time: Time) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    /// `at` itself, or the end of the quiet hours when `at` falls within
+    /// them in `tz`.
+    pub fn defer(&self, at: OffsetDateTime, tz: &Tz) -> OffsetDateTime {
+        if self.contains(at.to_timezone(tz).time()) {
+            next_local_time(at, self.end, tz)
+        } else {
+            at
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use sqlx::types::time::{Date, OffsetDateTime, Time};
+    use time::Month;
+    use time_tz::timezones::db;
+
+    use super::{next_local_time, parse_time_of_day, parse_timezone, QuietHours};
+
+    fn utc(month: u8, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2026, Month::try_from(month).unwrap(), day)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn parse_time_of_day_test() {
+        assert_eq!(parse_time_of_day("09:00"), Time::from_hms(9, 0, 0).ok());
+        assert_eq!(parse_time_of_day(" 9:30 "), Time::from_hms(9, 30, 0).ok());
+        assert_eq!(parse_time_of_day("23:59"), Time::from_hms(23, 59, 0).ok());
+        assert_eq!(parse_time_of_day("24:00"), None);
+        assert_eq!(parse_time_of_day("09:60"), None);
+        assert_eq!(parse_time_of_day("0900"), None);
+        assert_eq!(parse_time_of_day("09:0"), None);
+        assert_eq!(parse_time_of_day(""), None);
+    }
+
+    #[test]
+    fn parse_timezone_test() {
+        assert_eq!(
+            parse_timezone("Asia/Kuala_Lumpur"),
+            Some(db::asia::KUALA_LUMPUR)
+        );
+        assert_eq!(parse_timezone("Mars/Olympus_Mons"), None);
+    }
+
+    #[test]
+    fn next_local_time_test() {
+        let nine = Time::from_hms(9, 0, 0).unwrap();
+
+        // 09:00 UTC is already evening in Kuala Lumpur, so the next 09:00
+        // there is the following morning.
+        assert_eq!(
+            next_local_time(utc(10, 18, 9, 0), nine, db::asia::KUALA_LUMPUR),
+            utc(10, 19, 1, 0)
+        );
+        assert_eq!(
+            next_local_time(utc(10, 18, 9, 0), nine, db::america::NEW_YORK),
+            utc(10, 18, 13, 0)
+        );
+        assert_eq!(
+            next_local_time(utc(10, 18, 9, 0), nine, db::UTC),
+            utc(10, 18, 9, 0)
+        );
+
+        // 02:30 does not exist in Berlin on the night clocks go forward.
+        assert_eq!(
+            next_local_time(
+                utc(3, 28, 12, 0),
+                Time::from_hms(2, 30, 0).unwrap(),
+                db::europe::BERLIN
+            ),
+            utc(3, 29, 1, 30)
+        );
+    }
+
+    #[test]
+    fn quiet_hours_test() {
+        let quiet_hours = QuietHours::parse("21:00", "08:00").unwrap();
+
+        assert!(quiet_hours.contains(Time::from_hms(23, 0, 0).unwrap()));
+        assert!(quiet_hours.contains(Time::from_hms(3, 0, 0).unwrap()));
+        assert!(!quiet_hours.contains(Time::from_hms(8, 0, 0).unwrap()));
+        assert!(!quiet_hours.contains(Time::from_hms(12, 0, 0).unwrap()));
+        assert_eq!(QuietHours::parse("08:00", "08:00"), None);
+        assert_eq!(QuietHours::parse("08:00", "late"), None);
+
+        // 17:00 UTC is 01:00 in Kuala Lumpur, deferred until 08:00 there.
+        assert_eq!(
+            quiet_hours.defer(utc(10, 18, 17, 0), db::asia::KUALA_LUMPUR),
+            utc(10, 19, 0, 0)
+        );
+        assert_eq!(
+            quiet_hours.defer(utc(10, 18, 17, 0), db::europe::LONDON),
+            utc(10, 18, 17, 0)
+        );
+    }
+}
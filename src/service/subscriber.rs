@@ -1,14 +1,25 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use async_trait::async_trait;
-use madtofan_microservice_common::{
-    email::{subscribers_response::Subscriber, SubscribersResponse},
-    errors::{ServiceError, ServiceResult},
-};
+use futures::{stream::BoxStream, StreamExt};
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
 use mockall::automock;
 use tracing::log::{error, info};
 
-use crate::repository::{group::DynGroupRepositoryTrait, subcriber::DynSubscriberRepositoryTrait};
+use crate::{
+    proto::email::{
+        bulk_subscribers_response::Outcome, import_subscribers_response::RejectedRow,
+        subscribers_response::Subscriber, BulkSubscribersResponse, ExportFormat,
+        ImportSubscribersResponse, SubscriberOutcome, SubscribersResponse,
+    },
+    repository::{
+        group::{DynGroupRepositoryTrait, GroupEntity},
+        subcriber::{DynSubscriberRepositoryTrait, NewSubscriber, SubscriberEntity},
+    },
+    service::validation::DynEmailValidator,
+};
+
+const IMPORT_BATCH_SIZE: usize = 1000;
 
 #[automock]
 #[async_trait]
@@ -20,11 +31,33 @@ pub trait SubscriberServiceTrait {
         limit: Option<i64>,
     ) -> ServiceResult<SubscribersResponse>;
     async fn add_subscriber(&self, email: String, group_name: String) -> ServiceResult<()>;
+    async fn add_subscribers(
+        &self,
+        emails: Vec<String>,
+        group_name: String,
+    ) -> ServiceResult<BulkSubscribersResponse>;
+    async fn import_subscribers(
+        &self,
+        group_name: String,
+        csv: Vec<u8>,
+    ) -> ServiceResult<ImportSubscribersResponse>;
+    async fn export_subscribers(
+        &self,
+        group_name: String,
+        segment: serde_json::Value,
+        format: ExportFormat,
+        columns: Vec<String>,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<String>>>;
     async fn remove_subscriber_from_group(
         &self,
         email: String,
         group_name: String,
     ) -> ServiceResult<()>;
+    async fn remove_subscribers(
+        &self,
+        emails: Vec<String>,
+        group_name: String,
+    ) -> ServiceResult<BulkSubscribersResponse>;
 }
 
 pub type DynSubscriberServiceTrait = Arc<dyn SubscriberServiceTrait + Sync + Send>;
@@ -32,16 +65,172 @@ pub type DynSubscriberServiceTrait = Arc<dyn SubscriberServiceTrait + Sync + Sen
 pub struct SubscriberService {
     subscriber_repository: DynSubscriberRepositoryTrait,
     group_repository: DynGroupRepositoryTrait,
+    validator: DynEmailValidator,
+}
+
+struct ImportRow {
+    line: i64,
+    subscriber: NewSubscriber,
+}
+
+#[derive(Default)]
+struct ParsedImport {
+    rows: Vec<ImportRow>,
+    duplicates: Vec<i64>,
+    rejected: Vec<RejectedRow>,
 }
 
 impl SubscriberService {
     pub fn new(
         subscriber_repository: DynSubscriberRepositoryTrait,
         group_repository: DynGroupRepositoryTrait,
+        validator: DynEmailValidator,
     ) -> Self {
         Self {
             subscriber_repository,
             group_repository,
+            validator,
+        }
+    }
+
+    async fn get_existing_group(&self, group_name: &str) -> ServiceResult<GroupEntity> {
+        match self.group_repository.get_group(group_name).await? {
+            Some(group) => Ok(group),
+            None => {
+                error!("group {:?} does not exists", group_name);
+                Err(ServiceError::ObjectConflict(String::from(
+                    "group name does not exist",
+                )))
+            }
+        }
+    }
+
+    fn parse_import_csv(&self, csv: &[u8]) -> ServiceResult<ParsedImport> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(csv);
+
+        let headers = reader
+            .headers()
+            .map_err(|_| ServiceError::BadRequest(String::from("csv header is unreadable")))?
+            .clone();
+        let email_column = headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case("email"))
+            .ok_or_else(|| {
+                ServiceError::BadRequest(String::from("csv header must include an email column"))
+            })?;
+
+        let mut parsed = ParsedImport::default();
+        let mut seen_emails = HashSet::new();
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    parsed.rejected.push(RejectedRow {
+                        line: err.position().map_or(0, |position| position.line() as i64),
+                        reason: String::from("row could not be parsed"),
+                    });
+                    continue;
+                }
+            };
+            let line = record
+                .position()
+                .map_or(0, |position| position.line() as i64);
+
+            let email = match self
+                .validator
+                .validate(record.get(email_column).unwrap_or_default())
+            {
+                Ok(email) => email,
+                Err(err) => {
+                    parsed.rejected.push(RejectedRow {
+                        line,
+                        reason: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if !seen_emails.insert(email.clone()) {
+                parsed.duplicates.push(line);
+                continue;
+            }
+
+            let attributes = headers
+                .iter()
+                .zip(record.iter())
+                .enumerate()
+                .filter(|(column, (header, value))| {
+                    *column != email_column && !header.is_empty() && !value.is_empty()
+                })
+                .map(|(_, (header, value))| (header.to_string(), serde_json::Value::from(value)))
+                .collect::<serde_json::Map<String, serde_json::Value>>();
+
+            parsed.rows.push(ImportRow {
+                line,
+                subscriber: NewSubscriber {
+                    email,
+                    attributes: serde_json::Value::Object(attributes),
+                },
+            });
+        }
+
+        Ok(parsed)
+    }
+
+    fn csv_row<T: AsRef<[u8]>>(fields: impl IntoIterator<Item = T>) -> ServiceResult<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(fields).map_err(|_| {
+            ServiceError::InternalServerErrorWithContext(String::from("failed to write csv row"))
+        })?;
+        let row = writer.into_inner().map_err(|_| {
+            ServiceError::InternalServerErrorWithContext(String::from("failed to write csv row"))
+        })?;
+
+        Ok(String::from_utf8_lossy(&row).into_owned())
+    }
+
+    fn export_row(
+        subscriber: &SubscriberEntity,
+        format: ExportFormat,
+        columns: &[String],
+    ) -> ServiceResult<String> {
+        match format {
+            // Strings are written as they are, any other value as its JSON.
+            ExportFormat::Csv => Self::csv_row(
+                std::iter::once(subscriber.email.clone()).chain(columns.iter().map(|column| {
+                    match subscriber.attributes.get(column) {
+                        None | Some(serde_json::Value::Null) => String::new(),
+                        Some(serde_json::Value::String(value)) => value.clone(),
+                        Some(value) => value.to_string(),
+                    }
+                })),
+            ),
+            // Without columns every attribute is exported.
+            ExportFormat::Ndjson => {
+                let attributes = if columns.is_empty() {
+                    subscriber.attributes.clone()
+                } else {
+                    serde_json::Value::Object(
+                        columns
+                            .iter()
+                            .filter_map(|column| {
+                                subscriber
+                                    .attributes
+                                    .get(column)
+                                    .map(|value| (column.clone(), value.clone()))
+                            })
+                            .collect(),
+                    )
+                };
+                let row = serde_json::json!({
+                    "email": subscriber.email,
+                    "attributes": attributes,
+                    "created_at": subscriber.created_at.unix_timestamp(),
+                });
+                Ok(format!("{}\n", row))
+            }
         }
     }
 }
@@ -87,6 +276,7 @@ impl SubscriberServiceTrait for SubscriberService {
     }
 
     async fn add_subscriber(&self, email: String, group_name: String) -> ServiceResult<()> {
+        let email = self.validator.validate(&email)?;
         let existing_group = self.group_repository.get_group(&group_name).await?;
 
         match existing_group {
@@ -108,6 +298,148 @@ impl SubscriberServiceTrait for SubscriberService {
         }
     }
 
+    async fn add_subscribers(
+        &self,
+        emails: Vec<String>,
+        group_name: String,
+    ) -> ServiceResult<BulkSubscribersResponse> {
+        let group = self.get_existing_group(&group_name).await?;
+
+        let normalized_emails = emails
+            .iter()
+            .map(|email| self.validator.validate(email).ok())
+            .collect::<Vec<Option<String>>>();
+        let mut subscribers = Vec::new();
+        let mut seen_emails = HashSet::new();
+        for email in normalized_emails.iter().flatten() {
+            if seen_emails.insert(email) {
+                subscribers.push(NewSubscriber {
+                    email: email.clone(),
+                    attributes: serde_json::json!({}),
+                });
+            }
+        }
+
+        info!(
+            "adding {} subscribers into group {:?}",
+            subscribers.len(),
+            &group_name
+        );
+        let mut added_emails = self
+            .subscriber_repository
+            .add_subscribers(&subscribers, &group)
+            .await?
+            .into_iter()
+            .map(|subscriber| subscriber.email)
+            .collect::<HashSet<String>>();
+
+        info!("successfully added subscribers into group");
+        Ok(BulkSubscribersResponse {
+            outcomes: emails
+                .into_iter()
+                .zip(normalized_emails)
+                .map(|(email, normalized_email)| {
+                    let status = match normalized_email {
+                        None => SubscriberOutcome::Invalid,
+                        Some(normalized_email) if added_emails.remove(&normalized_email) => {
+                            SubscriberOutcome::Added
+                        }
+                        Some(_) => SubscriberOutcome::AlreadyPresent,
+                    };
+                    Outcome {
+                        email,
+                        status: status as i32,
+                    }
+                })
+                .collect::<Vec<Outcome>>(),
+        })
+    }
+
+    async fn import_subscribers(
+        &self,
+        group_name: String,
+        csv: Vec<u8>,
+    ) -> ServiceResult<ImportSubscribersResponse> {
+        let group = self.get_existing_group(&group_name).await?;
+
+        let ParsedImport {
+            rows,
+            mut duplicates,
+            rejected,
+        } = self.parse_import_csv(&csv)?;
+        info!(
+            "importing {} subscribers into group {:?}, {} rows rejected",
+            rows.len(),
+            &group_name,
+            rejected.len()
+        );
+
+        let mut inserted = 0;
+        for batch in rows.chunks(IMPORT_BATCH_SIZE) {
+            let subscribers = batch
+                .iter()
+                .map(|row| row.subscriber.clone())
+                .collect::<Vec<NewSubscriber>>();
+            let inserted_emails = self
+                .subscriber_repository
+                .add_subscribers(&subscribers, &group)
+                .await?
+                .into_iter()
+                .map(|subscriber| subscriber.email)
+                .collect::<HashSet<String>>();
+
+            inserted += inserted_emails.len() as i64;
+            duplicates.extend(
+                batch
+                    .iter()
+                    .filter(|row| !inserted_emails.contains(&row.subscriber.email))
+                    .map(|row| row.line),
+            );
+        }
+
+        duplicates.sort_unstable();
+
+        info!("successfully imported subscribers into group");
+        Ok(ImportSubscribersResponse {
+            inserted,
+            duplicates,
+            rejected,
+        })
+    }
+
+    async fn export_subscribers(
+        &self,
+        group_name: String,
+        segment: serde_json::Value,
+        format: ExportFormat,
+        columns: Vec<String>,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<String>>> {
+        let group = self.get_existing_group(&group_name).await?;
+        if !segment.is_object() {
+            return Err(ServiceError::BadRequest(String::from(
+                "segment must be an object of attributes",
+            )));
+        }
+
+        info!("exporting subscribers from group {:?}", &group_name);
+        let header = match format {
+            ExportFormat::Csv => Some(Self::csv_row(
+                std::iter::once("email").chain(columns.iter().map(String::as_str)),
+            )),
+            ExportFormat::Ndjson => None,
+        };
+        let rows = self
+            .subscriber_repository
+            .stream_subs_by_group(&group, segment)
+            .map(move |subscriber| {
+                subscriber
+                    .map_err(ServiceError::from)
+                    .and_then(|subscriber| Self::export_row(&subscriber, format, &columns))
+            });
+
+        Ok(futures::stream::iter(header).chain(rows).boxed())
+    }
+
     async fn remove_subscriber_from_group(
         &self,
         email: String,
@@ -133,4 +465,61 @@ impl SubscriberServiceTrait for SubscriberService {
             }
         }
     }
+
+    async fn remove_subscribers(
+        &self,
+        emails: Vec<String>,
+        group_name: String,
+    ) -> ServiceResult<BulkSubscribersResponse> {
+        let group = self.get_existing_group(&group_name).await?;
+
+        // Addresses stored before validation was tightened may not pass it
+        // any more, so they are matched as stored rather than validated.
+        let normalized_emails = emails
+            .iter()
+            .map(|email| email.trim().to_lowercase())
+            .map(|email| (!email.is_empty()).then_some(email))
+            .collect::<Vec<Option<String>>>();
+        let emails_to_remove = normalized_emails
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>();
+
+        info!(
+            "removing {} subscribers from group {:?}",
+            emails_to_remove.len(),
+            &group_name
+        );
+        let mut removed_emails = self
+            .subscriber_repository
+            .remove_subscribers_from_group(&emails_to_remove, &group)
+            .await?
+            .into_iter()
+            .map(|subscriber| subscriber.email.to_lowercase())
+            .collect::<HashSet<String>>();
+
+        info!("successfully removed subscribers from group");
+        Ok(BulkSubscribersResponse {
+            outcomes: emails
+                .into_iter()
+                .zip(normalized_emails)
+                .map(|(email, normalized_email)| {
+                    let status = match normalized_email {
+                        None => SubscriberOutcome::Invalid,
+                        Some(normalized_email) if removed_emails.remove(&normalized_email) => {
+                            SubscriberOutcome::Removed
+                        }
+                        Some(_) => SubscriberOutcome::NotFound,
+                    };
+                    Outcome {
+                        email,
+                        status: status as i32,
+                    }
+                })
+                .collect::<Vec<Outcome>>(),
+        })
+    }
 }
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use mockall::automock;
+use tracing::log::{error, info};
+
+use crate::{
+    proto::email::{TemplateResponse, TemplatesResponse},
+    repository::template::{DynTemplateRepositoryTrait, NewTemplate},
+    service::locale::normalize_locale,
+};
+
+#[automock]
+#[async_trait]
+pub trait TemplateServiceTrait {
+    async fn save_template(&self, template: NewTemplate) -> ServiceResult<TemplateResponse>;
+    async fn list_templates(&self, name: Option<String>) -> ServiceResult<TemplatesResponse>;
+    async fn remove_template(&self, name: String, locale: Option<String>) -> ServiceResult<()>;
+}
+
+pub type DynTemplateServiceTrait = Arc<dyn TemplateServiceTrait + Sync + Send>;
+
+pub struct TemplateService {
+    template_repository: DynTemplateRepositoryTrait,
+}
+
+impl TemplateService {
+    pub fn new(template_repository: DynTemplateRepositoryTrait) -> Self {
+        Self {
+            template_repository,
+        }
+    }
+
+    fn parse_locale(locale: &str) -> ServiceResult<String> {
+        normalize_locale(locale).ok_or_else(|| {
+            error!("template locale {:?} is invalid", locale);
+            ServiceError::BadRequest(String::from("template locale is invalid"))
+        })
+    }
+
+    fn validate_template(template: &NewTemplate) -> ServiceResult<()> {
+        if template.name.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "template name cannot be empty",
+            )));
+        }
+        if template.subject.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "template subject cannot be empty",
+            )));
+        }
+        if template.text_body.trim().is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "template text body cannot be empty",
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TemplateServiceTrait for TemplateService {
+    async fn save_template(&self, template: NewTemplate) -> ServiceResult<TemplateResponse> {
+        Self::validate_template(&template)?;
+        let template = NewTemplate {
+            locale: Self::parse_locale(&template.locale)?,
+            ..template
+        };
+
+        info!(
+            "saving the {:?} variant of template {:?}",
+            &template.locale, &template.name
+        );
+        let template = self.template_repository.save_template(&template).await?;
+
+        info!("template successfully saved");
+        Ok(template.into_template_response())
+    }
+
+    async fn list_templates(&self, name: Option<String>) -> ServiceResult<TemplatesResponse> {
+        let templates = match name {
+            Some(name) => {
+                self.template_repository
+                    .list_template_variants(&name)
+                    .await?
+            }
+            None => self.template_repository.list_templates().await?,
+        }
+        .into_iter()
+        .map(|template| template.into_template_response())
+        .collect::<Vec<TemplateResponse>>();
+
+        Ok(TemplatesResponse { templates })
+    }
+
+    async fn remove_template(&self, name: String, locale: Option<String>) -> ServiceResult<()> {
+        info!("removing template {:?}", &name);
+        let removed = match locale {
+            Some(locale) => self
+                .template_repository
+                .remove_template_variant(&name, &Self::parse_locale(&locale)?)
+                .await?
+                .is_some(),
+            None => !self
+                .template_repository
+                .remove_template(&name)
+                .await?
+                .is_empty(),
+        };
+
+        if !removed {
+            error!("template {:?} does not exist", &name);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "template does not exist",
+            )));
+        }
+
+        info!("template successfully removed");
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Builds signed open pixel and click redirect URLs pointing at the tracking
+/// endpoint, and checks the signatures when those URLs are hit. Signing keeps
+/// the click endpoint from being usable as an open redirect.
+#[derive(Clone)]
+pub struct Tracker {
+    base_url: String,
+    secret: Vec<u8>,
+}
+
+pub type DynTracker = Arc<Tracker>;
+
+impl Tracker {
+    pub fn new(base_url: &str, secret: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("hmac accepts keys of any size");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    fn sign(&self, payload: &str) -> String {
+        URL_SAFE_NO_PAD.encode(self.mac(payload).finalize().into_bytes())
+    }
+
+    fn verify(&self, payload: &str, signature: &str) -> bool {
+        match URL_SAFE_NO_PAD.decode(signature) {
+            Ok(signature) => self.mac(payload).verify_slice(&signature).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn open_url(&self, delivery_id: i64) -> String {
+        format!(
+            "{}/track/open/{}/{}",
+            self.base_url,
+            delivery_id,
+            self.sign(&format!("open:{}", delivery_id))
+        )
+    }
+
+    pub fn click_url(&self, delivery_id: i64, url: &str) -> String {
+        format!(
+            "{}/track/click/{}/{}?u={}",
+            self.base_url,
+            delivery_id,
+            self.sign(&format!("click:{}:{}", delivery_id, url)),
+            URL_SAFE_NO_PAD.encode(url)
+        )
+    }
+
+    pub fn unsubscribe_url(&self, delivery_id: i64) -> String {
+        format!(
+            "{}/unsubscribe/{}/{}",
+            self.base_url,
+            delivery_id,
+            self.sign(&format!("unsubscribe:{}", delivery_id))
+        )
+    }
+
+    pub fn verify_open(&self, delivery_id: i64, signature: &str) -> bool {
+        self.verify(&format!("open:{}", delivery_id), signature)
+    }
+
+    pub fn verify_unsubscribe(&self, delivery_id: i64, signature: &str) -> bool {
+        self.verify(&format!("unsubscribe:{}", delivery_id), signature)
+    }
+
+    /// Returns the original link target when the click URL is authentic.
+    pub fn verify_click(
+        &self,
+        delivery_id: i64,
+        encoded_url: &str,
+        signature: &str,
+    ) -> Option<String> {
+        let url = String::from_utf8(URL_SAFE_NO_PAD.decode(encoded_url).ok()?).ok()?;
+        self.verify(&format!("click:{}:{}", delivery_id, url), signature)
+            .then_some(url)
+    }
+
+    /// Rewrites every absolute http(s) link of an HTML body to its click
+    /// redirect, and appends the open pixel right before `</body>`.
+    pub fn instrument_html(&self, html: &str, delivery_id: i64) -> String {
+        let lowercase = html.to_ascii_lowercase();
+        let mut instrumented = String::with_capacity(html.len());
+        let mut position = 0;
+
+        while let Some(found) = lowercase[position..].find("href=") {
+            let value_start = position + found + "href=".len();
+            let quote = match html[value_start..].chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => {
+                    instrumented.push_str(&html[position..value_start]);
+                    position = value_start;
+                    continue;
+                }
+            };
+            let url_start = value_start + 1;
+            let url_end = match html[url_start..].find(quote) {
+                Some(length) => url_start + length,
+                None => break,
+            };
+
+            instrumented.push_str(&html[position..url_start]);
+            let url = html[url_start..url_end].replace("&amp;", "&");
+            let scheme = url.to_ascii_lowercase();
+            if scheme.starts_with("http://") || scheme.starts_with("https://") {
+                instrumented.push_str(&self.click_url(delivery_id, &url).replace('&', "&amp;"));
+            } else {
+                instrumented.push_str(&html[url_start..url_end]);
+            }
+            position = url_end;
+        }
+        instrumented.push_str(&html[position..]);
+
+        let pixel = format!(
+            "<img src=\"{}\" width=\"1\" height=\"1\" alt=\"\" style=\"display:none\" />",
+            self.open_url(delivery_id)
+        );
+        match instrumented.to_ascii_lowercase().rfind("</body>") {
+            Some(body_end) => instrumented.insert_str(body_end, &pixel),
+            None => instrumented.push_str(&pixel),
+        }
+
+        instrumented
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Tracker;
+
+    #[test]
+    fn open_url_test() {
+        let tracker = Tracker::new("https://track.example.com/", "secret");
+
+        let url = tracker.open_url(42);
+        let signature = url.rsplit('/').next().unwrap();
+
+        assert!(url.starts_with("https://track.example.com/track/open/42/"));
+        assert!(tracker.verify_open(42, signature));
+        assert!(!tracker.verify_open(43, signature));
+        assert!(!Tracker::new("https://track.example.com", "other").verify_open(42, signature));
+    }
+
+    #[test]
+    fn unsubscribe_url_test() {
+        let tracker = Tracker::new("https://track.example.com", "secret");
+
+        let url = tracker.unsubscribe_url(42);
+        let signature = url.rsplit('/').next().unwrap();
+
+        assert!(url.starts_with("https://track.example.com/unsubscribe/42/"));
+        assert!(tracker.verify_unsubscribe(42, signature));
+        assert!(!tracker.verify_unsubscribe(43, signature));
+        assert!(!tracker.verify_open(42, signature));
+    }
+
+    #[test]
+    fn click_url_test() {
+        let tracker = Tracker::new("https://track.example.com", "secret");
+        let target = "https://example.com/page?a=1&b=2";
+
+        let url = tracker.click_url(7, target);
+        let (path, encoded_url) = url.split_once("?u=").unwrap();
+        let signature = path.rsplit('/').next().unwrap();
+
+        assert_eq!(
+            tracker.verify_click(7, encoded_url, signature),
+            Some(target.to_string())
+        );
+        assert_eq!(tracker.verify_click(8, encoded_url, signature), None);
+        assert_eq!(
+            tracker.verify_click(7, "aHR0cHM6Ly9ldmlsLmV4YW1wbGU", signature),
+            None
+        );
+    }
+
+    #[test]
+    fn instrument_html_test() {
+        let tracker = Tracker::new("https://track.example.com", "secret");
+        let html = "<html><BODY><a HREF=\"https://example.com/?a=1&amp;b=2\">link</a> \
+            <a href='mailto:me@example.com'>mail</a> <a href=#top>top</a></BODY></html>";
+
+        let instrumented = tracker.instrument_html(html, 1);
+
+        assert!(instrumented.contains(&format!(
+            "HREF=\"{}\"",
+            tracker
+                .click_url(1, "https://example.com/?a=1&b=2")
+                .replace('&', "&amp;")
+        )));
+        assert!(instrumented.contains("href='mailto:me@example.com'"));
+        assert!(instrumented.contains("href=#top"));
+        assert!(instrumented.ends_with(&format!(
+            "<img src=\"{}\" width=\"1\" height=\"1\" alt=\"\" style=\"display:none\" /></BODY></html>",
+            tracker.open_url(1)
+        )));
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use madtofan_microservice_common::errors::{ServiceError, ServiceResult};
+use mockall::automock;
+use tracing::log::{error, info};
+
+use crate::{
+    proto::email::CampaignEngagementResponse,
+    repository::{
+        campaign::DynCampaignRepositoryTrait,
+        delivery::DynDeliveryRepositoryTrait,
+        tracking::{DynTrackingRepositoryTrait, TrackingEventKind},
+        unsubscription::DynUnsubscriptionRepositoryTrait,
+    },
+    service::tracker::{DynTracker, Tracker},
+};
+
+#[automock]
+#[async_trait]
+pub trait TrackingServiceTrait {
+    async fn record_open(
+        &self,
+        delivery_id: i64,
+        signature: String,
+        user_agent: String,
+    ) -> ServiceResult<()>;
+    async fn record_click(
+        &self,
+        delivery_id: i64,
+        encoded_url: String,
+        signature: String,
+        user_agent: String,
+    ) -> ServiceResult<String>;
+    async fn get_campaign_engagement(
+        &self,
+        campaign_id: i64,
+    ) -> ServiceResult<CampaignEngagementResponse>;
+    async fn unsubscribe(&self, delivery_id: i64, signature: String) -> ServiceResult<i64>;
+}
+
+pub type DynTrackingServiceTrait = Arc<dyn TrackingServiceTrait + Sync + Send>;
+
+pub struct TrackingService {
+    tracking_repository: DynTrackingRepositoryTrait,
+    campaign_repository: DynCampaignRepositoryTrait,
+    delivery_repository: DynDeliveryRepositoryTrait,
+    unsubscription_repository: DynUnsubscriptionRepositoryTrait,
+    tracker: Option<DynTracker>,
+}
+
+impl TrackingService {
+    pub fn new(
+        tracking_repository: DynTrackingRepositoryTrait,
+        campaign_repository: DynCampaignRepositoryTrait,
+        delivery_repository: DynDeliveryRepositoryTrait,
+        unsubscription_repository: DynUnsubscriptionRepositoryTrait,
+        tracker: Option<DynTracker>,
+    ) -> Self {
+        Self {
+            tracking_repository,
+            campaign_repository,
+            delivery_repository,
+            unsubscription_repository,
+            tracker,
+        }
+    }
+
+    fn tracker(&self) -> ServiceResult<&Tracker> {
+        self.tracker
+            .as_deref()
+            .ok_or_else(|| ServiceError::BadRequest(String::from("tracking is disabled")))
+    }
+
+    fn rate(count: i64, sent: i64) -> f64 {
+        if sent == 0 {
+            return 0.0;
+        }
+
+        count as f64 / sent as f64
+    }
+}
+
+#[async_trait]
+impl TrackingServiceTrait for TrackingService {
+    async fn record_open(
+        &self,
+        delivery_id: i64,
+        signature: String,
+        user_agent: String,
+    ) -> ServiceResult<()> {
+        if !self.tracker()?.verify_open(delivery_id, &signature) {
+            error!("open signature for delivery {:?} is invalid", delivery_id);
+            return Err(ServiceError::BadRequest(String::from(
+                "tracking signature is invalid",
+            )));
+        }
+
+        self.tracking_repository
+            .add_event(delivery_id, TrackingEventKind::Open, None, &user_agent)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_click(
+        &self,
+        delivery_id: i64,
+        encoded_url: String,
+        signature: String,
+        user_agent: String,
+    ) -> ServiceResult<String> {
+        let url = match self
+            .tracker()?
+            .verify_click(delivery_id, &encoded_url, &signature)
+        {
+            Some(url) => url,
+            None => {
+                error!("click signature for delivery {:?} is invalid", delivery_id);
+                return Err(ServiceError::BadRequest(String::from(
+                    "tracking signature is invalid",
+                )));
+            }
+        };
+
+        // The reader still gets redirected when the event cannot be stored.
+        if let Err(err) = self
+            .tracking_repository
+            .add_event(
+                delivery_id,
+                TrackingEventKind::Click,
+                Some(url.clone()),
+                &user_agent,
+            )
+            .await
+        {
+            error!("failed to record click for {:?}: {:?}", delivery_id, err);
+        }
+
+        Ok(url)
+    }
+
+    async fn get_campaign_engagement(
+        &self,
+        campaign_id: i64,
+    ) -> ServiceResult<CampaignEngagementResponse> {
+        if self
+            .campaign_repository
+            .get_campaign(campaign_id)
+            .await?
+            .is_none()
+        {
+            error!("campaign {:?} does not exist", campaign_id);
+            return Err(ServiceError::ObjectConflict(String::from(
+                "campaign does not exist",
+            )));
+        }
+
+        let engagement = self
+            .tracking_repository
+            .get_campaign_engagement(campaign_id)
+            .await?;
+
+        Ok(CampaignEngagementResponse {
+            campaign_id,
+            sent: engagement.sent,
+            opened: engagement.opened,
+            clicked: engagement.clicked,
+            opens: engagement.opens,
+            clicks: engagement.clicks,
+            open_rate: Self::rate(engagement.opened, engagement.sent),
+            click_rate: Self::rate(engagement.clicked, engagement.sent),
+            capped: engagement.capped,
+        })
+    }
+
+    async fn unsubscribe(&self, delivery_id: i64, signature: String) -> ServiceResult<i64> {
+        if !self.tracker()?.verify_unsubscribe(delivery_id, &signature) {
+            error!(
+                "unsubscribe signature for delivery {:?} is invalid",
+                delivery_id
+            );
+            return Err(ServiceError::BadRequest(String::from(
+                "unsubscribe signature is invalid",
+            )));
+        }
+
+        let delivery = match self.delivery_repository.get_delivery(delivery_id).await? {
+            Some(delivery) => delivery,
+            None => {
+                error!("delivery {:?} does not exist", delivery_id);
+                return Err(ServiceError::ObjectConflict(String::from(
+                    "message does not exist",
+                )));
+            }
+        };
+        info!("unsubscribing the recipient of delivery {:?}", delivery_id);
+        let removed = self
+            .unsubscription_repository
+            .unsubscribe(&delivery.recipient, delivery.id, delivery.campaign_id)
+            .await?;
+
+        info!("recipient unsubscribed from {} groups", removed);
+        Ok(removed)
+    }
+}
@@ -0,0 +1,370 @@
+use std::{collections::HashSet, fmt, fs, sync::Arc};
+
+use anyhow::Context;
+use madtofan_microservice_common::errors::ServiceError;
+
+const MAX_ADDRESS_LENGTH: usize = 254;
+const MAX_LOCAL_PART_LENGTH: usize = 64;
+const MAX_DOMAIN_LENGTH: usize = 253;
+const MAX_LABEL_LENGTH: usize = 63;
+
+#[derive(Debug, PartialEq)]
+pub enum EmailValidationError {
+    Empty,
+    MissingAt,
+    EmptyLocalPart,
+    EmptyDomain,
+    AddressTooLong(usize),
+    LocalPartTooLong(usize),
+    DomainTooLong(usize),
+    LabelTooLong(String),
+    InvalidLocalPart(String),
+    InvalidDomain(String),
+    UnqualifiedDomain(String),
+    AddressLiteral,
+    DisposableDomain(String),
+}
+
+impl fmt::Display for EmailValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "email address is empty"),
+            Self::MissingAt => write!(f, "email address must contain a single '@'"),
+            Self::EmptyLocalPart => write!(f, "email address is missing the part before '@'"),
+            Self::EmptyDomain => write!(f, "email address is missing the domain"),
+            Self::AddressTooLong(length) => write!(
+                f,
+                "email address is {} characters long, the maximum is {}",
+                length, MAX_ADDRESS_LENGTH
+            ),
+            Self::LocalPartTooLong(length) => write!(
+                f,
+                "local part is {} characters long, the maximum is {}",
+                length, MAX_LOCAL_PART_LENGTH
+            ),
+            Self::DomainTooLong(length) => write!(
+                f,
+                "domain is {} characters long, the maximum is {}",
+                length, MAX_DOMAIN_LENGTH
+            ),
+            Self::LabelTooLong(label) => write!(
+                f,
+                "domain label {:?} is longer than {} characters",
+                label, MAX_LABEL_LENGTH
+            ),
+            Self::InvalidLocalPart(reason) => write!(f, "local part is invalid: {}", reason),
+            Self::InvalidDomain(reason) => write!(f, "domain is invalid: {}", reason),
+            Self::UnqualifiedDomain(domain) => {
+                write!(f, "domain {:?} is not fully qualified", domain)
+            }
+            Self::AddressLiteral => write!(f, "address literals are not accepted"),
+            Self::DisposableDomain(domain) => {
+                write!(f, "domain {:?} is a disposable email provider", domain)
+            }
+        }
+    }
+}
+
+impl From<EmailValidationError> for ServiceError {
+    fn from(err: EmailValidationError) -> Self {
+        ServiceError::BadRequest(err.to_string())
+    }
+}
+
+pub type DynEmailValidator = Arc<EmailValidator>;
+
+#[derive(Default)]
+pub struct EmailValidator {
+    disposable_domains: HashSet<String>,
+}
+
+impl EmailValidator {
+    pub fn new(disposable_domains: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            disposable_domains: disposable_domains
+                .into_iter()
+                .map(|domain| domain.trim().trim_end_matches('.').to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn from_file(path: Option<&str>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("could not read disposable domain list {:?}", path))?;
+
+        Ok(Self::new(contents.lines().map(|line| {
+            line.split('#').next().unwrap_or_default().to_string()
+        })))
+    }
+
+    pub fn validate(&self, email: &str) -> Result<String, EmailValidationError> {
+        let email = email.trim();
+        if email.is_empty() {
+            return Err(EmailValidationError::Empty);
+        }
+
+        let (local_part, domain) = email
+            .rsplit_once('@')
+            .ok_or(EmailValidationError::MissingAt)?;
+        if local_part.is_empty() {
+            return Err(EmailValidationError::EmptyLocalPart);
+        }
+        if domain.is_empty() {
+            return Err(EmailValidationError::EmptyDomain);
+        }
+
+        Self::validate_local_part(local_part)?;
+        let domain = Self::normalize_domain(domain)?;
+
+        let address = format!("{}@{}", local_part, domain);
+        if address.len() > MAX_ADDRESS_LENGTH {
+            return Err(EmailValidationError::AddressTooLong(address.len()));
+        }
+
+        if let Some(disposable_domain) = self.disposable_parent(&domain) {
+            return Err(EmailValidationError::DisposableDomain(
+                disposable_domain.to_string(),
+            ));
+        }
+
+        Ok(address)
+    }
+
+    fn validate_local_part(local_part: &str) -> Result<(), EmailValidationError> {
+        if local_part.len() > MAX_LOCAL_PART_LENGTH {
+            return Err(EmailValidationError::LocalPartTooLong(local_part.len()));
+        }
+        if !local_part.is_ascii() {
+            return Err(EmailValidationError::InvalidLocalPart(String::from(
+                "non-ASCII characters are not supported",
+            )));
+        }
+
+        if local_part.starts_with('"') {
+            return Self::validate_quoted_string(local_part);
+        }
+
+        if local_part.starts_with('.') || local_part.ends_with('.') {
+            return Err(EmailValidationError::InvalidLocalPart(String::from(
+                "it cannot start or end with '.'",
+            )));
+        }
+        if local_part.contains("..") {
+            return Err(EmailValidationError::InvalidLocalPart(String::from(
+                "it cannot contain consecutive '.'",
+            )));
+        }
+        match local_part
+            .chars()
+            .find(|c| !Self::is_atext(*c) && *c != '.')
+        {
+            Some(c) => Err(EmailValidationError::InvalidLocalPart(format!(
+                "character {:?} must be quoted",
+                c
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_quoted_string(local_part: &str) -> Result<(), EmailValidationError> {
+        if local_part.len() < 2 || !local_part.ends_with('"') {
+            return Err(EmailValidationError::InvalidLocalPart(String::from(
+                "quoted string is not terminated",
+            )));
+        }
+
+        let mut escaped = false;
+        for c in local_part[1..local_part.len() - 1].chars() {
+            match (escaped, c) {
+                (true, ' '..='~') => escaped = false,
+                (false, '\\') => escaped = true,
+                (false, '"') => {
+                    return Err(EmailValidationError::InvalidLocalPart(String::from(
+                        "quotes inside a quoted string must be escaped",
+                    )))
+                }
+                (false, ' '..='~') => {}
+                _ => {
+                    return Err(EmailValidationError::InvalidLocalPart(format!(
+                        "character {:?} is not allowed in a quoted string",
+                        c
+                    )))
+                }
+            }
+        }
+        if escaped {
+            return Err(EmailValidationError::InvalidLocalPart(String::from(
+                "quoted string ends with an escape",
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn normalize_domain(domain: &str) -> Result<String, EmailValidationError> {
+        if domain.starts_with('[') {
+            return Err(EmailValidationError::AddressLiteral);
+        }
+
+        let ascii_domain = idna::domain_to_ascii(domain).map_err(|_| {
+            EmailValidationError::InvalidDomain(format!(
+                "{:?} cannot be converted to punycode",
+                domain
+            ))
+        })?;
+        if ascii_domain.len() > MAX_DOMAIN_LENGTH {
+            return Err(EmailValidationError::DomainTooLong(ascii_domain.len()));
+        }
+
+        let labels = ascii_domain.split('.').collect::<Vec<&str>>();
+        if labels.len() < 2 {
+            return Err(EmailValidationError::UnqualifiedDomain(ascii_domain));
+        }
+        for label in labels.iter() {
+            if label.is_empty() {
+                return Err(EmailValidationError::InvalidDomain(String::from(
+                    "it cannot contain empty labels",
+                )));
+            }
+            if label.len() > MAX_LABEL_LENGTH {
+                return Err(EmailValidationError::LabelTooLong(label.to_string()));
+            }
+            if label.starts_with('-') || label.ends_with('-') {
+                return Err(EmailValidationError::InvalidDomain(format!(
+                    "label {:?} cannot start or end with '-'",
+                    label
+                )));
+            }
+            if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return Err(EmailValidationError::InvalidDomain(format!(
+                    "label {:?} contains characters other than letters, digits and '-'",
+                    label
+                )));
+            }
+        }
+        if labels
+            .last()
+            .is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(EmailValidationError::InvalidDomain(String::from(
+                "top level domain cannot be numeric",
+            )));
+        }
+
+        Ok(ascii_domain)
+    }
+
+    fn disposable_parent<'a>(&self, domain: &'a str) -> Option<&'a str> {
+        let mut candidate = domain;
+        loop {
+            if self.disposable_domains.contains(candidate) {
+                return Some(candidate);
+            }
+            candidate = candidate.split_once('.')?.1;
+        }
+    }
+
+    fn is_atext(c: char) -> bool {
+        c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{EmailValidationError, EmailValidator};
+
+    #[test]
+    fn accepts_and_normalizes_addresses() {
+        let validator = EmailValidator::default();
+
+        assert_eq!(
+            validator.validate(" Jane.Doe+news@Example.COM "),
+            Ok(String::from("Jane.Doe+news@example.com"))
+        );
+        assert_eq!(
+            validator.validate("\"john doe\"@example.com"),
+            Ok(String::from("\"john doe\"@example.com"))
+        );
+        assert_eq!(
+            validator.validate("user@bücher.example"),
+            Ok(String::from("user@xn--bcher-kva.example"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_syntax() {
+        let validator = EmailValidator::default();
+
+        assert_eq!(validator.validate(""), Err(EmailValidationError::Empty));
+        assert_eq!(
+            validator.validate("no-at-sign"),
+            Err(EmailValidationError::MissingAt)
+        );
+        assert_eq!(
+            validator.validate("@example.com"),
+            Err(EmailValidationError::EmptyLocalPart)
+        );
+        assert!(matches!(
+            validator.validate("john..doe@example.com"),
+            Err(EmailValidationError::InvalidLocalPart(_))
+        ));
+        assert!(matches!(
+            validator.validate("john doe@example.com"),
+            Err(EmailValidationError::InvalidLocalPart(_))
+        ));
+        assert!(matches!(
+            validator.validate("john@-example.com"),
+            Err(EmailValidationError::InvalidDomain(_))
+        ));
+        assert!(matches!(
+            validator.validate("john@localhost"),
+            Err(EmailValidationError::UnqualifiedDomain(_))
+        ));
+        assert_eq!(
+            validator.validate("john@[127.0.0.1]"),
+            Err(EmailValidationError::AddressLiteral)
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_addresses() {
+        let validator = EmailValidator::default();
+
+        assert_eq!(
+            validator.validate(&format!("{}@example.com", "a".repeat(65))),
+            Err(EmailValidationError::LocalPartTooLong(65))
+        );
+        assert!(matches!(
+            validator.validate(&format!("john@{}.com", "a".repeat(64))),
+            Err(EmailValidationError::LabelTooLong(_))
+        ));
+        assert!(matches!(
+            validator.validate(&format!("john@{}com", "a.".repeat(127))),
+            Err(EmailValidationError::DomainTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_disposable_domains() {
+        let validator = EmailValidator::new(vec![String::from("Mailinator.com")]);
+
+        assert_eq!(
+            validator.validate("john@mailinator.com"),
+            Err(EmailValidationError::DisposableDomain(String::from(
+                "mailinator.com"
+            )))
+        );
+        assert_eq!(
+            validator.validate("john@eu.mailinator.com"),
+            Err(EmailValidationError::DisposableDomain(String::from(
+                "mailinator.com"
+            )))
+        );
+        assert!(validator.validate("john@notmailinator.com").is_ok());
+    }
+}
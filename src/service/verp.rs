@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+/// Builds and decodes per-message VERP envelope senders of the form
+/// `<prefix>+<token>@<domain>`, so a bounce arriving at the return path can
+/// be traced back to the exact delivery that caused it.
+#[derive(Clone, Debug)]
+pub struct Verp {
+    prefix: String,
+    domain: String,
+}
+
+pub type DynVerp = Arc<Verp>;
+
+impl Verp {
+    pub fn new(prefix: &str, domain: &str) -> Self {
+        Self {
+            prefix: prefix.to_ascii_lowercase(),
+            domain: domain.trim_start_matches('@').to_ascii_lowercase(),
+        }
+    }
+
+    pub fn generate_token() -> String {
+        Uuid::new_v4().simple().to_string()
+    }
+
+    pub fn encode(&self, token: &str) -> String {
+        format!("{}+{}@{}", self.prefix, token, self.domain)
+    }
+
+    /// Returns the token carried by a bounce address, or `None` when the
+    /// address was not generated by this encoder.
+    pub fn decode(&self, address: &str) -> Option<String> {
+        let address = address
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_ascii_lowercase();
+        let (local_part, domain) = address.rsplit_once('@')?;
+        if domain != self.domain {
+            return None;
+        }
+
+        let (prefix, token) = local_part.split_once('+')?;
+        if prefix != self.prefix || token.is_empty() {
+            return None;
+        }
+
+        Some(token.to_string())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Verp;
+
+    #[test]
+    fn encode_and_decode_test() {
+        let verp = Verp::new("bounce", "Mail.Example.com");
+        let token = Verp::generate_token();
+
+        let address = verp.encode(&token);
+
+        assert_eq!(address, format!("bounce+{}@mail.example.com", token));
+        assert_eq!(verp.decode(&address), Some(token.clone()));
+        assert_eq!(
+            verp.decode(&format!(
+                "<BOUNCE+{}@MAIL.EXAMPLE.COM>",
+                token.to_uppercase()
+            )),
+            Some(token)
+        );
+    }
+
+    #[test]
+    fn decode_foreign_address_test() {
+        let verp = Verp::new("bounce", "mail.example.com");
+
+        assert_eq!(verp.decode("bounce+token@other.example.com"), None);
+        assert_eq!(verp.decode("replies+token@mail.example.com"), None);
+        assert_eq!(verp.decode("bounce@mail.example.com"), None);
+        assert_eq!(verp.decode("bounce+@mail.example.com"), None);
+        assert_eq!(verp.decode("not an address"), None);
+    }
+}
@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use madtofan_microservice_common::errors::ServiceError;
+use tokio::fs;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::log::{error, info};
+
+use crate::service::bounce::DynBounceServiceTrait;
+
+const PROCESSED_DIRECTORY: &str = "processed";
+const FAILED_DIRECTORY: &str = "failed";
+
+/// Picks up `.eml` files dropped into a maildir-like directory by the inbound
+/// MTA and feeds them to the bounce service. Handled files are moved into
+/// `processed/` or `failed/` so they are never read twice, while files that hit
+/// a database error stay put and are retried on the next tick.
+pub struct BounceWatcher {
+    bounce_service: DynBounceServiceTrait,
+    directory: PathBuf,
+    poll_interval: Duration,
+}
+
+impl BounceWatcher {
+    pub fn new(
+        bounce_service: DynBounceServiceTrait,
+        directory: PathBuf,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            bounce_service,
+            directory,
+            poll_interval,
+        }
+    }
+
+    pub async fn run(self) {
+        info!(
+            "bounce watcher started on {:?}, polling every {:?}",
+            &self.directory, self.poll_interval
+        );
+        let mut ticker = interval(self.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if let Err(err) = self.process_directory().await {
+                error!("failed to scan the bounce directory: {:?}", err);
+            }
+        }
+    }
+
+    async fn process_directory(&self) -> std::io::Result<()> {
+        let mut entries = fs::read_dir(&self.directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_report = path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("eml"));
+            if is_report && entry.file_type().await?.is_file() {
+                if let Err(err) = self.process_file(&path).await {
+                    error!("failed to handle {:?}: {:?}", path, err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn process_file(&self, path: &Path) -> std::io::Result<()> {
+        let raw = fs::read(path).await?;
+        let target = match self.bounce_service.process_report(raw).await {
+            Ok(reports) => {
+                info!("processed {:?} with {} reports", path, reports.len());
+                PROCESSED_DIRECTORY
+            }
+            Err(
+                err @ (ServiceError::InternalServerError
+                | ServiceError::InternalServerErrorWithContext(_)),
+            ) => {
+                error!("failed to process {:?}, retrying later: {:?}", path, err);
+                return Ok(());
+            }
+            Err(err) => {
+                error!("failed to process {:?}: {:?}", path, err);
+                FAILED_DIRECTORY
+            }
+        };
+
+        let target = self.directory.join(target);
+        fs::create_dir_all(&target).await?;
+        fs::rename(path, target.join(path.file_name().unwrap_or_default())).await
+    }
+}
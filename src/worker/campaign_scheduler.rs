@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::log::{error, info};
+
+use crate::service::campaign::DynCampaignServiceTrait;
+
+pub struct CampaignScheduler {
+    campaign_service: DynCampaignServiceTrait,
+    poll_interval: Duration,
+}
+
+impl CampaignScheduler {
+    pub fn new(campaign_service: DynCampaignServiceTrait, poll_interval: Duration) -> Self {
+        Self {
+            campaign_service,
+            poll_interval,
+        }
+    }
+
+    pub async fn run(self) {
+        info!(
+            "campaign scheduler started, polling every {:?}",
+            self.poll_interval
+        );
+        let mut ticker = interval(self.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if let Err(err) = self.campaign_service.send_due_campaigns().await {
+                error!("failed to send due campaigns: {:?}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::log::{error, info};
+
+use crate::service::digest::DynDigestServiceTrait;
+
+pub struct DigestScheduler {
+    digest_service: DynDigestServiceTrait,
+    poll_interval: Duration,
+}
+
+impl DigestScheduler {
+    pub fn new(digest_service: DynDigestServiceTrait, poll_interval: Duration) -> Self {
+        Self {
+            digest_service,
+            poll_interval,
+        }
+    }
+
+    pub async fn run(self) {
+        info!(
+            "digest scheduler started, polling every {:?}",
+            self.poll_interval
+        );
+        let mut ticker = interval(self.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if let Err(err) = self.digest_service.send_due_digests().await {
+                error!("failed to send due digests: {:?}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod bounce_watcher;
+pub mod campaign_scheduler;
+pub mod digest_scheduler;